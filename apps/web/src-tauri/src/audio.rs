@@ -1,11 +1,12 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use std::io::Write;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread;
 use thiserror::Error;
 
-const TARGET_SAMPLE_RATE: u32 = 16000;
+pub const TARGET_SAMPLE_RATE: u32 = 16000;
 const TARGET_CHANNELS: u16 = 1;
 const FRAME_SIZE: usize = 4096;
 
@@ -23,6 +24,8 @@ pub enum AudioError {
     NotStarted,
     #[error("Audio already running")]
     AlreadyRunning,
+    #[error("Failed to read WAV file: {0}")]
+    WavError(String),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -42,7 +45,7 @@ impl SilenceLevel {
     }
 }
 
-fn compute_rms(samples: &[f32]) -> f32 {
+pub fn compute_rms(samples: &[f32]) -> f32 {
     if samples.is_empty() {
         return 0.0;
     }
@@ -75,6 +78,36 @@ fn resample_linear(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
     output
 }
 
+/// Reads a WAV file as mono samples at `TARGET_SAMPLE_RATE`, averaging channels.
+pub fn read_wav(path: &Path) -> Result<Vec<f32>, AudioError> {
+    let mut reader =
+        hound::WavReader::open(path).map_err(|e| AudioError::WavError(e.to_string()))?;
+    let spec = reader.spec();
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<Result<_, _>>()
+            .map_err(|e| AudioError::WavError(e.to_string()))?,
+        hound::SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()
+                .map_err(|e| AudioError::WavError(e.to_string()))?
+        }
+    };
+
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+
+    Ok(resample_linear(&mono, spec.sample_rate, TARGET_SAMPLE_RATE))
+}
+
 pub struct AudioCapture {
     is_recording: Arc<AtomicBool>,
     sample_rate: Arc<std::sync::RwLock<Option<u32>>>,
@@ -887,6 +920,35 @@ impl Default for AudioHandle {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_read_wav_downmixes_and_resamples() {
+        let path = std::env::temp_dir().join("stt_read_wav_stereo_48k.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for _ in 0..4800 {
+            writer.write_sample(i16::MAX / 2).unwrap();
+            writer.write_sample(0_i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let samples = read_wav(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(samples.len(), 1600);
+        assert!(samples.iter().all(|s| (s - 0.25).abs() < 0.001));
+    }
+
+    #[test]
+    fn test_read_wav_missing_file() {
+        let result = read_wav(Path::new("/nonexistent/clip.wav"));
+        assert!(matches!(result, Err(AudioError::WavError(_))));
+    }
+
     #[test]
     fn test_rms_calculation_silence() {
         let samples = vec![0.0, 0.0, 0.0, 0.0, 0.0];
@@ -0,0 +1,180 @@
+//! Runs the speech engine over a directory of WAV files with matching `.txt`
+//! reference transcripts and prints word/character error rates and real-time
+//! factor as JSON.
+//!
+//! Usage: stt-eval <dir> [--model <profile>] [--models-dir <dir>]
+//!                 [--prefs <config.json>] [--output <report.json>]
+
+use anyhow::{bail, Context, Result};
+use app_lib::audio::{read_wav, TARGET_SAMPLE_RATE};
+use app_lib::eval::{AggregateReport, EvalReport, FileReport};
+use app_lib::prefs::{ModelProfile, Preferences, Prefs};
+use app_lib::stt::SttEngine;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+struct Args {
+    dir: PathBuf,
+    model: Option<ModelProfile>,
+    models_dir: Option<PathBuf>,
+    prefs: Option<PathBuf>,
+    output: Option<PathBuf>,
+}
+
+fn usage() -> &'static str {
+    "usage: stt-eval <dir> [--model small.en|multilingual-small|multilingual-medium|<imported id>] \
+     [--models-dir <dir>] [--prefs <config.json>] [--output <report.json>]"
+}
+
+fn parse_args() -> Result<Args> {
+    let mut args = env::args().skip(1);
+    let mut dir = None;
+    let mut model = None;
+    let mut models_dir = None;
+    let mut prefs = None;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .with_context(|| format!("{} needs a value\n{}", flag, usage()))
+        };
+        match arg.as_str() {
+            "--model" => {
+                let name = value("--model")?;
+                model = Some(
+                    serde_json::from_value(serde_json::Value::String(name.clone()))
+                        .with_context(|| format!("Unknown model profile: {}", name))?,
+                );
+            }
+            "--models-dir" => models_dir = Some(PathBuf::from(value("--models-dir")?)),
+            "--prefs" => prefs = Some(PathBuf::from(value("--prefs")?)),
+            "--output" => output = Some(PathBuf::from(value("--output")?)),
+            "-h" | "--help" => {
+                println!("{}", usage());
+                std::process::exit(0);
+            }
+            _ if arg.starts_with("--") => bail!("Unknown option: {}\n{}", arg, usage()),
+            _ if dir.is_none() => dir = Some(PathBuf::from(arg)),
+            _ => bail!("Unexpected argument: {}\n{}", arg, usage()),
+        }
+    }
+
+    Ok(Args {
+        dir: dir.context(usage())?,
+        model,
+        models_dir,
+        prefs,
+        output,
+    })
+}
+
+/// WAV files in `dir` that have a `.txt` reference next to them, sorted by name.
+fn collect_pairs(dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut pairs = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let is_wav = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+        if !is_wav {
+            continue;
+        }
+
+        let reference = path.with_extension("txt");
+        if reference.exists() {
+            pairs.push((path, reference));
+        } else {
+            eprintln!("Skipping {}: no reference transcript", path.display());
+        }
+    }
+    pairs.sort();
+    Ok(pairs)
+}
+
+fn main() -> Result<()> {
+    let args = parse_args()?;
+
+    let mut prefs = match &args.prefs {
+        Some(path) => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str::<Preferences>(&content)
+                .with_context(|| format!("Invalid preferences in {}", path.display()))?
+        }
+        None => Preferences::default(),
+    };
+    if let Some(model) = args.model {
+        prefs.model_profile = model;
+    }
+
+    let models_dir = match args.models_dir {
+        Some(dir) => dir,
+        None => Prefs::get_models_dir()?,
+    };
+
+    let engine = SttEngine::new();
+    engine.load_model(prefs.model_profile.clone(), models_dir)?;
+
+    let pairs = collect_pairs(&args.dir)?;
+    if pairs.is_empty() {
+        bail!(
+            "No WAV files with reference transcripts in {}",
+            args.dir.display()
+        );
+    }
+
+    let mut files = Vec::with_capacity(pairs.len());
+    for (wav, reference) in pairs {
+        let audio = read_wav(&wav)?;
+        let reference_text = fs::read_to_string(&reference)
+            .with_context(|| format!("Failed to read {}", reference.display()))?;
+
+        let started = Instant::now();
+        let result = engine
+            .transcribe(&audio, &prefs)
+            .with_context(|| format!("Failed to transcribe {}", wav.display()))?;
+        let processing_seconds = started.elapsed().as_secs_f64();
+
+        let report = FileReport::new(
+            wav.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            reference_text.trim().to_string(),
+            result.text,
+            audio.len() as f64 / TARGET_SAMPLE_RATE as f64,
+            processing_seconds,
+        );
+        eprintln!(
+            "{}: WER {:.3}, CER {:.3}, RTF {:.3}",
+            report.file, report.wer, report.cer, report.rtf
+        );
+        files.push(report);
+    }
+
+    let aggregate = AggregateReport::from_files(&files);
+    eprintln!(
+        "Total ({} files): WER {:.3}, CER {:.3}, RTF {:.3}",
+        aggregate.files, aggregate.wer, aggregate.cer, aggregate.rtf
+    );
+
+    let report = EvalReport {
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64,
+        model_profile: prefs.model_profile,
+        files,
+        aggregate,
+    };
+    let json = serde_json::to_string_pretty(&report)?;
+    match args.output {
+        Some(path) => {
+            fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,557 @@
+//! Headless transcription with the app's engine and preferences.
+//!
+//! Usage: stt [options] [FILE...]
+//!
+//! WAV files are read with their own sample rate and channel count; `.pcm` and
+//! `.raw` files, and stdin (no files or `-`), are raw 16 kHz mono PCM.
+
+use anyhow::{bail, Context, Result};
+use app_lib::audio::{read_wav, TARGET_SAMPLE_RATE};
+use app_lib::db::{Database, SessionMode};
+use app_lib::prefs::{ModelProfile, Preferences, Prefs};
+use app_lib::redaction::RedactionPrefs;
+use app_lib::session::SessionManager;
+use app_lib::stt::{
+    CancelToken, SttEngine, TextSegment, TranscriptionProgress, TranscriptionResult,
+};
+use serde::Serialize;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{env, fs};
+
+const USAGE: &str = "usage: stt [options] [FILE...]
+
+Transcribes WAV files, or raw 16 kHz mono PCM from stdin when no file (or `-`) is given.
+
+options:
+  --format <text|json|srt>   output format (default: text)
+  --pcm <s16le|f32le>        sample format of raw PCM input (default: s16le)
+  --chunk-seconds <n>        transcribe stdin every n seconds instead of at EOF
+  --model <profile>          small.en, multilingual-small, multilingual-medium
+                             or the id of an imported model
+  --models-dir <dir>         where model files live (default: the app's models dir)
+  --save                     record a session with the transcripts in the app database
+  -h, --help                 show this help";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Srt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PcmFormat {
+    S16Le,
+    F32Le,
+}
+
+impl PcmFormat {
+    fn bytes_per_sample(self) -> usize {
+        match self {
+            PcmFormat::S16Le => 2,
+            PcmFormat::F32Le => 4,
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Vec<f32> {
+        match self {
+            PcmFormat::S16Le => bytes
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+                .collect(),
+            PcmFormat::F32Le => bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        }
+    }
+}
+
+struct Args {
+    inputs: Vec<String>,
+    format: OutputFormat,
+    pcm: PcmFormat,
+    chunk_seconds: Option<u32>,
+    model: Option<ModelProfile>,
+    models_dir: Option<PathBuf>,
+    save: bool,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args> {
+    let mut args = args.into_iter();
+    let mut parsed = Args {
+        inputs: Vec::new(),
+        format: OutputFormat::Text,
+        pcm: PcmFormat::S16Le,
+        chunk_seconds: None,
+        model: None,
+        models_dir: None,
+        save: false,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .with_context(|| format!("{} needs a value\n\n{}", flag, USAGE))
+        };
+        match arg.as_str() {
+            "--format" => {
+                parsed.format = match value("--format")?.as_str() {
+                    "text" => OutputFormat::Text,
+                    "json" => OutputFormat::Json,
+                    "srt" => OutputFormat::Srt,
+                    other => bail!("Unknown output format: {}", other),
+                }
+            }
+            "--pcm" => {
+                parsed.pcm = match value("--pcm")?.as_str() {
+                    "s16le" => PcmFormat::S16Le,
+                    "f32le" => PcmFormat::F32Le,
+                    other => bail!("Unknown PCM format: {}", other),
+                }
+            }
+            "--chunk-seconds" => {
+                let seconds: u32 = value("--chunk-seconds")?
+                    .parse()
+                    .context("--chunk-seconds must be a whole number")?;
+                if seconds == 0 {
+                    bail!("--chunk-seconds must be greater than 0");
+                }
+                parsed.chunk_seconds = Some(seconds);
+            }
+            "--model" => {
+                let name = value("--model")?;
+                parsed.model = Some(
+                    serde_json::from_value(serde_json::Value::String(name.clone()))
+                        .with_context(|| format!("Unknown model profile: {}", name))?,
+                );
+            }
+            "--models-dir" => parsed.models_dir = Some(PathBuf::from(value("--models-dir")?)),
+            "--save" => parsed.save = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            "-" => parsed.inputs.push(arg),
+            _ if arg.starts_with('-') => bail!("Unknown option: {}\n\n{}", arg, USAGE),
+            _ => parsed.inputs.push(arg),
+        }
+    }
+
+    if parsed.inputs.is_empty() {
+        parsed.inputs.push("-".to_string());
+    }
+    if parsed.format == OutputFormat::Srt && parsed.inputs.len() > 1 {
+        bail!("SRT output needs a single input");
+    }
+    Ok(parsed)
+}
+
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    source: &'a str,
+    text: &'a str,
+    language: Option<&'a str>,
+    language_probability: Option<f32>,
+    duration_ms: u64,
+    segments: &'a [TextSegment],
+}
+
+/// Writes results as they are produced so piped input shows up chunk by chunk.
+struct Output {
+    format: OutputFormat,
+    multiple_sources: bool,
+    srt_index: usize,
+    srt_offset_ms: i64,
+}
+
+impl Output {
+    fn write(
+        &mut self,
+        source: &str,
+        samples: usize,
+        result: &TranscriptionResult,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        let duration_ms = samples as u64 * 1000 / TARGET_SAMPLE_RATE as u64;
+        match self.format {
+            OutputFormat::Text => {
+                if self.multiple_sources {
+                    writeln!(out, "{}: {}", source, result.text)?;
+                } else if !result.text.is_empty() {
+                    writeln!(out, "{}", result.text)?;
+                }
+            }
+            OutputFormat::Json => {
+                let json = JsonOutput {
+                    source,
+                    text: &result.text,
+                    language: result.language.as_deref(),
+                    language_probability: result.language_probability,
+                    duration_ms,
+                    segments: &result.segments,
+                };
+                writeln!(out, "{}", serde_json::to_string(&json)?)?;
+            }
+            OutputFormat::Srt => {
+                for segment in &result.segments {
+                    let text = segment.text.trim();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    self.srt_index += 1;
+                    writeln!(out, "{}", self.srt_index)?;
+                    writeln!(
+                        out,
+                        "{} --> {}",
+                        srt_timestamp(self.srt_offset_ms + segment.start_ms as i64),
+                        srt_timestamp(self.srt_offset_ms + segment.end_ms as i64)
+                    )?;
+                    writeln!(out, "{}\n", text)?;
+                }
+                self.srt_offset_ms += duration_ms as i64;
+            }
+        }
+        out.flush()
+    }
+}
+
+fn srt_timestamp(ms: i64) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Optional database session the transcripts are recorded into.
+struct Recorder {
+    sessions: SessionManager,
+    redaction: RedactionPrefs,
+}
+
+impl Recorder {
+    fn start(prefs: &Preferences) -> Result<Self> {
+        let db = Database::new().context("Failed to open the app database")?;
+        let sessions = SessionManager::new(Arc::new(db));
+        let session = sessions
+            .start_session(SessionMode::Record, prefs, Some("stt CLI".to_string()))
+            .map_err(anyhow::Error::msg)?;
+        eprintln!("Recording into session {}", session.id);
+        Ok(Self {
+            sessions,
+            redaction: prefs.redaction.clone(),
+        })
+    }
+
+    fn add(&self, result: &TranscriptionResult) -> Result<()> {
+        if result.text.is_empty() {
+            return Ok(());
+        }
+        let mut result = result.clone();
+        result.redact(&self.redaction);
+        self.sessions
+            .add_entry_with_details(&result.text, false, SessionMode::Record, (&result).into())
+            .map(|_| ())
+            .map_err(anyhow::Error::msg)
+    }
+
+    fn finish(self) -> Result<()> {
+        self.sessions
+            .end_session()
+            .map(|_| ())
+            .map_err(anyhow::Error::msg)
+    }
+}
+
+struct Transcriber<'a> {
+    engine: &'a SttEngine,
+    prefs: &'a Preferences,
+    output: Output,
+    recorder: Option<Recorder>,
+}
+
+impl Transcriber<'_> {
+    fn run(&mut self, source: &str, audio: &[f32]) -> Result<()> {
+        let timestamps = self.output.format != OutputFormat::Text;
+        let report_progress = |progress: TranscriptionProgress| {
+            if progress.total_windows > 1 {
+                eprint!(
+                    "\r{}: {}/{} windows",
+                    source, progress.completed_windows, progress.total_windows
+                );
+                if progress.completed_windows == progress.total_windows {
+                    eprintln!();
+                }
+            }
+        };
+        let result = if timestamps {
+            self.engine
+                .transcribe_with_timestamps(audio, self.prefs, report_progress)
+        } else {
+            self.engine.transcribe_with_progress(
+                audio,
+                self.prefs,
+                &CancelToken::new(),
+                report_progress,
+            )
+        }
+        .with_context(|| format!("Failed to transcribe {}", source))?;
+
+        self.output
+            .write(source, audio.len(), &result, &mut io::stdout().lock())?;
+        if let Some(recorder) = &self.recorder {
+            recorder.add(&result)?;
+        }
+        Ok(())
+    }
+
+    fn run_stdin(&mut self, pcm: PcmFormat, chunk_seconds: Option<u32>) -> Result<()> {
+        let mut stdin = io::stdin().lock();
+        let Some(chunk_seconds) = chunk_seconds else {
+            let mut bytes = Vec::new();
+            stdin.read_to_end(&mut bytes)?;
+            if bytes.is_empty() {
+                bail!("No audio on stdin\n\n{}", USAGE);
+            }
+            return self.run("stdin", &pcm.decode(&bytes));
+        };
+
+        let chunk_bytes =
+            chunk_seconds as usize * TARGET_SAMPLE_RATE as usize * pcm.bytes_per_sample();
+        let mut bytes = Vec::with_capacity(chunk_bytes);
+        let mut buf = [0u8; 8192];
+        loop {
+            let read = stdin.read(&mut buf)?;
+            if read > 0 {
+                bytes.extend_from_slice(&buf[..read]);
+            }
+            if bytes.len() >= chunk_bytes || (read == 0 && !bytes.is_empty()) {
+                let take = bytes.len().min(chunk_bytes);
+                let chunk: Vec<u8> = bytes.drain(..take).collect();
+                self.run("stdin", &pcm.decode(&chunk))?;
+            }
+            if read == 0 && bytes.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn read_input(path: &Path, pcm: PcmFormat) -> Result<Vec<f32>> {
+    let is_raw = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pcm") || ext.eq_ignore_ascii_case("raw"));
+    if is_raw {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        return Ok(pcm.decode(&bytes));
+    }
+    read_wav(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn main() -> Result<()> {
+    let args = parse_args(env::args().skip(1))?;
+
+    let mut prefs = Prefs::new()?.get();
+    if let Some(model) = args.model.clone() {
+        prefs.model_profile = model;
+    }
+    let models_dir = match args.models_dir.clone() {
+        Some(dir) => dir,
+        None => Prefs::get_models_dir()?,
+    };
+
+    let engine = SttEngine::new();
+    engine.load_model(prefs.model_profile.clone(), models_dir)?;
+
+    let recorder = if args.save {
+        Some(Recorder::start(&prefs)?)
+    } else {
+        None
+    };
+    let mut transcriber = Transcriber {
+        engine: &engine,
+        prefs: &prefs,
+        output: Output {
+            format: args.format,
+            multiple_sources: args.inputs.len() > 1,
+            srt_index: 0,
+            srt_offset_ms: 0,
+        },
+        recorder,
+    };
+
+    let mut outcome = Ok(());
+    for input in &args.inputs {
+        outcome = if input == "-" {
+            transcriber.run_stdin(args.pcm, args.chunk_seconds)
+        } else {
+            read_input(Path::new(input), args.pcm).and_then(|audio| transcriber.run(input, &audio))
+        };
+        if outcome.is_err() {
+            break;
+        }
+    }
+
+    if let Some(recorder) = transcriber.recorder.take() {
+        recorder.finish()?;
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Result<Args> {
+        parse_args(list.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args_defaults_to_stdin_text() {
+        let parsed = args(&[]).unwrap();
+        assert_eq!(parsed.inputs, vec!["-"]);
+        assert_eq!(parsed.format, OutputFormat::Text);
+        assert_eq!(parsed.pcm, PcmFormat::S16Le);
+        assert!(!parsed.save);
+    }
+
+    #[test]
+    fn test_parse_args_options() {
+        let parsed = args(&[
+            "--format",
+            "json",
+            "--pcm",
+            "f32le",
+            "--model",
+            "multilingual-small",
+            "--save",
+            "a.wav",
+            "b.wav",
+        ])
+        .unwrap();
+        assert_eq!(parsed.format, OutputFormat::Json);
+        assert_eq!(parsed.pcm, PcmFormat::F32Le);
+        assert_eq!(parsed.model, Some(ModelProfile::MultilingualSmall));
+        assert!(parsed.save);
+        assert_eq!(parsed.inputs, vec!["a.wav", "b.wav"]);
+    }
+
+    #[test]
+    fn test_parse_args_rejects_bad_input() {
+        assert!(args(&["--format", "xml"]).is_err());
+        assert!(args(&["--model", "huge"]).is_err());
+        assert!(args(&["--chunk-seconds", "0"]).is_err());
+        assert!(args(&["--format"]).is_err());
+        assert!(args(&["--verbose"]).is_err());
+        assert!(args(&["--format", "srt", "a.wav", "b.wav"]).is_err());
+    }
+
+    #[test]
+    fn test_pcm_decoding() {
+        let s16: Vec<u8> = [0i16, 16384, -32768]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        assert_eq!(PcmFormat::S16Le.decode(&s16), vec![0.0, 0.5, -1.0]);
+
+        let f32_bytes: Vec<u8> = [0.25f32, -0.75]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        assert_eq!(PcmFormat::F32Le.decode(&f32_bytes), vec![0.25, -0.75]);
+
+        // A trailing partial sample is ignored.
+        assert_eq!(PcmFormat::S16Le.decode(&[0, 0, 1]), vec![0.0]);
+    }
+
+    #[test]
+    fn test_srt_timestamp() {
+        assert_eq!(srt_timestamp(0), "00:00:00,000");
+        assert_eq!(srt_timestamp(3_723_045), "01:02:03,045");
+        assert_eq!(srt_timestamp(-5), "00:00:00,000");
+    }
+
+    fn result_with_segments(segments: &[(&str, i32, i32)]) -> TranscriptionResult {
+        TranscriptionResult {
+            text: segments.iter().map(|(t, _, _)| *t).collect(),
+            segments: segments
+                .iter()
+                .map(|(text, start_ms, end_ms)| TextSegment {
+                    text: text.to_string(),
+                    start_ms: *start_ms,
+                    end_ms: *end_ms,
+                    no_speech_prob: 0.0,
+                    speaker: None,
+                })
+                .collect(),
+            language: Some("en".to_string()),
+            language_probability: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            actions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_srt_output_continues_numbering_and_time_across_chunks() {
+        let mut output = Output {
+            format: OutputFormat::Srt,
+            multiple_sources: false,
+            srt_index: 0,
+            srt_offset_ms: 0,
+        };
+        let mut out = Vec::new();
+        let first = result_with_segments(&[(" Hello there.", 0, 1500), (" ", 1500, 1600)]);
+        let second = result_with_segments(&[(" General Kenobi.", 200, 1800)]);
+        output.write("stdin", 32_000, &first, &mut out).unwrap();
+        output.write("stdin", 32_000, &second, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1\n00:00:00,000 --> 00:00:01,500\nHello there.\n\n\
+             2\n00:00:02,200 --> 00:00:03,800\nGeneral Kenobi.\n\n"
+        );
+    }
+
+    #[test]
+    fn test_json_output_is_one_object_per_line() {
+        let mut output = Output {
+            format: OutputFormat::Json,
+            multiple_sources: true,
+            srt_index: 0,
+            srt_offset_ms: 0,
+        };
+        let mut out = Vec::new();
+        let result = result_with_segments(&[(" Hi.", 0, 500)]);
+        output.write("a.wav", 16_000, &result, &mut out).unwrap();
+
+        let line = String::from_utf8(out).unwrap();
+        let value: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(value["source"], "a.wav");
+        assert_eq!(value["duration_ms"], 1000);
+        assert_eq!(value["segments"][0]["end_ms"], 500);
+        assert_eq!(value["language"], "en");
+    }
+
+    #[test]
+    fn test_text_output_prefixes_source_for_multiple_inputs() {
+        let mut output = Output {
+            format: OutputFormat::Text,
+            multiple_sources: true,
+            srt_index: 0,
+            srt_offset_ms: 0,
+        };
+        let mut out = Vec::new();
+        let mut result = result_with_segments(&[]);
+        result.text = "ship it".to_string();
+        output.write("a.wav", 16_000, &result, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "a.wav: ship it\n");
+    }
+}
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use directories::ProjectDirs;
 use parking_lot::Mutex;
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, Row};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use thiserror::Error;
@@ -65,6 +65,8 @@ pub struct Session {
     pub model_profile: String,
     pub translated: bool,
     pub app_name: Option<String>,
+    /// Name of the app profile chosen when the session started.
+    pub profile: Option<String>,
     pub chars_count: i64,
     pub words_count: i64,
 }
@@ -78,6 +80,8 @@ pub struct SessionCreate {
     pub model_profile: String,
     pub translated: bool,
     pub app_name: Option<String>,
+    #[serde(default)]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,6 +93,17 @@ pub struct Entry {
     pub text: String,
     pub source: SessionMode,
     pub typed: bool,
+    pub language: Option<String>,
+    pub language_probability: Option<f32>,
+    pub speaker: Option<String>,
+    pub original_text: Option<String>,
+    pub translated_text: Option<String>,
+    pub unfiltered_text: Option<String>,
+    /// How many characters were typed before typing was cancelled; `None`
+    /// when the entry was typed in full or not at all.
+    pub typed_chars: Option<i64>,
+    /// Whether what was typed has since been removed by "scratch that".
+    pub retracted: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -100,6 +115,30 @@ pub struct EntryCreate {
     pub text: String,
     pub source: SessionMode,
     pub typed: bool,
+    pub language: Option<String>,
+    pub language_probability: Option<f32>,
+    pub speaker: Option<String>,
+    pub original_text: Option<String>,
+    pub translated_text: Option<String>,
+    pub unfiltered_text: Option<String>,
+    #[serde(default)]
+    pub typed_chars: Option<i64>,
+}
+
+/// Which stored text a search runs against.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryTextField {
+    Text,
+    Original,
+    Translation,
+}
+
+/// A user-chosen display name for a diarization label within one session.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionSpeaker {
+    pub speaker: String,
+    pub name: String,
 }
 
 pub struct Database {
@@ -215,6 +254,78 @@ impl Database {
             [],
         )?;
 
+        add_column_if_missing(&conn, "sessions", "profile", "TEXT")?;
+        add_column_if_missing(&conn, "entries", "language", "TEXT")?;
+        add_column_if_missing(&conn, "entries", "language_probability", "REAL")?;
+        add_column_if_missing(&conn, "entries", "speaker", "TEXT")?;
+        add_column_if_missing(&conn, "entries", "original_text", "TEXT")?;
+        add_column_if_missing(&conn, "entries", "translated_text", "TEXT")?;
+        add_column_if_missing(&conn, "entries", "unfiltered_text", "TEXT")?;
+        add_column_if_missing(&conn, "entries", "typed_chars", "INTEGER")?;
+        add_column_if_missing(&conn, "entries", "retracted", "INTEGER NOT NULL DEFAULT 0")?;
+
+        let translation_index_exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = 'entry_translation_search')",
+            [],
+            |row| row.get(0),
+        )?;
+
+        conn.execute(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS entry_translation_search USING fts5(
+                id,
+                original_text,
+                translated_text,
+                content='entries',
+                content_rowid='rowid'
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS entries_translation_ai AFTER INSERT ON entries BEGIN
+                INSERT INTO entry_translation_search(rowid, id, original_text, translated_text)
+                VALUES (new.rowid, new.id, new.original_text, new.translated_text);
+            END",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS entries_translation_ad AFTER DELETE ON entries BEGIN
+                INSERT INTO entry_translation_search(entry_translation_search, rowid, id, original_text, translated_text)
+                VALUES ('delete', old.rowid, old.id, old.original_text, old.translated_text);
+            END",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS entries_translation_au AFTER UPDATE ON entries BEGIN
+                INSERT INTO entry_translation_search(entry_translation_search, rowid, id, original_text, translated_text)
+                VALUES ('delete', old.rowid, old.id, old.original_text, old.translated_text);
+                INSERT INTO entry_translation_search(rowid, id, original_text, translated_text)
+                VALUES (new.rowid, new.id, new.original_text, new.translated_text);
+            END",
+            [],
+        )?;
+
+        // Entries from before the index existed must be indexed so later deletes
+        // and updates find their tokens.
+        if !translation_index_exists {
+            conn.execute(
+                "INSERT INTO entry_translation_search(entry_translation_search) VALUES ('rebuild')",
+                [],
+            )?;
+        }
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS session_speakers (
+                session_id TEXT NOT NULL REFERENCES sessions(id),
+                speaker TEXT NOT NULL,
+                name TEXT NOT NULL,
+                PRIMARY KEY (session_id, speaker)
+            )",
+            [],
+        )?;
+
         Ok(())
     }
 
@@ -222,8 +333,8 @@ impl Database {
         let conn = self.conn.lock();
 
         conn.execute(
-            "INSERT INTO sessions (id, mode, started_at, language, model_profile, translated, app_name)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO sessions (id, mode, started_at, language, model_profile, translated, app_name, profile)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             params![
                 session.id,
                 session.mode.to_string(),
@@ -232,6 +343,7 @@ impl Database {
                 session.model_profile,
                 session.translated as i32,
                 session.app_name,
+                session.profile,
             ],
         )?;
 
@@ -244,6 +356,7 @@ impl Database {
             model_profile: session.model_profile,
             translated: session.translated,
             app_name: session.app_name,
+            profile: session.profile,
             chars_count: 0,
             words_count: 0,
         })
@@ -252,27 +365,14 @@ impl Database {
     pub fn get_session(&self, id: &str) -> Result<Option<Session>, DbError> {
         let conn = self.conn.lock();
 
-        let mut stmt = conn.prepare(
-            "SELECT id, mode, started_at, ended_at, language, model_profile, translated, app_name, chars_count, words_count
-             FROM sessions WHERE id = ?1",
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SESSION_COLUMNS} FROM sessions WHERE id = ?1"
+        ))?;
 
         let mut rows = stmt.query(params![id])?;
 
         if let Some(row) = rows.next()? {
-            let mode_str: String = row.get(1)?;
-            Ok(Some(Session {
-                id: row.get(0)?,
-                mode: mode_str.parse().unwrap_or_default(),
-                started_at: row.get(2)?,
-                ended_at: row.get(3)?,
-                language: row.get(4)?,
-                model_profile: row.get(5)?,
-                translated: row.get::<_, i32>(6)? != 0,
-                app_name: row.get(7)?,
-                chars_count: row.get(8)?,
-                words_count: row.get(9)?,
-            }))
+            Ok(Some(session_from_row(row)?))
         } else {
             Ok(None)
         }
@@ -281,26 +381,11 @@ impl Database {
     pub fn get_all_sessions(&self) -> Result<Vec<Session>, DbError> {
         let conn = self.conn.lock();
 
-        let mut stmt = conn.prepare(
-            "SELECT id, mode, started_at, ended_at, language, model_profile, translated, app_name, chars_count, words_count
-             FROM sessions ORDER BY started_at DESC",
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SESSION_COLUMNS} FROM sessions ORDER BY started_at DESC"
+        ))?;
 
-        let rows = stmt.query_map([], |row| {
-            let mode_str: String = row.get(1)?;
-            Ok(Session {
-                id: row.get(0)?,
-                mode: mode_str.parse().unwrap_or_default(),
-                started_at: row.get(2)?,
-                ended_at: row.get(3)?,
-                language: row.get(4)?,
-                model_profile: row.get(5)?,
-                translated: row.get::<_, i32>(6)? != 0,
-                app_name: row.get(7)?,
-                chars_count: row.get(8)?,
-                words_count: row.get(9)?,
-            })
-        })?;
+        let rows = stmt.query_map([], session_from_row)?;
 
         let mut sessions = Vec::new();
         for session in rows {
@@ -343,10 +428,25 @@ impl Database {
         self.get_session(id)
     }
 
+    pub fn set_session_language(&self, id: &str, language: Option<&str>) -> Result<(), DbError> {
+        let conn = self.conn.lock();
+
+        conn.execute(
+            "UPDATE sessions SET language = ?1 WHERE id = ?2",
+            params![language, id],
+        )?;
+
+        Ok(())
+    }
+
     pub fn delete_session(&self, id: &str) -> Result<bool, DbError> {
         let conn = self.conn.lock();
 
         conn.execute("DELETE FROM entries WHERE session_id = ?1", params![id])?;
+        conn.execute(
+            "DELETE FROM session_speakers WHERE session_id = ?1",
+            params![id],
+        )?;
         let rows_affected = conn.execute("DELETE FROM sessions WHERE id = ?1", params![id])?;
 
         Ok(rows_affected > 0)
@@ -356,8 +456,8 @@ impl Database {
         let conn = self.conn.lock();
 
         conn.execute(
-            "INSERT INTO entries (id, session_id, started_at, ended_at, text, source, typed)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO entries (id, session_id, started_at, ended_at, text, source, typed, language, language_probability, speaker, original_text, translated_text, unfiltered_text, typed_chars)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
             params![
                 entry.id,
                 entry.session_id,
@@ -366,6 +466,13 @@ impl Database {
                 entry.text,
                 entry.source.to_string(),
                 entry.typed as i32,
+                entry.language,
+                entry.language_probability,
+                entry.speaker,
+                entry.original_text,
+                entry.translated_text,
+                entry.unfiltered_text,
+                entry.typed_chars,
             ],
         )?;
 
@@ -377,30 +484,28 @@ impl Database {
             text: entry.text,
             source: entry.source,
             typed: entry.typed,
+            language: entry.language,
+            language_probability: entry.language_probability,
+            speaker: entry.speaker,
+            original_text: entry.original_text,
+            translated_text: entry.translated_text,
+            unfiltered_text: entry.unfiltered_text,
+            typed_chars: entry.typed_chars,
+            retracted: false,
         })
     }
 
     pub fn get_entry(&self, id: &str) -> Result<Option<Entry>, DbError> {
         let conn = self.conn.lock();
 
-        let mut stmt = conn.prepare(
-            "SELECT id, session_id, started_at, ended_at, text, source, typed
-             FROM entries WHERE id = ?1",
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {ENTRY_COLUMNS} FROM entries e WHERE e.id = ?1"
+        ))?;
 
         let mut rows = stmt.query(params![id])?;
 
         if let Some(row) = rows.next()? {
-            let source_str: String = row.get(5)?;
-            Ok(Some(Entry {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                started_at: row.get(2)?,
-                ended_at: row.get(3)?,
-                text: row.get(4)?,
-                source: source_str.parse().unwrap_or_default(),
-                typed: row.get::<_, i32>(6)? != 0,
-            }))
+            Ok(Some(entry_from_row(row)?))
         } else {
             Ok(None)
         }
@@ -409,23 +514,12 @@ impl Database {
     pub fn get_entries_by_session(&self, session_id: &str) -> Result<Vec<Entry>, DbError> {
         let conn = self.conn.lock();
 
-        let mut stmt = conn.prepare(
-            "SELECT id, session_id, started_at, ended_at, text, source, typed
-             FROM entries WHERE session_id = ?1 ORDER BY started_at ASC",
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {ENTRY_COLUMNS} FROM entries e
+             WHERE e.session_id = ?1 ORDER BY e.started_at ASC"
+        ))?;
 
-        let rows = stmt.query_map(params![session_id], |row| {
-            let source_str: String = row.get(5)?;
-            Ok(Entry {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                started_at: row.get(2)?,
-                ended_at: row.get(3)?,
-                text: row.get(4)?,
-                source: source_str.parse().unwrap_or_default(),
-                typed: row.get::<_, i32>(6)? != 0,
-            })
-        })?;
+        let rows = stmt.query_map(params![session_id], entry_from_row)?;
 
         let mut entries = Vec::new();
         for entry in rows {
@@ -437,23 +531,11 @@ impl Database {
     pub fn get_all_entries(&self) -> Result<Vec<Entry>, DbError> {
         let conn = self.conn.lock();
 
-        let mut stmt = conn.prepare(
-            "SELECT id, session_id, started_at, ended_at, text, source, typed
-             FROM entries ORDER BY started_at DESC",
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {ENTRY_COLUMNS} FROM entries e ORDER BY e.started_at DESC"
+        ))?;
 
-        let rows = stmt.query_map([], |row| {
-            let source_str: String = row.get(5)?;
-            Ok(Entry {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                started_at: row.get(2)?,
-                ended_at: row.get(3)?,
-                text: row.get(4)?,
-                source: source_str.parse().unwrap_or_default(),
-                typed: row.get::<_, i32>(6)? != 0,
-            })
-        })?;
+        let rows = stmt.query_map([], entry_from_row)?;
 
         let mut entries = Vec::new();
         for entry in rows {
@@ -488,6 +570,46 @@ impl Database {
         self.get_entry(id)
     }
 
+    /// Attaches the source-language and/or translated text to an entry, e.g.
+    /// once a background translation pass finishes.
+    pub fn update_entry_texts(
+        &self,
+        id: &str,
+        original_text: Option<String>,
+        translated_text: Option<String>,
+    ) -> Result<Option<Entry>, DbError> {
+        let conn = self.conn.lock();
+
+        if let Some(original_text) = original_text {
+            conn.execute(
+                "UPDATE entries SET original_text = ?1 WHERE id = ?2",
+                params![original_text, id],
+            )?;
+        }
+
+        if let Some(translated_text) = translated_text {
+            conn.execute(
+                "UPDATE entries SET translated_text = ?1 WHERE id = ?2",
+                params![translated_text, id],
+            )?;
+        }
+
+        drop(conn);
+        self.get_entry(id)
+    }
+
+    /// Marks an entry's typed text as removed from the app it went to.
+    pub fn set_entry_retracted(&self, id: &str) -> Result<bool, DbError> {
+        let conn = self.conn.lock();
+
+        let rows_affected = conn.execute(
+            "UPDATE entries SET retracted = 1 WHERE id = ?1",
+            params![id],
+        )?;
+
+        Ok(rows_affected > 0)
+    }
+
     pub fn delete_entry(&self, id: &str) -> Result<bool, DbError> {
         let conn = self.conn.lock();
 
@@ -499,26 +621,15 @@ impl Database {
     pub fn search_entries(&self, query: &str) -> Result<Vec<Entry>, DbError> {
         let conn = self.conn.lock();
 
-        let mut stmt = conn.prepare(
-            "SELECT e.id, e.session_id, e.started_at, e.ended_at, e.text, e.source, e.typed
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {ENTRY_COLUMNS}
              FROM entries e
              JOIN entry_search es ON e.id = es.id
              WHERE entry_search MATCH ?1
-             ORDER BY e.started_at DESC",
-        )?;
+             ORDER BY e.started_at DESC"
+        ))?;
 
-        let rows = stmt.query_map(params![query], |row| {
-            let source_str: String = row.get(5)?;
-            Ok(Entry {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                started_at: row.get(2)?,
-                ended_at: row.get(3)?,
-                text: row.get(4)?,
-                source: source_str.parse().unwrap_or_default(),
-                typed: row.get::<_, i32>(6)? != 0,
-            })
-        })?;
+        let rows = stmt.query_map(params![query], entry_from_row)?;
 
         let mut entries = Vec::new();
         for entry in rows {
@@ -526,6 +637,147 @@ impl Database {
         }
         Ok(entries)
     }
+
+    pub fn search_entries_in(
+        &self,
+        query: &str,
+        field: EntryTextField,
+    ) -> Result<Vec<Entry>, DbError> {
+        let column = match field {
+            EntryTextField::Text => return self.search_entries(query),
+            EntryTextField::Original => "original_text",
+            EntryTextField::Translation => "translated_text",
+        };
+
+        let conn = self.conn.lock();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {ENTRY_COLUMNS}
+             FROM entries e
+             JOIN entry_translation_search ts ON e.rowid = ts.rowid
+             WHERE ts.{column} MATCH ?1
+             ORDER BY e.started_at DESC"
+        ))?;
+
+        let rows = stmt.query_map(params![query], entry_from_row)?;
+
+        let mut entries = Vec::new();
+        for entry in rows {
+            entries.push(entry?);
+        }
+        Ok(entries)
+    }
+
+    /// Names `speaker` for this session; an empty name removes the override.
+    pub fn rename_speaker(
+        &self,
+        session_id: &str,
+        speaker: &str,
+        name: &str,
+    ) -> Result<(), DbError> {
+        let conn = self.conn.lock();
+
+        let name = name.trim();
+        if name.is_empty() {
+            conn.execute(
+                "DELETE FROM session_speakers WHERE session_id = ?1 AND speaker = ?2",
+                params![session_id, speaker],
+            )?;
+        } else {
+            conn.execute(
+                "INSERT INTO session_speakers (session_id, speaker, name) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(session_id, speaker) DO UPDATE SET name = excluded.name",
+                params![session_id, speaker, name],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_session_speakers(&self, session_id: &str) -> Result<Vec<SessionSpeaker>, DbError> {
+        let conn = self.conn.lock();
+
+        let mut stmt = conn.prepare(
+            "SELECT speaker, name FROM session_speakers WHERE session_id = ?1 ORDER BY speaker",
+        )?;
+
+        let rows = stmt.query_map(params![session_id], |row| {
+            Ok(SessionSpeaker {
+                speaker: row.get(0)?,
+                name: row.get(1)?,
+            })
+        })?;
+
+        let mut speakers = Vec::new();
+        for speaker in rows {
+            speakers.push(speaker?);
+        }
+        Ok(speakers)
+    }
+}
+
+const SESSION_COLUMNS: &str = "id, mode, started_at, ended_at, language, model_profile, translated, app_name, chars_count, words_count, profile";
+
+fn session_from_row(row: &Row<'_>) -> rusqlite::Result<Session> {
+    let mode_str: String = row.get(1)?;
+    Ok(Session {
+        id: row.get(0)?,
+        mode: mode_str.parse().unwrap_or_default(),
+        started_at: row.get(2)?,
+        ended_at: row.get(3)?,
+        language: row.get(4)?,
+        model_profile: row.get(5)?,
+        translated: row.get::<_, i32>(6)? != 0,
+        app_name: row.get(7)?,
+        chars_count: row.get(8)?,
+        words_count: row.get(9)?,
+        profile: row.get(10)?,
+    })
+}
+
+const ENTRY_COLUMNS: &str = "e.id, e.session_id, e.started_at, e.ended_at, e.text, e.source, e.typed, e.language, e.language_probability, e.speaker, e.original_text, e.translated_text, e.unfiltered_text, e.typed_chars, e.retracted";
+
+fn entry_from_row(row: &Row<'_>) -> rusqlite::Result<Entry> {
+    let source_str: String = row.get(5)?;
+    Ok(Entry {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        started_at: row.get(2)?,
+        ended_at: row.get(3)?,
+        text: row.get(4)?,
+        source: source_str.parse().unwrap_or_default(),
+        typed: row.get::<_, i32>(6)? != 0,
+        language: row.get(7)?,
+        language_probability: row.get(8)?,
+        speaker: row.get(9)?,
+        original_text: row.get(10)?,
+        translated_text: row.get(11)?,
+        unfiltered_text: row.get(12)?,
+        typed_chars: row.get(13)?,
+        retracted: row.get::<_, i32>(14)? != 0,
+    })
+}
+
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), DbError> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+            [],
+        )?;
+    }
+
+    Ok(())
 }
 
 pub fn count_words(text: &str) -> i64 {
@@ -549,6 +801,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: Some("TestApp".to_string()),
+            profile: None,
         }
     }
 
@@ -561,6 +814,13 @@ mod tests {
             text: "Hello world test".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         }
     }
 
@@ -606,6 +866,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         db.create_session(session_create).unwrap();
 
@@ -617,6 +878,13 @@ mod tests {
             text: "Hello world test".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         let created = db.create_entry(entry_create.clone()).unwrap();
         assert_eq!(created.id, entry_create.id);
@@ -643,6 +911,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         db.create_session(session_create).unwrap();
 
@@ -654,6 +923,13 @@ mod tests {
             text: "First entry".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         let entry2 = EntryCreate {
             id: "entry-2".to_string(),
@@ -663,6 +939,13 @@ mod tests {
             text: "Second entry".to_string(),
             source: SessionMode::Hold,
             typed: true,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         db.create_entry(entry1).unwrap();
         db.create_entry(entry2).unwrap();
@@ -685,6 +968,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         let session2 = SessionCreate {
             id: "session-2".to_string(),
@@ -694,6 +978,7 @@ mod tests {
             model_profile: "small".to_string(),
             translated: true,
             app_name: None,
+            profile: None,
         };
 
         db.create_session(session1).unwrap();
@@ -731,6 +1016,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         db.create_session(session_create).unwrap();
 
@@ -742,6 +1028,13 @@ mod tests {
             text: "Test entry".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         db.create_entry(entry_create).unwrap();
 
@@ -767,6 +1060,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         db.create_session(session_create).unwrap();
 
@@ -779,6 +1073,13 @@ mod tests {
             text: "Original text".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         db.create_entry(entry_create).unwrap();
 
@@ -803,6 +1104,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         db.create_session(session_create).unwrap();
 
@@ -815,6 +1117,13 @@ mod tests {
             text: "Test entry".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         db.create_entry(entry_create).unwrap();
 
@@ -837,6 +1146,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         db.create_session(session_create).unwrap();
 
@@ -848,6 +1158,13 @@ mod tests {
             text: "Hello world this is a test".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         let entry2 = EntryCreate {
             id: "entry-2".to_string(),
@@ -857,6 +1174,13 @@ mod tests {
             text: "Another entry with different content".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
 
         db.create_entry(entry1).unwrap();
@@ -885,6 +1209,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         db.create_session(session_create).unwrap();
 
@@ -896,6 +1221,13 @@ mod tests {
             text: "The quick brown fox jumps".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         db.create_entry(entry).unwrap();
 
@@ -915,6 +1247,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         db.create_session(session_create).unwrap();
 
@@ -927,6 +1260,13 @@ mod tests {
             text: "First entry text".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         db.create_entry(entry_create).unwrap();
 
@@ -939,6 +1279,13 @@ mod tests {
             text: "Second entry text".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         db.create_entry(entry_create2).unwrap();
 
@@ -959,6 +1306,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         db.create_session(session_create).unwrap();
 
@@ -971,6 +1319,13 @@ mod tests {
             text: "Searchable content".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         db.create_entry(entry_create).unwrap();
 
@@ -993,6 +1348,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         db.create_session(session_create).unwrap();
 
@@ -1007,6 +1363,13 @@ mod tests {
             text: "Test".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         db.create_entry(entry_create).unwrap();
 
@@ -1027,6 +1390,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         db.create_session(session_create).unwrap();
 
@@ -1045,6 +1409,13 @@ mod tests {
             text: text.to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         db.create_entry(entry).unwrap();
 
@@ -1148,6 +1519,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
 
         let db = Database::new_in_memory().unwrap();
@@ -1172,6 +1544,13 @@ mod tests {
             text: "Typed content".to_string(),
             source: SessionMode::Hold,
             typed: true,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         };
         db.create_entry(entry.clone()).unwrap();
 
@@ -1189,6 +1568,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: Some("Safari".to_string()),
+            profile: None,
         };
 
         let db = Database::new_in_memory().unwrap();
@@ -1199,6 +1579,28 @@ mod tests {
         assert_eq!(retrieved.app_name, Some("Safari".to_string()));
     }
 
+    #[test]
+    fn test_profile_field() {
+        let session = SessionCreate {
+            id: "profile-test".to_string(),
+            mode: SessionMode::Toggle,
+            started_at: 1000,
+            language: None,
+            model_profile: "base".to_string(),
+            translated: false,
+            app_name: Some("Terminal".to_string()),
+            profile: Some("Shell".to_string()),
+        };
+
+        let db = Database::new_in_memory().unwrap();
+        db.create_session(session).unwrap();
+
+        let retrieved = db.get_session("profile-test").unwrap().unwrap();
+        assert_eq!(retrieved.profile, Some("Shell".to_string()));
+        let all = db.get_all_sessions().unwrap();
+        assert_eq!(all[0].profile, Some("Shell".to_string()));
+    }
+
     #[test]
     fn test_multiple_sessions_with_entries() {
         let db = Database::new_in_memory().unwrap();
@@ -1214,6 +1616,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
         let session2 = SessionCreate {
             id: session2_id.clone(),
@@ -1223,6 +1626,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: false,
             app_name: None,
+            profile: None,
         };
 
         db.create_session(session1).unwrap();
@@ -1236,6 +1640,13 @@ mod tests {
             text: "Session 1 entry".to_string(),
             source: SessionMode::Hold,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         })
         .unwrap();
 
@@ -1247,6 +1658,13 @@ mod tests {
             text: "Session 2 entry".to_string(),
             source: SessionMode::Toggle,
             typed: false,
+            language: None,
+            language_probability: None,
+            speaker: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            typed_chars: None,
         })
         .unwrap();
 
@@ -1269,6 +1687,7 @@ mod tests {
             model_profile: "base".to_string(),
             translated: true,
             app_name: None,
+            profile: None,
         };
 
         let db = Database::new_in_memory().unwrap();
@@ -1279,6 +1698,254 @@ mod tests {
         assert!(retrieved.translated);
     }
 
+    #[test]
+    fn test_entry_language_roundtrip() {
+        let db = Database::new_in_memory().unwrap();
+        let session_create = create_test_session();
+        db.create_session(session_create.clone()).unwrap();
+
+        let entry = EntryCreate {
+            language: Some("es".to_string()),
+            language_probability: Some(0.93),
+            ..create_test_entry(&session_create.id)
+        };
+        db.create_entry(entry.clone()).unwrap();
+
+        let retrieved = db.get_entry(&entry.id).unwrap().unwrap();
+        assert_eq!(retrieved.language, Some("es".to_string()));
+        assert_eq!(retrieved.language_probability, Some(0.93));
+
+        let searched = db.search_entries("hello").unwrap();
+        assert_eq!(searched[0].language, Some("es".to_string()));
+    }
+
+    #[test]
+    fn test_set_session_language() {
+        let db = Database::new_in_memory().unwrap();
+        let session = SessionCreate {
+            language: None,
+            ..create_test_session()
+        };
+        db.create_session(session.clone()).unwrap();
+
+        db.set_session_language(&session.id, Some("de")).unwrap();
+
+        let retrieved = db.get_session(&session.id).unwrap().unwrap();
+        assert_eq!(retrieved.language, Some("de".to_string()));
+    }
+
+    #[test]
+    fn test_migration_adds_language_columns_to_existing_entries_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE entries (
+                id TEXT PRIMARY KEY,
+                session_id TEXT NOT NULL,
+                started_at INTEGER NOT NULL,
+                ended_at INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                source TEXT NOT NULL,
+                typed INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO entries (id, session_id, started_at, ended_at, text, source, typed)
+             VALUES ('old-entry', 's', 1, 2, 'legacy text', 'hold', 1)",
+            [],
+        )
+        .unwrap();
+
+        let db = Database {
+            conn: Mutex::new(conn),
+        };
+        db.run_migrations().unwrap();
+
+        let entry = db.get_entry("old-entry").unwrap().unwrap();
+        assert_eq!(entry.text, "legacy text");
+        assert_eq!(entry.language, None);
+        assert_eq!(entry.language_probability, None);
+        assert_eq!(entry.speaker, None);
+        assert_eq!(entry.original_text, None);
+        assert_eq!(entry.translated_text, None);
+
+        db.update_entry_texts("old-entry", Some("texto".to_string()), None)
+            .unwrap();
+        assert!(db.delete_entry("old-entry").unwrap());
+    }
+
+    #[test]
+    fn test_entry_speaker_round_trip() {
+        let db = Database::new_in_memory().unwrap();
+        let session = create_test_session();
+        db.create_session(session.clone()).unwrap();
+
+        let entry = EntryCreate {
+            speaker: Some("S2".to_string()),
+            ..create_test_entry(&session.id)
+        };
+        db.create_entry(entry.clone()).unwrap();
+
+        let retrieved = db.get_entry(&entry.id).unwrap().unwrap();
+        assert_eq!(retrieved.speaker, Some("S2".to_string()));
+    }
+
+    #[test]
+    fn test_entry_unfiltered_text_round_trip() {
+        let db = Database::new_in_memory().unwrap();
+        let session = create_test_session();
+        db.create_session(session.clone()).unwrap();
+
+        let entry = EntryCreate {
+            unfiltered_text: Some("um Hello Hello world test".to_string()),
+            ..create_test_entry(&session.id)
+        };
+        db.create_entry(entry.clone()).unwrap();
+
+        let retrieved = db.get_entry(&entry.id).unwrap().unwrap();
+        assert_eq!(
+            retrieved.unfiltered_text.as_deref(),
+            Some("um Hello Hello world test")
+        );
+    }
+
+    #[test]
+    fn test_entry_typed_chars_round_trip() {
+        let db = Database::new_in_memory().unwrap();
+        let session = create_test_session();
+        db.create_session(session.clone()).unwrap();
+
+        let entry = EntryCreate {
+            typed_chars: Some(12),
+            ..create_test_entry(&session.id)
+        };
+        db.create_entry(entry.clone()).unwrap();
+
+        let retrieved = db.get_entry(&entry.id).unwrap().unwrap();
+        assert_eq!(retrieved.typed_chars, Some(12));
+    }
+
+    #[test]
+    fn test_set_entry_retracted() {
+        let db = Database::new_in_memory().unwrap();
+        let session = create_test_session();
+        db.create_session(session.clone()).unwrap();
+
+        let entry = db.create_entry(create_test_entry(&session.id)).unwrap();
+        assert!(!entry.retracted);
+
+        assert!(db.set_entry_retracted(&entry.id).unwrap());
+        let retrieved = db.get_entry(&entry.id).unwrap().unwrap();
+        assert!(retrieved.retracted);
+
+        assert!(!db.set_entry_retracted("missing").unwrap());
+    }
+
+    #[test]
+    fn test_rename_speaker() {
+        let db = Database::new_in_memory().unwrap();
+        let session = create_test_session();
+        db.create_session(session.clone()).unwrap();
+
+        db.rename_speaker(&session.id, "S1", "Alice").unwrap();
+        db.rename_speaker(&session.id, "S2", "Bob").unwrap();
+        db.rename_speaker(&session.id, "S1", " Alicia ").unwrap();
+
+        assert_eq!(
+            db.get_session_speakers(&session.id).unwrap(),
+            vec![
+                SessionSpeaker {
+                    speaker: "S1".to_string(),
+                    name: "Alicia".to_string(),
+                },
+                SessionSpeaker {
+                    speaker: "S2".to_string(),
+                    name: "Bob".to_string(),
+                },
+            ]
+        );
+        assert!(db.get_session_speakers("other").unwrap().is_empty());
+
+        db.rename_speaker(&session.id, "S2", "").unwrap();
+        let speakers = db.get_session_speakers(&session.id).unwrap();
+        assert_eq!(speakers.len(), 1);
+        assert_eq!(speakers[0].speaker, "S1");
+    }
+
+    #[test]
+    fn test_delete_session_removes_speaker_names() {
+        let db = Database::new_in_memory().unwrap();
+        let session = create_test_session();
+        db.create_session(session.clone()).unwrap();
+        db.rename_speaker(&session.id, "S1", "Alice").unwrap();
+
+        db.delete_session(&session.id).unwrap();
+        assert!(db.get_session_speakers(&session.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_original_and_translated_text_separately() {
+        let db = Database::new_in_memory().unwrap();
+        let session = create_test_session();
+        db.create_session(session.clone()).unwrap();
+
+        db.create_entry(EntryCreate {
+            id: "bilingual".to_string(),
+            text: "good morning everyone".to_string(),
+            original_text: Some("buenos días a todos".to_string()),
+            translated_text: Some("good morning everyone".to_string()),
+            ..create_test_entry(&session.id)
+        })
+        .unwrap();
+
+        let ids = |query: &str, field: EntryTextField| -> Vec<String> {
+            db.search_entries_in(query, field)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.id)
+                .collect()
+        };
+        assert_eq!(ids("buenos", EntryTextField::Original), vec!["bilingual"]);
+        assert!(ids("buenos", EntryTextField::Translation).is_empty());
+        assert!(ids("buenos", EntryTextField::Text).is_empty());
+        assert_eq!(
+            ids("morning", EntryTextField::Translation),
+            vec!["bilingual"]
+        );
+        assert!(ids("morning", EntryTextField::Original).is_empty());
+    }
+
+    #[test]
+    fn test_update_entry_texts_reindexes_translation() {
+        let db = Database::new_in_memory().unwrap();
+        let session = create_test_session();
+        db.create_session(session.clone()).unwrap();
+        let entry = EntryCreate {
+            original_text: Some("hola".to_string()),
+            ..create_test_entry(&session.id)
+        };
+        db.create_entry(entry.clone()).unwrap();
+
+        let updated = db
+            .update_entry_texts(&entry.id, None, Some("hello".to_string()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.original_text, Some("hola".to_string()));
+        assert_eq!(updated.translated_text, Some("hello".to_string()));
+
+        let found = db
+            .search_entries_in("hello", EntryTextField::Translation)
+            .unwrap();
+        assert_eq!(found.len(), 1);
+
+        db.delete_entry(&entry.id).unwrap();
+        assert!(db
+            .search_entries_in("hola", EntryTextField::Original)
+            .unwrap()
+            .is_empty());
+    }
+
     #[test]
     fn test_idempotent_migrations() {
         let db = Database::new_in_memory().unwrap();
@@ -0,0 +1,543 @@
+use crate::audio::{compute_rms, TARGET_SAMPLE_RATE};
+use crate::stt::TextSegment;
+use std::f32::consts::PI;
+
+const FRAME_LEN: usize = 400; // 25 ms
+const HOP_LEN: usize = 160; // 10 ms
+const FFT_LEN: usize = 512;
+const MEL_FILTERS: usize = 26;
+const CEPSTRAL_COEFFS: usize = 13;
+const PRE_EMPHASIS: f32 = 0.97;
+/// Segments with fewer voiced frames than this (about a quarter second) are
+/// too short to embed reliably and take the neighbouring speaker instead.
+const MIN_VOICED_FRAMES: usize = 25;
+/// Cosine distance under which an embedding joins an existing speaker.
+const SAME_SPEAKER_DISTANCE: f32 = 0.2;
+const MAX_SPEAKERS: usize = 8;
+
+/// MFCC front end with the mel filterbank and DCT matrix precomputed.
+pub struct Mfcc {
+    window: Vec<f32>,
+    filters: Vec<Vec<(usize, f32)>>,
+    dct: Vec<Vec<f32>>,
+}
+
+impl Mfcc {
+    pub fn new() -> Self {
+        let window = (0..FRAME_LEN)
+            .map(|n| 0.54 - 0.46 * (2.0 * PI * n as f32 / (FRAME_LEN - 1) as f32).cos())
+            .collect();
+
+        let hz_to_mel = |hz: f32| 2595.0 * (1.0 + hz / 700.0).log10();
+        let mel_to_hz = |mel: f32| 700.0 * (10f32.powf(mel / 2595.0) - 1.0);
+        let (low, high) = (hz_to_mel(20.0), hz_to_mel(TARGET_SAMPLE_RATE as f32 / 2.0));
+        let bin_of = |hz: f32| (hz * FFT_LEN as f32 / TARGET_SAMPLE_RATE as f32).floor() as usize;
+        let points: Vec<usize> = (0..MEL_FILTERS + 2)
+            .map(|i| {
+                bin_of(mel_to_hz(
+                    low + (high - low) * i as f32 / (MEL_FILTERS + 1) as f32,
+                ))
+            })
+            .collect();
+
+        let filters = (0..MEL_FILTERS)
+            .map(|m| {
+                let (left, center, right) = (points[m], points[m + 1], points[m + 2]);
+                let mut weights = Vec::new();
+                for bin in left..=right.min(FFT_LEN / 2) {
+                    let weight = if bin <= center {
+                        (bin - left) as f32 / (center - left).max(1) as f32
+                    } else {
+                        (right - bin) as f32 / (right - center).max(1) as f32
+                    };
+                    if weight > 0.0 {
+                        weights.push((bin, weight));
+                    }
+                }
+                weights
+            })
+            .collect();
+
+        let dct = (0..CEPSTRAL_COEFFS)
+            .map(|k| {
+                (0..MEL_FILTERS)
+                    .map(|m| (PI * k as f32 * (m as f32 + 0.5) / MEL_FILTERS as f32).cos())
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            window,
+            filters,
+            dct,
+        }
+    }
+
+    /// Cepstral coefficients for one frame of `FRAME_LEN` samples.
+    fn frame(&self, samples: &[f32]) -> [f32; CEPSTRAL_COEFFS] {
+        let mut re = [0.0f32; FFT_LEN];
+        let mut im = [0.0f32; FFT_LEN];
+        let mut previous = 0.0;
+        for (i, (&sample, &w)) in samples.iter().zip(&self.window).enumerate() {
+            re[i] = (sample - PRE_EMPHASIS * previous) * w;
+            previous = sample;
+        }
+        fft(&mut re, &mut im);
+
+        let log_energies: Vec<f32> = self
+            .filters
+            .iter()
+            .map(|weights| {
+                let energy: f32 = weights
+                    .iter()
+                    .map(|&(bin, w)| w * (re[bin] * re[bin] + im[bin] * im[bin]))
+                    .sum();
+                (energy + 1e-10).ln()
+            })
+            .collect();
+
+        let mut coeffs = [0.0; CEPSTRAL_COEFFS];
+        for (coeff, basis) in coeffs.iter_mut().zip(&self.dct) {
+            *coeff = basis.iter().zip(&log_energies).map(|(b, e)| b * e).sum();
+        }
+        coeffs
+    }
+
+    /// Summarises the voiced frames of `samples` as the mean and standard
+    /// deviation of each cepstral coefficient except the energy term.
+    pub fn embed(&self, samples: &[f32]) -> Option<Vec<f32>> {
+        if samples.len() < FRAME_LEN {
+            return None;
+        }
+
+        let frames: Vec<&[f32]> = (0..=(samples.len() - FRAME_LEN) / HOP_LEN)
+            .map(|i| &samples[i * HOP_LEN..i * HOP_LEN + FRAME_LEN])
+            .collect();
+        let loudness: Vec<f32> = frames.iter().map(|frame| compute_rms(frame)).collect();
+        let loudest = loudness.iter().copied().fold(0.0, f32::max);
+        let voiced_floor = (loudest * 0.25).max(1e-3);
+
+        let cepstra: Vec<[f32; CEPSTRAL_COEFFS]> = frames
+            .iter()
+            .zip(&loudness)
+            .filter(|(_, &rms)| rms >= voiced_floor)
+            .map(|(frame, _)| self.frame(frame))
+            .collect();
+        if cepstra.len() < MIN_VOICED_FRAMES {
+            return None;
+        }
+
+        let count = cepstra.len() as f32;
+        let mut embedding = Vec::with_capacity(2 * (CEPSTRAL_COEFFS - 1));
+        for k in 1..CEPSTRAL_COEFFS {
+            let mean = cepstra.iter().map(|c| c[k]).sum::<f32>() / count;
+            embedding.push(mean);
+        }
+        for k in 1..CEPSTRAL_COEFFS {
+            let mean = embedding[k - 1];
+            let variance = cepstra.iter().map(|c| (c[k] - mean).powi(2)).sum::<f32>() / count;
+            embedding.push(variance.sqrt());
+        }
+        Some(embedding)
+    }
+}
+
+impl Default for Mfcc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// In-place iterative radix-2 FFT; `re.len()` must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 1.0;
+    }
+    1.0 - dot / (norm_a * norm_b)
+}
+
+struct Speaker {
+    label: String,
+    centroid: Vec<f32>,
+    count: usize,
+}
+
+/// Assigns speaker labels (`S1`, `S2`, ...) consistently across the chunks of
+/// one record session by clustering segment embeddings online.
+pub struct SpeakerTracker {
+    session_id: String,
+    mfcc: Mfcc,
+    speakers: Vec<Speaker>,
+}
+
+impl SpeakerTracker {
+    pub fn new(session_id: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            mfcc: Mfcc::new(),
+            speakers: Vec::new(),
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    #[cfg(test)]
+    pub fn speaker_count(&self) -> usize {
+        self.speakers.len()
+    }
+
+    fn assign(&mut self, embedding: Vec<f32>) -> String {
+        let full = self.speakers.len() >= MAX_SPEAKERS;
+        let nearest = self
+            .speakers
+            .iter_mut()
+            .map(|speaker| (cosine_distance(&speaker.centroid, &embedding), speaker))
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        match nearest {
+            Some((distance, speaker)) if distance < SAME_SPEAKER_DISTANCE || full => {
+                speaker.count += 1;
+                let weight = 1.0 / speaker.count as f32;
+                for (c, e) in speaker.centroid.iter_mut().zip(&embedding) {
+                    *c += (e - *c) * weight;
+                }
+                speaker.label.clone()
+            }
+            _ => {
+                let label = format!("S{}", self.speakers.len() + 1);
+                self.speakers.push(Speaker {
+                    label: label.clone(),
+                    centroid: embedding,
+                    count: 1,
+                });
+                label
+            }
+        }
+    }
+
+    /// Labels each segment with a speaker. `audio_data` is the audio the
+    /// segment timestamps refer to. Segments too short to embed take the
+    /// speaker of the previous segment, or of the next one at the start.
+    pub fn label_segments(&mut self, audio_data: &[f32], segments: &mut [TextSegment]) {
+        let samples_per_ms = TARGET_SAMPLE_RATE as usize / 1000;
+        for segment in segments.iter_mut() {
+            let start = (segment.start_ms.max(0) as usize * samples_per_ms).min(audio_data.len());
+            let end = (segment.end_ms.max(0) as usize * samples_per_ms).min(audio_data.len());
+            segment.speaker = if start < end {
+                self.mfcc
+                    .embed(&audio_data[start..end])
+                    .map(|embedding| self.assign(embedding))
+            } else {
+                None
+            };
+        }
+
+        let mut previous: Option<String> = None;
+        for segment in segments.iter_mut() {
+            match &segment.speaker {
+                Some(label) => previous = Some(label.clone()),
+                None => segment.speaker = previous.clone(),
+            }
+        }
+        let mut next: Option<String> = None;
+        for segment in segments.iter_mut().rev() {
+            match &segment.speaker {
+                Some(label) => next = Some(label.clone()),
+                None => segment.speaker = next.clone(),
+            }
+        }
+    }
+}
+
+/// Consecutive segments by the same speaker, joined into one turn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeakerTurn {
+    pub speaker: Option<String>,
+    pub text: String,
+    pub start_ms: i32,
+    pub end_ms: i32,
+}
+
+pub fn speaker_turns(segments: &[TextSegment]) -> Vec<SpeakerTurn> {
+    let mut turns: Vec<SpeakerTurn> = Vec::new();
+    for segment in segments {
+        let text = segment.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        match turns.last_mut() {
+            Some(turn) if turn.speaker == segment.speaker => {
+                turn.text.push(' ');
+                turn.text.push_str(text);
+                turn.end_ms = segment.end_ms;
+            }
+            _ => turns.push(SpeakerTurn {
+                speaker: segment.speaker.clone(),
+                text: text.to_string(),
+                start_ms: segment.start_ms,
+                end_ms: segment.end_ms,
+            }),
+        }
+    }
+    turns
+}
+
+#[cfg(test)]
+/// A crude vowel: harmonics of `f0` shaped by three formant resonances,
+/// with slow vibrato so frames are not identical.
+fn synth_voice(f0: f32, formants: [f32; 3], seconds: f32, seed: f32) -> Vec<f32> {
+    let rate = TARGET_SAMPLE_RATE as f32;
+    let len = (seconds * rate) as usize;
+    let harmonics = (4000.0 / f0) as usize;
+    let mut phase = vec![0.0f32; harmonics + 1];
+
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / rate;
+            let pitch = f0 * (1.0 + 0.02 * (2.0 * PI * (5.0 + seed) * t).sin());
+            let mut sample = 0.0;
+            for (h, phase) in phase.iter_mut().enumerate().skip(1) {
+                let freq = pitch * h as f32;
+                *phase += 2.0 * PI * freq / rate;
+                let gain: f32 = formants
+                    .iter()
+                    .map(|f| 1.0 / (1.0 + ((freq - f) / 120.0).powi(2)))
+                    .sum();
+                sample += gain * phase.sin();
+            }
+            sample * 0.05
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub fn voice_a(seconds: f32, seed: f32) -> Vec<f32> {
+    synth_voice(115.0, [730.0, 1090.0, 2440.0], seconds, seed)
+}
+
+#[cfg(test)]
+pub fn voice_b(seconds: f32, seed: f32) -> Vec<f32> {
+    synth_voice(225.0, [310.0, 2790.0, 3310.0], seconds, seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::read_wav;
+    use std::path::{Path, PathBuf};
+
+    fn write_fixture(name: &str, samples: &[f32]) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: TARGET_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in samples {
+            writer
+                .write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+                .unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    /// Writes a two-voice conversation to a WAV file and returns the file
+    /// together with segments covering each utterance.
+    fn two_voice_fixture(name: &str, turns: &[(char, f32)]) -> (PathBuf, Vec<TextSegment>) {
+        let gap = vec![0.0; TARGET_SAMPLE_RATE as usize / 4];
+        let mut audio = Vec::new();
+        let mut segments = Vec::new();
+        for (i, &(voice, seconds)) in turns.iter().enumerate() {
+            let start_ms = (audio.len() * 1000 / TARGET_SAMPLE_RATE as usize) as i32;
+            let utterance = match voice {
+                'a' => voice_a(seconds, i as f32 * 0.3),
+                _ => voice_b(seconds, i as f32 * 0.3),
+            };
+            audio.extend(utterance);
+            segments.push(TextSegment {
+                text: format!(" utterance {}", i + 1),
+                start_ms,
+                end_ms: (audio.len() * 1000 / TARGET_SAMPLE_RATE as usize) as i32,
+                no_speech_prob: 0.0,
+                speaker: None,
+            });
+            audio.extend(&gap);
+        }
+        (write_fixture(name, &audio), segments)
+    }
+
+    fn labels(segments: &[TextSegment]) -> Vec<&str> {
+        segments
+            .iter()
+            .map(|s| s.speaker.as_deref().unwrap_or("-"))
+            .collect()
+    }
+
+    fn load(path: &Path) -> Vec<f32> {
+        let audio = read_wav(path).unwrap();
+        std::fs::remove_file(path).ok();
+        audio
+    }
+
+    #[test]
+    fn test_fft_of_impulse_is_flat() {
+        let mut re = [0.0f32; 8];
+        let mut im = [0.0f32; 8];
+        re[0] = 1.0;
+        fft(&mut re, &mut im);
+        assert!(re.iter().all(|v| (v - 1.0).abs() < 1e-6));
+        assert!(im.iter().all(|v| v.abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_fft_finds_sine_bin() {
+        let mut re: Vec<f32> = (0..64)
+            .map(|n| (2.0 * PI * 4.0 * n as f32 / 64.0).sin())
+            .collect();
+        let mut im = vec![0.0; 64];
+        fft(&mut re, &mut im);
+        let magnitudes: Vec<f32> = re.iter().zip(&im).map(|(r, i)| r.hypot(*i)).collect();
+        let peak = (0..32).max_by(|&a, &b| magnitudes[a].total_cmp(&magnitudes[b]));
+        assert_eq!(peak, Some(4));
+    }
+
+    #[test]
+    fn test_embedding_separates_voices() {
+        let mfcc = Mfcc::new();
+        let a1 = mfcc.embed(&voice_a(1.5, 0.0)).unwrap();
+        let a2 = mfcc.embed(&voice_a(1.2, 0.7)).unwrap();
+        let b1 = mfcc.embed(&voice_b(1.5, 0.0)).unwrap();
+
+        assert!(cosine_distance(&a1, &a2) < SAME_SPEAKER_DISTANCE);
+        assert!(cosine_distance(&a1, &b1) > SAME_SPEAKER_DISTANCE);
+    }
+
+    #[test]
+    fn test_embedding_rejects_silence_and_short_audio() {
+        let mfcc = Mfcc::new();
+        assert!(mfcc.embed(&[0.0; 16_000]).is_none());
+        assert!(mfcc.embed(&voice_a(0.1, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_two_voice_fixture_is_labelled_by_speaker() {
+        let (path, mut segments) = two_voice_fixture(
+            "stt_diarization_two_voices.wav",
+            &[('a', 1.5), ('b', 1.2), ('a', 1.0), ('b', 1.8), ('b', 1.0)],
+        );
+        let audio = load(&path);
+
+        let mut tracker = SpeakerTracker::new("session");
+        tracker.label_segments(&audio, &mut segments);
+
+        assert_eq!(labels(&segments), vec!["S1", "S2", "S1", "S2", "S2"]);
+        assert_eq!(tracker.speaker_count(), 2);
+    }
+
+    #[test]
+    fn test_labels_stay_consistent_across_chunks() {
+        let (first_path, mut first) =
+            two_voice_fixture("stt_diarization_chunk1.wav", &[('b', 1.5), ('a', 1.5)]);
+        let (second_path, mut second) =
+            two_voice_fixture("stt_diarization_chunk2.wav", &[('a', 1.2), ('b', 1.2)]);
+
+        let mut tracker = SpeakerTracker::new("session");
+        tracker.label_segments(&load(&first_path), &mut first);
+        tracker.label_segments(&load(&second_path), &mut second);
+
+        assert_eq!(labels(&first), vec!["S1", "S2"]);
+        assert_eq!(labels(&second), vec!["S2", "S1"]);
+    }
+
+    #[test]
+    fn test_short_segments_inherit_neighbouring_speaker() {
+        let (path, mut segments) = two_voice_fixture(
+            "stt_diarization_short.wav",
+            &[('a', 0.1), ('a', 1.5), ('b', 1.5), ('b', 0.1)],
+        );
+        let audio = load(&path);
+
+        let mut tracker = SpeakerTracker::new("session");
+        tracker.label_segments(&audio, &mut segments);
+
+        assert_eq!(labels(&segments), vec!["S1", "S1", "S2", "S2"]);
+    }
+
+    #[test]
+    fn test_speaker_turns_join_consecutive_segments() {
+        let segment = |text: &str, start_ms, end_ms, speaker: &str| TextSegment {
+            text: text.to_string(),
+            start_ms,
+            end_ms,
+            no_speech_prob: 0.0,
+            speaker: Some(speaker.to_string()),
+        };
+        let turns = speaker_turns(&[
+            segment(" Hi there.", 0, 900, "S1"),
+            segment(" How are you?", 900, 2000, "S1"),
+            segment(" ", 2000, 2100, "S2"),
+            segment(" Fine, thanks.", 2100, 3000, "S2"),
+        ]);
+
+        assert_eq!(
+            turns,
+            vec![
+                SpeakerTurn {
+                    speaker: Some("S1".to_string()),
+                    text: "Hi there. How are you?".to_string(),
+                    start_ms: 0,
+                    end_ms: 2000,
+                },
+                SpeakerTurn {
+                    speaker: Some("S2".to_string()),
+                    text: "Fine, thanks.".to_string(),
+                    start_ms: 2100,
+                    end_ms: 3000,
+                },
+            ]
+        );
+    }
+}
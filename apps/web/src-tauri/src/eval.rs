@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+
+/// Edit distance between a hypothesis and a reference, plus the reference
+/// length it is measured against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ErrorCount {
+    pub errors: usize,
+    pub reference_len: usize,
+}
+
+impl ErrorCount {
+    pub fn rate(&self) -> f64 {
+        if self.reference_len == 0 {
+            return if self.errors == 0 { 0.0 } else { 1.0 };
+        }
+        self.errors as f64 / self.reference_len as f64
+    }
+
+    fn add(&mut self, other: ErrorCount) {
+        self.errors += other.errors;
+        self.reference_len += other.reference_len;
+    }
+}
+
+/// Lowercases and drops punctuation so scoring only counts recognition
+/// mistakes, not formatting differences.
+pub fn normalize_for_scoring(text: &str) -> Vec<String> {
+    text.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '\'' {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+fn edit_distance<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=hypothesis.len()).collect();
+    let mut current = vec![0; hypothesis.len() + 1];
+
+    for (i, r) in reference.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, h) in hypothesis.iter().enumerate() {
+            let substitution = previous[j] + usize::from(r != h);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[hypothesis.len()]
+}
+
+pub fn word_errors(reference: &str, hypothesis: &str) -> ErrorCount {
+    let reference = normalize_for_scoring(reference);
+    let hypothesis = normalize_for_scoring(hypothesis);
+    ErrorCount {
+        errors: edit_distance(&reference, &hypothesis),
+        reference_len: reference.len(),
+    }
+}
+
+pub fn char_errors(reference: &str, hypothesis: &str) -> ErrorCount {
+    let reference: Vec<char> = normalize_for_scoring(reference).join(" ").chars().collect();
+    let hypothesis: Vec<char> = normalize_for_scoring(hypothesis)
+        .join(" ")
+        .chars()
+        .collect();
+    ErrorCount {
+        errors: edit_distance(&reference, &hypothesis),
+        reference_len: reference.len(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileReport {
+    pub file: String,
+    pub reference: String,
+    pub hypothesis: String,
+    pub audio_seconds: f64,
+    pub processing_seconds: f64,
+    pub words: ErrorCount,
+    pub chars: ErrorCount,
+    pub wer: f64,
+    pub cer: f64,
+    pub rtf: f64,
+}
+
+impl FileReport {
+    pub fn new(
+        file: String,
+        reference: String,
+        hypothesis: String,
+        audio_seconds: f64,
+        processing_seconds: f64,
+    ) -> Self {
+        let words = word_errors(&reference, &hypothesis);
+        let chars = char_errors(&reference, &hypothesis);
+        Self {
+            file,
+            wer: words.rate(),
+            cer: chars.rate(),
+            rtf: real_time_factor(processing_seconds, audio_seconds),
+            reference,
+            hypothesis,
+            audio_seconds,
+            processing_seconds,
+            words,
+            chars,
+        }
+    }
+}
+
+/// Corpus-level totals: error rates are computed over all words and characters
+/// rather than averaged per file, so long files weigh more.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AggregateReport {
+    pub files: usize,
+    pub audio_seconds: f64,
+    pub processing_seconds: f64,
+    pub words: ErrorCount,
+    pub chars: ErrorCount,
+    pub wer: f64,
+    pub cer: f64,
+    pub rtf: f64,
+}
+
+impl AggregateReport {
+    pub fn from_files(files: &[FileReport]) -> Self {
+        let mut aggregate = Self {
+            files: files.len(),
+            ..Default::default()
+        };
+        for file in files {
+            aggregate.audio_seconds += file.audio_seconds;
+            aggregate.processing_seconds += file.processing_seconds;
+            aggregate.words.add(file.words);
+            aggregate.chars.add(file.chars);
+        }
+        aggregate.wer = aggregate.words.rate();
+        aggregate.cer = aggregate.chars.rate();
+        aggregate.rtf = real_time_factor(aggregate.processing_seconds, aggregate.audio_seconds);
+        aggregate
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalReport {
+    pub created_at: i64,
+    pub model_profile: crate::prefs::ModelProfile,
+    pub files: Vec<FileReport>,
+    pub aggregate: AggregateReport,
+}
+
+fn real_time_factor(processing_seconds: f64, audio_seconds: f64) -> f64 {
+    if audio_seconds > 0.0 {
+        processing_seconds / audio_seconds
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_errors_exact_match() {
+        let count = word_errors("Hello, world.", "hello world");
+        assert_eq!(count.errors, 0);
+        assert_eq!(count.reference_len, 2);
+        assert_eq!(count.rate(), 0.0);
+    }
+
+    #[test]
+    fn test_word_errors_counts_substitution_insertion_deletion() {
+        assert_eq!(word_errors("the cat sat", "the bat sat").errors, 1);
+        assert_eq!(word_errors("the cat sat", "the cat sat down").errors, 1);
+        assert_eq!(word_errors("the cat sat", "the sat").errors, 1);
+        assert_eq!(word_errors("the cat sat", "").errors, 3);
+    }
+
+    #[test]
+    fn test_word_error_rate_can_exceed_one() {
+        let count = word_errors("yes", "no no no");
+        assert_eq!(count.errors, 3);
+        assert_eq!(count.rate(), 3.0);
+    }
+
+    #[test]
+    fn test_char_errors() {
+        let count = char_errors("kitten", "sitting");
+        assert_eq!(count.errors, 3);
+        assert_eq!(count.reference_len, 6);
+    }
+
+    #[test]
+    fn test_empty_reference_rate() {
+        assert_eq!(word_errors("", "").rate(), 0.0);
+        assert_eq!(word_errors("", "hallucinated").rate(), 1.0);
+    }
+
+    #[test]
+    fn test_file_report_metrics() {
+        let report = FileReport::new(
+            "a.wav".to_string(),
+            "ship the build today".to_string(),
+            "ship the bill today".to_string(),
+            10.0,
+            2.5,
+        );
+        assert_eq!(report.wer, 0.25);
+        assert_eq!(report.rtf, 0.25);
+        assert!(report.cer > 0.0 && report.cer < 0.25);
+    }
+
+    #[test]
+    fn test_aggregate_weighs_by_reference_length() {
+        let files = vec![
+            FileReport::new(
+                "short.wav".to_string(),
+                "one".to_string(),
+                "two".to_string(),
+                1.0,
+                1.0,
+            ),
+            FileReport::new(
+                "long.wav".to_string(),
+                "a b c d e f g h i".to_string(),
+                "a b c d e f g h i".to_string(),
+                9.0,
+                2.0,
+            ),
+        ];
+        let aggregate = AggregateReport::from_files(&files);
+        assert_eq!(aggregate.files, 2);
+        assert_eq!(aggregate.words.errors, 1);
+        assert_eq!(aggregate.words.reference_len, 10);
+        assert!((aggregate.wer - 0.1).abs() < 1e-9);
+        assert!((aggregate.rtf - 0.3).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,372 @@
+use crate::audio::compute_rms;
+use crate::prefs::HallucinationFilter;
+use crate::stt::TranscriptionResult;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DropReason {
+    LowEnergy(f32),
+    NoSpeech(f32),
+    NonSpeechMarker,
+    Blocklisted,
+    Repetition,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DroppedText {
+    pub text: String,
+    pub reason: DropReason,
+}
+
+/// Removes text whisper most likely made up, updating `result` in place.
+/// Every removal is logged and returned so thresholds can be tuned.
+pub fn filter_hallucinations(
+    result: &mut TranscriptionResult,
+    audio_data: &[f32],
+    filter: &HallucinationFilter,
+) -> Vec<DroppedText> {
+    let mut dropped = Vec::new();
+    if !filter.enabled || result.text.trim().is_empty() {
+        return dropped;
+    }
+
+    let rms = compute_rms(audio_data);
+    if rms < filter.min_rms {
+        dropped.push(DroppedText {
+            text: std::mem::take(&mut result.text),
+            reason: DropReason::LowEnergy(rms),
+        });
+        result.segments.clear();
+        log_dropped(&dropped);
+        return dropped;
+    }
+
+    let blocklist: Vec<String> = filter
+        .blocklist
+        .iter()
+        .map(|phrase| normalize_phrase(phrase))
+        .filter(|phrase| !phrase.is_empty())
+        .collect();
+
+    let segment_count = result.segments.len();
+    result.segments.retain(|segment| {
+        let reason = if segment.no_speech_prob >= filter.no_speech_threshold {
+            Some(DropReason::NoSpeech(segment.no_speech_prob))
+        } else if is_non_speech_marker(&segment.text) {
+            Some(DropReason::NonSpeechMarker)
+        } else if blocklist.contains(&normalize_phrase(&segment.text)) {
+            Some(DropReason::Blocklisted)
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => {
+                dropped.push(DroppedText {
+                    text: segment.text.clone(),
+                    reason,
+                });
+                false
+            }
+            None => true,
+        }
+    });
+    if result.segments.len() != segment_count {
+        result.text = result.segments.iter().map(|s| s.text.as_str()).collect();
+    }
+
+    let (collapsed, repeated) = collapse_repetitions(&result.text, filter.max_repeats as usize);
+    dropped.extend(repeated.into_iter().map(|text| DroppedText {
+        text,
+        reason: DropReason::Repetition,
+    }));
+    result.text = collapsed;
+
+    let remaining = result.text.trim();
+    if !remaining.is_empty()
+        && (is_non_speech_marker(remaining) || blocklist.contains(&normalize_phrase(remaining)))
+    {
+        let reason = if is_non_speech_marker(remaining) {
+            DropReason::NonSpeechMarker
+        } else {
+            DropReason::Blocklisted
+        };
+        dropped.push(DroppedText {
+            text: std::mem::take(&mut result.text),
+            reason,
+        });
+        result.segments.clear();
+    }
+
+    log_dropped(&dropped);
+    dropped
+}
+
+fn log_dropped(dropped: &[DroppedText]) {
+    for item in dropped {
+        log::info!(
+            "Dropped likely hallucination ({:?}): {:?}",
+            item.reason,
+            item.text
+        );
+    }
+}
+
+/// Lowercases and strips punctuation so "Thanks for watching!" matches
+/// a blocklist entry of "thanks for watching".
+fn normalize_phrase(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '\'' {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whisper annotates non-speech as `[BLANK_AUDIO]`, `(music)`, `*laughs*` or
+/// music notes. A segment made of nothing but such markers is not dictation.
+fn is_non_speech_marker(text: &str) -> bool {
+    let mut rest = text.trim();
+    if rest.is_empty() {
+        return false;
+    }
+
+    while !rest.is_empty() {
+        let close = match rest.chars().next() {
+            Some('[') => ']',
+            Some('(') => ')',
+            Some('*') => '*',
+            Some('♪') | Some('♫') => {
+                rest = rest[rest.chars().next().unwrap().len_utf8()..].trim_start();
+                continue;
+            }
+            _ => return false,
+        };
+        match rest[1..].find(close) {
+            Some(end) => rest = rest[end + 2..].trim_start(),
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Collapses a phrase repeated back to back more than `max_repeats` times into
+/// a single occurrence. Returns the new text and the removed repetitions.
+fn collapse_repetitions(text: &str, max_repeats: usize) -> (String, Vec<String>) {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let keys: Vec<String> = words.iter().map(|word| normalize_phrase(word)).collect();
+    let max_repeats = max_repeats.max(1);
+
+    let mut kept: Vec<&str> = Vec::with_capacity(words.len());
+    let mut removed = Vec::new();
+    let mut i = 0;
+    'outer: while i < words.len() {
+        let max_len = (words.len() - i) / (max_repeats + 1);
+        for len in 1..=max_len {
+            let phrase = &keys[i..i + len];
+            if phrase.iter().all(|key| key.is_empty()) {
+                continue;
+            }
+
+            let mut repeats = 1;
+            while i + (repeats + 1) * len <= words.len()
+                && keys[i + repeats * len..i + (repeats + 1) * len] == *phrase
+            {
+                repeats += 1;
+            }
+
+            if repeats > max_repeats {
+                kept.extend(&words[i..i + len]);
+                removed.push(words[i + len..i + repeats * len].join(" "));
+                i += repeats * len;
+                continue 'outer;
+            }
+        }
+        kept.push(words[i]);
+        i += 1;
+    }
+
+    if removed.is_empty() {
+        return (text.to_string(), removed);
+    }
+    (kept.join(" "), removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stt::TextSegment;
+
+    fn speech() -> Vec<f32> {
+        (0..1600).map(|i| (i as f32 * 0.05).sin() * 0.2).collect()
+    }
+
+    fn segment(text: &str, no_speech_prob: f32) -> TextSegment {
+        TextSegment {
+            text: text.to_string(),
+            start_ms: 0,
+            end_ms: 0,
+            no_speech_prob,
+            speaker: None,
+        }
+    }
+
+    fn result_from(segments: Vec<TextSegment>) -> TranscriptionResult {
+        TranscriptionResult {
+            text: segments.iter().map(|s| s.text.as_str()).collect(),
+            segments,
+            language: None,
+            language_probability: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            actions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_keeps_ordinary_dictation() {
+        let mut result = result_from(vec![segment(" Send the report tomorrow.", 0.05)]);
+        let dropped =
+            filter_hallucinations(&mut result, &speech(), &HallucinationFilter::default());
+        assert!(dropped.is_empty());
+        assert_eq!(result.text, " Send the report tomorrow.");
+    }
+
+    #[test]
+    fn test_drops_everything_for_silent_audio() {
+        let mut result = result_from(vec![segment(" Thank you.", 0.1)]);
+        let dropped =
+            filter_hallucinations(&mut result, &[0.0; 1600], &HallucinationFilter::default());
+        assert_eq!(result.text, "");
+        assert!(result.segments.is_empty());
+        assert!(matches!(dropped[0].reason, DropReason::LowEnergy(_)));
+        assert_eq!(dropped[0].text, " Thank you.");
+    }
+
+    #[test]
+    fn test_drops_segments_with_high_no_speech_probability() {
+        let mut result = result_from(vec![
+            segment(" Meeting at noon.", 0.1),
+            segment(" Bye.", 0.92),
+        ]);
+        let dropped =
+            filter_hallucinations(&mut result, &speech(), &HallucinationFilter::default());
+        assert_eq!(result.text, " Meeting at noon.");
+        assert_eq!(result.segments.len(), 1);
+        assert_eq!(dropped[0].reason, DropReason::NoSpeech(0.92));
+    }
+
+    #[test]
+    fn test_drops_blocklisted_segments_ignoring_case_and_punctuation() {
+        let mut result = result_from(vec![
+            segment(" Fix the login bug.", 0.0),
+            segment(" THANKS FOR WATCHING!", 0.0),
+        ]);
+        let dropped =
+            filter_hallucinations(&mut result, &speech(), &HallucinationFilter::default());
+        assert_eq!(result.text, " Fix the login bug.");
+        assert_eq!(dropped[0].reason, DropReason::Blocklisted);
+    }
+
+    #[test]
+    fn test_blocklist_only_matches_whole_segments() {
+        let mut result = result_from(vec![segment(
+            " Please subscribe me to the mailing list.",
+            0.0,
+        )]);
+        let dropped =
+            filter_hallucinations(&mut result, &speech(), &HallucinationFilter::default());
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn test_custom_blocklist_entries_are_used() {
+        let filter = HallucinationFilter {
+            blocklist: vec!["okay bye".to_string()],
+            ..Default::default()
+        };
+        let mut result = result_from(vec![segment(" Okay, bye.", 0.0)]);
+        filter_hallucinations(&mut result, &speech(), &filter);
+        assert_eq!(result.text, "");
+    }
+
+    #[test]
+    fn test_drops_non_speech_markers() {
+        let mut result = result_from(vec![
+            segment(" [BLANK_AUDIO]", 0.0),
+            segment(" (upbeat music)", 0.0),
+            segment(" ♪ *laughs* ♪", 0.0),
+            segment(" Hello there.", 0.0),
+        ]);
+        let dropped =
+            filter_hallucinations(&mut result, &speech(), &HallucinationFilter::default());
+        assert_eq!(result.text, " Hello there.");
+        assert_eq!(dropped.len(), 3);
+        assert!(dropped
+            .iter()
+            .all(|d| d.reason == DropReason::NonSpeechMarker));
+    }
+
+    #[test]
+    fn test_collapses_phrase_repeated_many_times() {
+        let text = " I'll be right back.".repeat(10);
+        let mut result = result_from(vec![segment(&text, 0.0)]);
+        let dropped =
+            filter_hallucinations(&mut result, &speech(), &HallucinationFilter::default());
+        assert_eq!(result.text, "I'll be right back.");
+        assert_eq!(dropped[0].reason, DropReason::Repetition);
+    }
+
+    #[test]
+    fn test_repeated_blocklisted_phrase_is_dropped_entirely() {
+        let text = " Thank you for watching.".repeat(6);
+        let mut result = result_from(vec![segment(&text, 0.0)]);
+        let dropped =
+            filter_hallucinations(&mut result, &speech(), &HallucinationFilter::default());
+        assert_eq!(result.text, "");
+        assert_eq!(dropped.last().unwrap().reason, DropReason::Blocklisted);
+    }
+
+    #[test]
+    fn test_short_intentional_repetition_is_kept() {
+        let mut result = result_from(vec![segment(" No, no, no, that's wrong.", 0.0)]);
+        let dropped =
+            filter_hallucinations(&mut result, &speech(), &HallucinationFilter::default());
+        assert!(dropped.is_empty());
+        assert_eq!(result.text, " No, no, no, that's wrong.");
+    }
+
+    #[test]
+    fn test_collapse_keeps_surrounding_words() {
+        let (text, removed) = collapse_repetitions("so the the the the the end", 3);
+        assert_eq!(text, "so the end");
+        assert_eq!(removed, vec!["the the the the".to_string()]);
+    }
+
+    #[test]
+    fn test_disabled_filter_keeps_everything() {
+        let filter = HallucinationFilter {
+            enabled: false,
+            ..Default::default()
+        };
+        let mut result = result_from(vec![segment(" [BLANK_AUDIO]", 0.99)]);
+        let dropped = filter_hallucinations(&mut result, &[0.0; 16], &filter);
+        assert!(dropped.is_empty());
+        assert_eq!(result.text, " [BLANK_AUDIO]");
+    }
+
+    #[test]
+    fn test_is_non_speech_marker() {
+        assert!(is_non_speech_marker("[MUSIC]"));
+        assert!(is_non_speech_marker(" [BLANK_AUDIO] (silence) "));
+        assert!(!is_non_speech_marker("[MUSIC] and then"));
+        assert!(!is_non_speech_marker("(unclosed"));
+        assert!(!is_non_speech_marker(""));
+    }
+}
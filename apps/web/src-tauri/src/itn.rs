@@ -0,0 +1,1025 @@
+//! Inverse text normalization: rewrites spoken numbers, dates, times and
+//! units in a transcript the way they are usually written.
+
+/// Languages with normalization rules. Text in any other language is left
+/// unchanged.
+pub const SUPPORTED_LANGUAGES: &[&str] = &["en"];
+
+pub fn is_supported(language: &str) -> bool {
+    SUPPORTED_LANGUAGES.contains(&base_language(language).as_str())
+}
+
+/// "en-US" and "en_GB" both use the "en" rules.
+pub fn base_language(language: &str) -> String {
+    language
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Normalizes `text` with the rules for `language`. Line breaks and tabs are
+/// kept and nothing is matched across them.
+pub fn normalize(text: &str, language: &str) -> String {
+    let rules: fn(&str) -> String = match base_language(language).as_str() {
+        "en" => normalize_english,
+        _ => return text.to_string(),
+    };
+
+    let mut output = String::with_capacity(text.len());
+    for piece in text.split_inclusive(['\n', '\t']) {
+        let line = piece.strip_suffix(['\n', '\t']).unwrap_or(piece);
+        output.push_str(&rules(line));
+        output.push_str(&piece[line.len()..]);
+    }
+    output
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    raw: String,
+    lead: String,
+    core: String,
+    trail: String,
+}
+
+impl Token {
+    fn new(raw: &str) -> Self {
+        let core = raw.trim_matches(|c: char| !c.is_alphanumeric());
+        let lead_len = raw.find(core).unwrap_or(0);
+        Self {
+            raw: raw.to_string(),
+            lead: raw[..lead_len].to_string(),
+            core: core.to_lowercase(),
+            trail: raw[lead_len + core.len()..].to_string(),
+        }
+    }
+}
+
+/// Splits on single spaces so the original spacing can be rebuilt, and
+/// breaks "twenty-three" into two number words.
+fn tokenize(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for raw in line.split(' ') {
+        let token = Token::new(raw);
+        let compound = token.core.split_once('-').filter(|(tens, unit)| {
+            matches!(number_word(tens), Some((NumberWord::Tens(_), false)))
+                && matches!(number_word(unit), Some((NumberWord::Unit(_), _)))
+        });
+        match compound {
+            Some((tens, unit)) => {
+                tokens.push(Token {
+                    raw: format!("{}{}", token.lead, tens),
+                    lead: token.lead.clone(),
+                    core: tens.to_string(),
+                    trail: String::new(),
+                });
+                tokens.push(Token {
+                    raw: format!("{}{}", unit, token.trail),
+                    lead: String::new(),
+                    core: unit.to_string(),
+                    trail: token.trail.clone(),
+                });
+            }
+            None => tokens.push(token),
+        }
+    }
+    tokens
+}
+
+/// Whether `tokens[index]` reads on from the token before it, with no
+/// punctuation in between.
+fn continues(tokens: &[Token], index: usize) -> bool {
+    index > 0
+        && tokens.get(index).is_some_and(|token| token.lead.is_empty())
+        && tokens[index - 1].trail.is_empty()
+}
+
+fn core_at(tokens: &[Token], index: usize) -> &str {
+    tokens.get(index).map_or("", |token| token.core.as_str())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NumberWord {
+    Zero,
+    Unit(u64),
+    Teen(u64),
+    Tens(u64),
+    Hundred,
+    Scale(u64),
+}
+
+const UNITS: [&str; 9] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+const UNIT_ORDINALS: [&str; 9] = [
+    "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+];
+const TEENS: [&str; 10] = [
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+const TEEN_ORDINALS: [&str; 10] = [
+    "tenth",
+    "eleventh",
+    "twelfth",
+    "thirteenth",
+    "fourteenth",
+    "fifteenth",
+    "sixteenth",
+    "seventeenth",
+    "eighteenth",
+    "nineteenth",
+];
+const TENS: [&str; 8] = [
+    "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+const TENS_ORDINALS: [&str; 8] = [
+    "twentieth",
+    "thirtieth",
+    "fortieth",
+    "fiftieth",
+    "sixtieth",
+    "seventieth",
+    "eightieth",
+    "ninetieth",
+];
+const SCALES: [(&str, &str, u64); 4] = [
+    ("thousand", "thousandth", 1_000),
+    ("million", "millionth", 1_000_000),
+    ("billion", "billionth", 1_000_000_000),
+    ("trillion", "trillionth", 1_000_000_000_000),
+];
+
+/// The number a word stands for and whether it is an ordinal.
+fn number_word(word: &str) -> Option<(NumberWord, bool)> {
+    let position = |list: &[&str]| list.iter().position(|entry| *entry == word);
+    if word == "zero" {
+        return Some((NumberWord::Zero, false));
+    }
+    if word == "hundred" || word == "hundredth" {
+        return Some((NumberWord::Hundred, word == "hundredth"));
+    }
+    if let Some(index) = position(&UNITS) {
+        return Some((NumberWord::Unit(index as u64 + 1), false));
+    }
+    if let Some(index) = position(&UNIT_ORDINALS) {
+        return Some((NumberWord::Unit(index as u64 + 1), true));
+    }
+    if let Some(index) = position(&TEENS) {
+        return Some((NumberWord::Teen(index as u64 + 10), false));
+    }
+    if let Some(index) = position(&TEEN_ORDINALS) {
+        return Some((NumberWord::Teen(index as u64 + 10), true));
+    }
+    if let Some(index) = position(&TENS) {
+        return Some((NumberWord::Tens(index as u64 * 10 + 20), false));
+    }
+    if let Some(index) = position(&TENS_ORDINALS) {
+        return Some((NumberWord::Tens(index as u64 * 10 + 20), true));
+    }
+    SCALES.iter().find_map(|(name, ordinal, value)| {
+        if word == *name {
+            Some((NumberWord::Scale(*value), false))
+        } else if word == *ordinal {
+            Some((NumberWord::Scale(*value), true))
+        } else {
+            None
+        }
+    })
+}
+
+pub fn is_number_word(word: &str) -> bool {
+    number_word(word).is_some()
+}
+
+/// A single spoken digit, as used after "point" or in "nineteen oh five".
+fn digit_word(word: &str) -> Option<u64> {
+    match word {
+        "zero" | "oh" | "o" => Some(0),
+        _ => match number_word(word) {
+            Some((NumberWord::Unit(value), false)) => Some(value),
+            _ => None,
+        },
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Last {
+    None,
+    Unit,
+    Teen,
+    Tens,
+    Hundred,
+    Scale,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cardinal {
+    value: u64,
+    ordinal: bool,
+    len: usize,
+}
+
+/// Reads the longest well-formed spoken integer starting at `start`, so
+/// "twenty twenty" stops after the first "twenty".
+fn parse_cardinal(tokens: &[Token], start: usize) -> Option<Cardinal> {
+    let mut total = 0u64;
+    let mut current = 0u64;
+    let mut last = Last::None;
+    let mut last_scale = u64::MAX;
+    let mut index = start;
+
+    while index < tokens.len() {
+        if index > start && !continues(tokens, index) {
+            break;
+        }
+        let core = core_at(tokens, index);
+        let next = number_word(core_at(tokens, index + 1));
+
+        if core == "a"
+            && last == Last::None
+            && matches!(core_at(tokens, index + 1), "hundred" | "thousand")
+            && continues(tokens, index + 1)
+        {
+            current = 1;
+            last = Last::Unit;
+            index += 1;
+            continue;
+        }
+        if core == "and"
+            && matches!(last, Last::Hundred | Last::Scale)
+            && continues(tokens, index + 1)
+            && matches!(
+                next,
+                Some((
+                    NumberWord::Unit(_) | NumberWord::Teen(_) | NumberWord::Tens(_),
+                    _
+                ))
+            )
+        {
+            index += 1;
+            continue;
+        }
+
+        let Some((word, ordinal)) = number_word(core) else {
+            break;
+        };
+        match (word, last) {
+            (NumberWord::Zero, Last::None) => {
+                return Some(Cardinal {
+                    value: 0,
+                    ordinal: false,
+                    len: 1,
+                });
+            }
+            (NumberWord::Unit(value), Last::None | Last::Tens | Last::Hundred | Last::Scale) => {
+                current += value;
+                last = Last::Unit;
+            }
+            (
+                NumberWord::Teen(value) | NumberWord::Tens(value),
+                Last::None | Last::Hundred | Last::Scale,
+            ) => {
+                current += value;
+                last = if matches!(word, NumberWord::Teen(_)) {
+                    Last::Teen
+                } else {
+                    Last::Tens
+                };
+            }
+            (NumberWord::Hundred, Last::Unit | Last::Teen) if current < 100 => {
+                current *= 100;
+                last = Last::Hundred;
+            }
+            (NumberWord::Scale(scale), Last::Unit | Last::Teen | Last::Tens | Last::Hundred)
+                if scale < last_scale =>
+            {
+                total += current * scale;
+                current = 0;
+                last_scale = scale;
+                last = Last::Scale;
+            }
+            _ => break,
+        }
+        index += 1;
+        if ordinal {
+            return Some(Cardinal {
+                value: total + current,
+                ordinal: true,
+                len: index - start,
+            });
+        }
+    }
+
+    (last != Last::None).then_some(Cardinal {
+        value: total + current,
+        ordinal: false,
+        len: index - start,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Number {
+    /// Written digits, with any decimal or version points.
+    digits: String,
+    /// The integer value, unless the number has a point in it.
+    value: Option<u64>,
+    ordinal: bool,
+    spoken: bool,
+    len: usize,
+}
+
+impl Number {
+    fn written(&self) -> String {
+        match self.value {
+            Some(value) if self.spoken => group_thousands(value),
+            _ => self.digits.clone(),
+        }
+    }
+}
+
+fn parse_number(tokens: &[Token], start: usize) -> Option<Number> {
+    let token = tokens.get(start)?;
+    if let Some(number) = digit_number(&token.core) {
+        return Some(number);
+    }
+
+    let cardinal = parse_cardinal(tokens, start)?;
+    let mut number = Number {
+        digits: cardinal.value.to_string(),
+        value: Some(cardinal.value),
+        ordinal: cardinal.ordinal,
+        spoken: true,
+        len: cardinal.len,
+    };
+    if cardinal.ordinal {
+        return Some(number);
+    }
+
+    let mut index = start + cardinal.len;
+    while core_at(tokens, index) == "point" && continues(tokens, index) {
+        let Some((fraction, len)) = parse_fraction(tokens, index + 1) else {
+            break;
+        };
+        number.digits.push('.');
+        number.digits.push_str(&fraction);
+        number.value = None;
+        index += 1 + len;
+    }
+    number.len = index - start;
+    Some(number)
+}
+
+/// The digits after "point": either a run of single digits ("one four")
+/// or a whole number, as in "version two point twelve".
+fn parse_fraction(tokens: &[Token], start: usize) -> Option<(String, usize)> {
+    if !continues(tokens, start) {
+        return None;
+    }
+    let mut digits = String::new();
+    let mut index = start;
+    while let Some(digit) = digit_word(core_at(tokens, index)) {
+        if index > start && !continues(tokens, index) {
+            break;
+        }
+        digits.push_str(&digit.to_string());
+        index += 1;
+    }
+    if !digits.is_empty() {
+        return Some((digits, index - start));
+    }
+    parse_cardinal(tokens, start)
+        .filter(|cardinal| !cardinal.ordinal)
+        .map(|cardinal| (cardinal.value.to_string(), cardinal.len))
+}
+
+/// Numbers whisper already wrote as digits: "42", "1,000", "3.5", "21st".
+fn digit_number(core: &str) -> Option<Number> {
+    let (digits, ordinal) = match ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|suffix| core.strip_suffix(suffix))
+    {
+        Some(digits) => (digits, true),
+        None => (core, false),
+    };
+    let well_formed = digits.starts_with(|c: char| c.is_ascii_digit())
+        && digits.ends_with(|c: char| c.is_ascii_digit())
+        && digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ',' || c == '.')
+        && digits.matches('.').count() <= 1;
+    if !well_formed || (ordinal && !digits.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    let value = if digits.contains('.') {
+        None
+    } else {
+        digits.replace(',', "").parse().ok()
+    };
+    Some(Number {
+        digits: core.to_string(),
+        value,
+        ordinal,
+        spoken: false,
+        len: 1,
+    })
+}
+
+fn group_thousands(value: u64) -> String {
+    let digits = value.to_string();
+    if value < 10_000 {
+        return digits;
+    }
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    let offset = digits.len() % 3;
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && index % 3 == offset {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+fn ordinal_suffix(value: u64) -> &'static str {
+    if (11..=13).contains(&(value % 100)) {
+        return "th";
+    }
+    match value % 10 {
+        1 => "st",
+        2 => "nd",
+        3 => "rd",
+        _ => "th",
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+fn month(word: &str) -> Option<&'static str> {
+    MONTHS
+        .iter()
+        .find(|month| month.eq_ignore_ascii_case(word))
+        .copied()
+}
+
+const CURRENCIES: [(&str, &str); 6] = [
+    ("dollar", "$"),
+    ("dollars", "$"),
+    ("euro", "€"),
+    ("euros", "€"),
+    ("yen", "¥"),
+    ("cents", "¢"),
+];
+
+/// Unit phrases and their symbols, longer phrases first.
+const UNITS_OF_MEASURE: &[(&str, &str)] = &[
+    ("degrees celsius", "°C"),
+    ("degrees centigrade", "°C"),
+    ("degrees fahrenheit", "°F"),
+    ("kilometers per hour", "km/h"),
+    ("kilometres per hour", "km/h"),
+    ("miles per hour", "mph"),
+    ("degree", "°"),
+    ("degrees", "°"),
+    ("kilometer", "km"),
+    ("kilometers", "km"),
+    ("kilometre", "km"),
+    ("kilometres", "km"),
+    ("meter", "m"),
+    ("meters", "m"),
+    ("metre", "m"),
+    ("metres", "m"),
+    ("centimeter", "cm"),
+    ("centimeters", "cm"),
+    ("centimetre", "cm"),
+    ("centimetres", "cm"),
+    ("millimeter", "mm"),
+    ("millimeters", "mm"),
+    ("millimetre", "mm"),
+    ("millimetres", "mm"),
+    ("kilogram", "kg"),
+    ("kilograms", "kg"),
+    ("gram", "g"),
+    ("grams", "g"),
+    ("milligrams", "mg"),
+    ("liter", "L"),
+    ("liters", "L"),
+    ("litre", "L"),
+    ("litres", "L"),
+    ("milliliters", "mL"),
+    ("millilitres", "mL"),
+    ("kilobytes", "KB"),
+    ("megabytes", "MB"),
+    ("gigabytes", "GB"),
+    ("terabytes", "TB"),
+    ("milliseconds", "ms"),
+    ("hertz", "Hz"),
+    ("kilohertz", "kHz"),
+    ("megahertz", "MHz"),
+    ("gigahertz", "GHz"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+struct Match {
+    text: String,
+    len: usize,
+}
+
+impl Match {
+    /// Wraps `written` in the punctuation around `tokens[start..start + len]`.
+    fn new(tokens: &[Token], start: usize, len: usize, written: String) -> Self {
+        let last = &tokens[start + len - 1];
+        let mut trail = last.trail.as_str();
+        // "p.m." keeps its own period unless it also ends the sentence.
+        if matches!(last.core.as_str(), "a.m" | "p.m") && start + len < tokens.len() {
+            trail = trail.strip_prefix('.').unwrap_or(trail);
+        }
+        Self {
+            text: format!("{}{}{}", tokens[start].lead, written, trail),
+            len,
+        }
+    }
+}
+
+fn normalize_english(line: &str) -> String {
+    let tokens = tokenize(line);
+    let mut output = Vec::with_capacity(tokens.len());
+    let mut index = 0;
+    while index < tokens.len() {
+        let matched = match_date(&tokens, index)
+            .or_else(|| match_time(&tokens, index))
+            .or_else(|| match_number(&tokens, index));
+        match matched {
+            Some(matched) => {
+                output.push(matched.text);
+                index += matched.len;
+            }
+            None => {
+                output.push(tokens[index].raw.clone());
+                index += 1;
+            }
+        }
+    }
+    output.join(" ")
+}
+
+/// "March fifth", "March the fifth, twenty twenty four", "the fifth of
+/// March" and "March twenty twenty four".
+fn match_date(tokens: &[Token], start: usize) -> Option<Match> {
+    if let Some(name) = month(core_at(tokens, start)) {
+        if continues(tokens, start + 1) {
+            if let Some((year, len)) = parse_year(tokens, start + 1, 10..=20) {
+                return Some(Match::new(tokens, start, 1 + len, format!("{name} {year}")));
+            }
+        }
+        let mut index = start + 1;
+        if core_at(tokens, index) == "the" && continues(tokens, index) {
+            index += 1;
+        }
+        // "may" and "march" are also verbs, so they need an ordinal day.
+        let needs_ordinal = index > start + 1 || matches!(name, "May" | "March");
+        if continues(tokens, index) {
+            if let Some(day) = parse_day(tokens, index, needs_ordinal) {
+                let end = index + day.len;
+                return Some(date_with_year(tokens, start, end, name, day.value));
+            }
+        }
+        return None;
+    }
+
+    let mut index = start;
+    if core_at(tokens, index) == "the" {
+        index += 1;
+        if !continues(tokens, index) {
+            return None;
+        }
+    }
+    let day = parse_day(tokens, index, true)?;
+    let of = index + day.len;
+    if core_at(tokens, of) != "of" || !continues(tokens, of) || !continues(tokens, of + 1) {
+        return None;
+    }
+    let name = month(core_at(tokens, of + 1))?;
+    Some(date_with_year(tokens, start, of + 2, name, day.value))
+}
+
+fn date_with_year(tokens: &[Token], start: usize, end: usize, name: &str, day: u64) -> Match {
+    let comma_only =
+        tokens[end - 1].trail == "," && tokens.get(end).is_some_and(|token| token.lead.is_empty());
+    let year = (comma_only || continues(tokens, end))
+        .then(|| parse_year(tokens, end, 10..=20))
+        .flatten();
+    match year {
+        Some((year, len)) => {
+            let written = format!("{name} {day}, {year}");
+            Match::new(tokens, start, end - start + len, written)
+        }
+        None => Match::new(tokens, start, end - start, format!("{name} {day}")),
+    }
+}
+
+fn parse_day(tokens: &[Token], start: usize, needs_ordinal: bool) -> Option<Cardinal> {
+    let number = parse_number(tokens, start)?;
+    let value = number.value.filter(|day| (1..=31).contains(day))?;
+    (number.ordinal || !needs_ordinal).then_some(Cardinal {
+        value,
+        ordinal: number.ordinal,
+        len: number.len,
+    })
+}
+
+/// A year written as digits, said as a whole number ("two thousand five")
+/// or said in pairs ("nineteen eighty four", "twenty oh nine"). Pairs only
+/// count when the first half is in `centuries`.
+fn parse_year(
+    tokens: &[Token],
+    start: usize,
+    centuries: std::ops::RangeInclusive<u64>,
+) -> Option<(u64, usize)> {
+    let token = tokens.get(start)?;
+    if token.core.len() == 4 {
+        if let Some(year) = digit_number(&token.core).and_then(|number| number.value) {
+            return (1000..=2999).contains(&year).then_some((year, 1));
+        }
+    }
+
+    let century = match number_word(&token.core) {
+        Some((NumberWord::Teen(value) | NumberWord::Tens(value), false)) => Some(value),
+        _ => None,
+    }
+    .filter(|century| centuries.contains(century));
+    if let Some(century) = century.filter(|_| continues(tokens, start + 1)) {
+        let second = core_at(tokens, start + 1);
+        if matches!(second, "oh" | "o") && continues(tokens, start + 2) {
+            if let Some((NumberWord::Unit(unit), false)) = number_word(core_at(tokens, start + 2)) {
+                return Some((century * 100 + unit, 3));
+            }
+        }
+        if matches!(
+            number_word(second),
+            Some((NumberWord::Teen(_) | NumberWord::Tens(_), false))
+        ) {
+            if let Some(rest) = parse_cardinal(tokens, start + 1) {
+                if !rest.ordinal && rest.value < 100 {
+                    return Some((century * 100 + rest.value, 1 + rest.len));
+                }
+            }
+        }
+    }
+
+    parse_cardinal(tokens, start)
+        .filter(|cardinal| !cardinal.ordinal && (1000..=2999).contains(&cardinal.value))
+        .filter(|cardinal| {
+            // Keep "two thousand" from swallowing the year of a pair form.
+            cardinal.value % 1000 != 0 || cardinal.len <= 2
+        })
+        .map(|cardinal| (cardinal.value, cardinal.len))
+}
+
+/// "three pm", "ten thirty a.m.", "seven oh five PM", "3:30 pm" and
+/// "four o'clock".
+fn match_time(tokens: &[Token], start: usize) -> Option<Match> {
+    let (hour, minute, len) = match parse_clock(core_at(tokens, start)) {
+        Some((hour, minute)) => (hour, Some(minute), 1),
+        None => {
+            let hour = parse_cardinal(tokens, start)
+                .filter(|hour| !hour.ordinal && (1..=12).contains(&hour.value))?;
+            let mut index = start + hour.len;
+            let mut minute = None;
+            if continues(tokens, index) {
+                if matches!(core_at(tokens, index), "oh" | "o") && continues(tokens, index + 1) {
+                    if let Some((NumberWord::Unit(unit), false)) =
+                        number_word(core_at(tokens, index + 1))
+                    {
+                        minute = Some(unit);
+                        index += 2;
+                    }
+                } else if let Some(minutes) = parse_cardinal(tokens, index)
+                    .filter(|minutes| !minutes.ordinal && (10..=59).contains(&minutes.value))
+                {
+                    minute = Some(minutes.value);
+                    index += minutes.len;
+                }
+            }
+            (hour.value, minute, index - start)
+        }
+    };
+
+    let marker = start + len;
+    if !continues(tokens, marker) {
+        return None;
+    }
+    let written = match core_at(tokens, marker) {
+        "am" | "a.m" => with_minutes(hour, minute) + " AM",
+        "pm" | "p.m" => with_minutes(hour, minute) + " PM",
+        "o'clock" if minute.is_none() => format!("{hour}:00"),
+        _ => return None,
+    };
+    Some(Match::new(tokens, start, len + 1, written))
+}
+
+fn with_minutes(hour: u64, minute: Option<u64>) -> String {
+    match minute {
+        Some(minute) => format!("{hour}:{minute:02}"),
+        None => hour.to_string(),
+    }
+}
+
+fn parse_clock(core: &str) -> Option<(u64, u64)> {
+    let (hour, minute) = core.split_once(':')?;
+    if minute.len() != 2 {
+        return None;
+    }
+    let hour: u64 = hour.parse().ok()?;
+    let minute: u64 = minute.parse().ok()?;
+    ((1..=12).contains(&hour) && minute < 60).then_some((hour, minute))
+}
+
+/// Plain numbers and numbers followed by "percent", a currency or a unit.
+fn match_number(tokens: &[Token], start: usize) -> Option<Match> {
+    if let Some((year, len)) = parse_year(tokens, start, 19..=20).filter(|(_, len)| *len > 1) {
+        let cardinal_len = parse_cardinal(tokens, start).map_or(0, |cardinal| cardinal.len);
+        if cardinal_len < len {
+            return Some(Match::new(tokens, start, len, year.to_string()));
+        }
+    }
+
+    let number = parse_number(tokens, start)?;
+    let after = start + number.len;
+    if number.ordinal {
+        let value = number.value?;
+        if !number.spoken || (value < 10 && number.len == 1) {
+            return None;
+        }
+        let written = format!("{value}{}", ordinal_suffix(value));
+        return Some(Match::new(tokens, start, number.len, written));
+    }
+
+    if continues(tokens, after) {
+        if let Some(matched) = match_suffix(tokens, start, &number) {
+            return Some(matched);
+        }
+    }
+
+    let after_version = start > 0 && core_at(tokens, start - 1) == "version";
+    let small = number.value.is_some_and(|value| value < 10);
+    if !number.spoken || (small && !after_version) {
+        return None;
+    }
+    Some(Match::new(tokens, start, number.len, number.written()))
+}
+
+fn match_suffix(tokens: &[Token], start: usize, number: &Number) -> Option<Match> {
+    let after = start + number.len;
+    let written = number.written();
+    let word = core_at(tokens, after);
+
+    if word == "percent" {
+        return Some(Match::new(tokens, start, number.len + 1, written + "%"));
+    }
+    if word == "per" && core_at(tokens, after + 1) == "cent" && continues(tokens, after + 1) {
+        return Some(Match::new(tokens, start, number.len + 2, written + "%"));
+    }
+
+    if let Some((_, symbol)) = CURRENCIES.iter().find(|(name, _)| *name == word) {
+        if *symbol == "¢" {
+            return Some(Match::new(tokens, start, number.len + 1, written + "¢"));
+        }
+        let len = number.len + 1;
+        if let Some((cents, cents_len)) = number.value.and_then(|_| parse_cents(tokens, after + 1))
+        {
+            let amount = format!("{symbol}{written}.{cents:02}");
+            return Some(Match::new(tokens, start, len + cents_len, amount));
+        }
+        return Some(Match::new(tokens, start, len, format!("{symbol}{written}")));
+    }
+
+    UNITS_OF_MEASURE
+        .iter()
+        .map(|(phrase, symbol)| (phrase.split(' ').collect::<Vec<_>>(), symbol))
+        .find(|(words, _)| {
+            words.iter().enumerate().all(|(offset, word)| {
+                core_at(tokens, after + offset) == *word
+                    && (offset == 0 || continues(tokens, after + offset))
+            })
+        })
+        .map(|(words, symbol)| {
+            let separator = if symbol.starts_with('°') { "" } else { " " };
+            let written = format!("{written}{separator}{symbol}");
+            Match::new(tokens, start, number.len + words.len(), written)
+        })
+}
+
+/// "and fifty cents" after a whole currency amount.
+fn parse_cents(tokens: &[Token], start: usize) -> Option<(u64, usize)> {
+    if core_at(tokens, start) != "and" || !continues(tokens, start) {
+        return None;
+    }
+    let cents = parse_number(tokens, start + 1)
+        .filter(|cents| continues(tokens, start + 1) && !cents.ordinal)?;
+    let value = cents.value.filter(|value| (1..=99).contains(value))?;
+    let word = start + 1 + cents.len;
+    (matches!(core_at(tokens, word), "cent" | "cents") && continues(tokens, word))
+        .then_some((value, cents.len + 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_english_table() {
+        let cases = [
+            // Cardinals
+            ("twenty three", "23"),
+            ("twenty-three apples", "23 apples"),
+            ("one hundred", "100"),
+            ("a hundred people", "100 people"),
+            ("three hundred and twelve", "312"),
+            ("two thousand", "2000"),
+            ("twelve thousand five hundred", "12,500"),
+            ("one million two hundred thousand", "1,200,000"),
+            ("nineteen hundred", "1900"),
+            ("seven", "seven"),
+            ("one of them", "one of them"),
+            ("zero", "zero"),
+            ("ten", "10"),
+            ("it costs 42", "it costs 42"),
+            ("bread and butter", "bread and butter"),
+            ("a hundred and five", "105"),
+            ("four hundred and", "400 and"),
+            ("twenty, thirty", "20, 30"),
+            // Ordinals
+            ("the first time", "the first time"),
+            ("the twenty first century", "the 21st century"),
+            ("the twenty-second floor", "the 22nd floor"),
+            ("eleventh hour", "11th hour"),
+            ("the one hundredth visitor", "the 100th visitor"),
+            ("one hundred and third", "103rd"),
+            ("thirteenth", "13th"),
+            // Decimals
+            ("three point one four", "3.14"),
+            ("zero point five", "0.5"),
+            ("one point oh five", "1.05"),
+            ("the point is", "the point is"),
+            ("ten point", "10 point"),
+            // Percentages
+            ("twenty three point five percent", "23.5%"),
+            ("five percent", "5%"),
+            ("fifty per cent", "50%"),
+            ("100 percent", "100%"),
+            // Currency
+            ("twenty dollars", "$20"),
+            ("one dollar", "$1"),
+            ("twenty three dollars and fifty cents", "$23.50"),
+            ("five dollars and five cents", "$5.05"),
+            ("fifty cents", "50¢"),
+            ("ten euros", "€10"),
+            ("a thousand yen", "¥1000"),
+            ("two million dollars", "$2,000,000"),
+            // Times
+            ("three pm", "3 PM"),
+            ("at three thirty p.m. today", "at 3:30 PM today"),
+            ("seven oh five AM", "7:05 AM"),
+            ("twelve fifteen am", "12:15 AM"),
+            ("3:30 pm", "3:30 PM"),
+            ("four o'clock", "4:00"),
+            ("meet at ten p.m.", "meet at 10 PM."),
+            ("thirteen pm", "13 pm"),
+            // Dates
+            ("the fifth of march", "March 5"),
+            ("the fifth of march at three pm", "March 5 at 3 PM"),
+            ("march fifth", "March 5"),
+            ("march five", "march five"),
+            (
+                "january twenty first, twenty twenty four",
+                "January 21, 2024",
+            ),
+            ("july fourth nineteen seventy six", "July 4, 1976"),
+            ("june the third", "June 3"),
+            ("june three", "June 3"),
+            ("may the fourth be with you", "May 4 be with you"),
+            ("you may go", "you may go"),
+            ("december twenty twenty", "December 2020"),
+            (
+                "on the thirty first of december, 1999",
+                "on December 31, 1999",
+            ),
+            ("the second of august two thousand five", "August 2, 2005"),
+            ("october 3rd", "October 3"),
+            ("march fifth, then", "March 5, then"),
+            ("the fortieth of may", "the 40th of may"),
+            // Years
+            ("in nineteen eighty four", "in 1984"),
+            ("twenty twenty four", "2024"),
+            ("nineteen oh five", "1905"),
+            ("twenty one", "21"),
+            // Versions
+            ("version two point one", "version 2.1"),
+            ("version two point one point three", "version 2.1.3"),
+            ("version two", "version 2"),
+            ("python three point twelve", "python 3.12"),
+            // Units
+            ("five kilometers", "5 km"),
+            ("two hundred grams of flour", "200 g of flour"),
+            ("one point five liters", "1.5 L"),
+            ("sixteen gigabytes", "16 GB"),
+            ("ninety degrees", "90°"),
+            ("twenty degrees celsius", "20°C"),
+            ("sixty miles per hour", "60 mph"),
+            ("two point four gigahertz", "2.4 GHz"),
+            ("three hundred milliseconds", "300 ms"),
+            // Punctuation and casing
+            ("Twenty three.", "23."),
+            ("(twenty three)", "(23)"),
+            ("about fifty, maybe sixty.", "about 50, maybe 60."),
+            ("It's Five Percent!", "It's 5%!"),
+            ("\"twenty dollars\"", "\"$20\""),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(normalize(input, "en"), expected, "input: {input:?}");
+        }
+    }
+
+    #[test]
+    fn test_normalize_keeps_line_breaks_and_tabs() {
+        assert_eq!(
+            normalize("twenty dollars\nthirty\tforty", "en"),
+            "$20\n30\t40"
+        );
+        assert_eq!(normalize("ten\n", "en"), "10\n");
+        assert_eq!(normalize("", "en"), "");
+        assert_eq!(normalize(" twenty ", "en"), " 20 ");
+    }
+
+    #[test]
+    fn test_normalize_matches_language_variants() {
+        assert_eq!(normalize("twenty percent", "en-US"), "20%");
+        assert_eq!(normalize("twenty percent", "EN_gb"), "20%");
+    }
+
+    #[test]
+    fn test_normalize_leaves_other_languages_unchanged() {
+        assert_eq!(normalize("twenty percent", "de"), "twenty percent");
+        assert_eq!(normalize("vingt pour cent", "fr"), "vingt pour cent");
+    }
+
+    #[test]
+    fn test_is_supported() {
+        assert!(is_supported("en"));
+        assert!(is_supported("en-US"));
+        assert!(!is_supported("de"));
+        assert!(!is_supported(""));
+    }
+
+    #[test]
+    fn test_group_thousands() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(9999), "9999");
+        assert_eq!(group_thousands(10_000), "10,000");
+        assert_eq!(group_thousands(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn test_ordinal_suffix() {
+        let cases = [
+            (1, "st"),
+            (2, "nd"),
+            (3, "rd"),
+            (4, "th"),
+            (11, "th"),
+            (12, "th"),
+            (13, "th"),
+            (21, "st"),
+            (102, "nd"),
+            (111, "th"),
+        ];
+        for (value, suffix) in cases {
+            assert_eq!(ordinal_suffix(value), suffix, "value: {value}");
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use serde::{Deserialize, Serialize};
+
+/// The keyboard layout the user types on. Layouts are the standard PC
+/// variants; characters they can only produce with AltGr or a dead key are
+/// typed as Unicode instead.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyboardLayout {
+    #[default]
+    Us,
+    Uk,
+    De,
+    Fr,
+    Dvorak,
+}
+
+/// A physical key on an ISO board, by row (0 is the number row) and column.
+/// Column 0 of the bottom row is the ISO key left of Z, which ANSI boards
+/// don't have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPosition {
+    pub row: u8,
+    pub column: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanKey {
+    Shift,
+    Return,
+    Tab,
+    Space,
+    Position(KeyPosition),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEvent {
+    Press(PlanKey),
+    Release(PlanKey),
+    Click(PlanKey),
+    /// Text the layout has no plain or shifted key for.
+    Unicode(char),
+}
+
+/// Unused slots in a row are `\0`.
+struct Row {
+    base: &'static str,
+    shifted: &'static str,
+}
+
+const fn row(base: &'static str, shifted: &'static str) -> Row {
+    Row { base, shifted }
+}
+
+const US: [Row; 4] = [
+    row("`1234567890-=", "~!@#$%^&*()_+"),
+    row("qwertyuiop[]\\", "QWERTYUIOP{}|"),
+    row("asdfghjkl;'", "ASDFGHJKL:\""),
+    row("\0zxcvbnm,./", "\0ZXCVBNM<>?"),
+];
+
+const UK: [Row; 4] = [
+    row("`1234567890-=", "¬!\"£$%^&*()_+"),
+    row("qwertyuiop[]", "QWERTYUIOP{}"),
+    row("asdfghjkl;'#", "ASDFGHJKL:@~"),
+    row("\\zxcvbnm,./", "|ZXCVBNM<>?"),
+];
+
+const DE: [Row; 4] = [
+    row("^1234567890ß´", "°!\"§$%&/()=?`"),
+    row("qwertzuiopü+", "QWERTZUIOPÜ*"),
+    row("asdfghjklöä#", "ASDFGHJKLÖÄ'"),
+    row("<yxcvbnm,.-", ">YXCVBNM;:_"),
+];
+
+const FR: [Row; 4] = [
+    row("²&é\"'(-è_çà)=", "\u{0}1234567890°+"),
+    row("azertyuiop^$", "AZERTYUIOP¨£"),
+    row("qsdfghjklmù*", "QSDFGHJKLM%µ"),
+    row("<wxcvbn,;:!", ">WXCVBN?./§"),
+];
+
+const DVORAK: [Row; 4] = [
+    row("`1234567890[]", "~!@#$%^&*(){}"),
+    row("',.pyfgcrl/=\\", "\"<>PYFGCRL?+|"),
+    row("aoeuidhtns-", "AOEUIDHTNS_"),
+    row("\0;qjkxbmwvz", "\0:QJKXBMWVZ"),
+];
+
+impl KeyboardLayout {
+    fn rows(self) -> &'static [Row; 4] {
+        match self {
+            KeyboardLayout::Us => &US,
+            KeyboardLayout::Uk => &UK,
+            KeyboardLayout::De => &DE,
+            KeyboardLayout::Fr => &FR,
+            KeyboardLayout::Dvorak => &DVORAK,
+        }
+    }
+
+    /// Keys that start a composition instead of typing their character.
+    fn dead_keys(self) -> &'static [char] {
+        match self {
+            KeyboardLayout::De => &['^', '´', '`'],
+            KeyboardLayout::Fr => &['^', '¨'],
+            _ => &[],
+        }
+    }
+
+    /// The key that types `c`, and whether it needs Shift.
+    pub fn locate(self, c: char) -> Option<(KeyPosition, bool)> {
+        if c == '\0' || self.dead_keys().contains(&c) {
+            return None;
+        }
+        for (row, keys) in self.rows().iter().enumerate() {
+            for (shifted, chars) in [(false, keys.base), (true, keys.shifted)] {
+                if let Some(column) = chars.chars().position(|k| k == c) {
+                    let position = KeyPosition {
+                        row: row as u8,
+                        column: column as u8,
+                    };
+                    return Some((position, shifted));
+                }
+            }
+        }
+        None
+    }
+
+    /// The character a key types, with or without Shift.
+    pub fn char_at(self, position: KeyPosition, shifted: bool) -> Option<char> {
+        let row = self.rows().get(position.row as usize)?;
+        let chars = if shifted { row.shifted } else { row.base };
+        chars
+            .chars()
+            .nth(position.column as usize)
+            .filter(|&c| c != '\0')
+    }
+}
+
+/// The key events that type `text` on `layout`. Shift is held across runs
+/// of shifted characters and always released by the end of the plan.
+pub fn plan_keystrokes(text: &str, layout: KeyboardLayout) -> Vec<KeyEvent> {
+    let mut events = Vec::with_capacity(text.len() * 2);
+    let mut shift_held = false;
+    for c in text.chars() {
+        let step = match c {
+            '\n' => Some((PlanKey::Return, false)),
+            '\t' => Some((PlanKey::Tab, false)),
+            ' ' => Some((PlanKey::Space, false)),
+            _ => layout
+                .locate(c)
+                .map(|(position, shifted)| (PlanKey::Position(position), shifted)),
+        };
+        let shift = matches!(step, Some((_, true)));
+        if shift != shift_held {
+            events.push(if shift {
+                KeyEvent::Press(PlanKey::Shift)
+            } else {
+                KeyEvent::Release(PlanKey::Shift)
+            });
+            shift_held = shift;
+        }
+        events.push(match step {
+            Some((key, _)) => KeyEvent::Click(key),
+            None => KeyEvent::Unicode(c),
+        });
+    }
+    if shift_held {
+        events.push(KeyEvent::Release(PlanKey::Shift));
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use KeyEvent::{Click, Press, Release, Unicode};
+
+    fn key(layout: KeyboardLayout, c: char) -> PlanKey {
+        let (position, _) = layout.locate(c).expect("key exists");
+        PlanKey::Position(position)
+    }
+
+    #[test]
+    fn test_shifted_symbol_uses_base_key() {
+        let us = KeyboardLayout::Us;
+        assert_eq!(
+            plan_keystrokes("!", us),
+            vec![
+                Press(PlanKey::Shift),
+                Click(key(us, '1')),
+                Release(PlanKey::Shift)
+            ]
+        );
+        assert_eq!(
+            plan_keystrokes("?", us),
+            vec![
+                Press(PlanKey::Shift),
+                Click(key(us, '/')),
+                Release(PlanKey::Shift)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shift_is_held_across_a_run() {
+        let us = KeyboardLayout::Us;
+        assert_eq!(
+            plan_keystrokes("Hi OK!", us),
+            vec![
+                Press(PlanKey::Shift),
+                Click(key(us, 'h')),
+                Release(PlanKey::Shift),
+                Click(key(us, 'i')),
+                Click(PlanKey::Space),
+                Press(PlanKey::Shift),
+                Click(key(us, 'o')),
+                Click(key(us, 'k')),
+                Click(key(us, '1')),
+                Release(PlanKey::Shift),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plain_text_and_whitespace() {
+        let us = KeyboardLayout::Us;
+        assert_eq!(
+            plan_keystrokes("a.\n\tb", us),
+            vec![
+                Click(key(us, 'a')),
+                Click(key(us, '.')),
+                Click(PlanKey::Return),
+                Click(PlanKey::Tab),
+                Click(key(us, 'b')),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_characters_fall_back_to_unicode() {
+        assert_eq!(
+            plan_keystrokes("É€", KeyboardLayout::Us),
+            vec![Unicode('É'), Unicode('€')]
+        );
+        // Shift is released before Unicode text so it can't modify it.
+        assert_eq!(
+            plan_keystrokes("Aé", KeyboardLayout::Us),
+            vec![
+                Press(PlanKey::Shift),
+                Click(key(KeyboardLayout::Us, 'a')),
+                Release(PlanKey::Shift),
+                Unicode('é'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_layout_positions() {
+        let at = |row, column| KeyPosition { row, column };
+        let cases = [
+            (KeyboardLayout::Us, '!', at(0, 1), true),
+            (KeyboardLayout::Us, '"', at(2, 10), true),
+            (KeyboardLayout::Uk, '"', at(0, 2), true),
+            (KeyboardLayout::Uk, '@', at(2, 10), true),
+            (KeyboardLayout::Uk, '#', at(2, 11), false),
+            (KeyboardLayout::Uk, '\\', at(3, 0), false),
+            (KeyboardLayout::De, 'z', at(1, 5), false),
+            (KeyboardLayout::De, 'y', at(3, 1), false),
+            (KeyboardLayout::De, '(', at(0, 8), true),
+            (KeyboardLayout::De, '-', at(3, 10), false),
+            (KeyboardLayout::Fr, 'a', at(1, 0), false),
+            (KeyboardLayout::Fr, 'q', at(2, 0), false),
+            (KeyboardLayout::Fr, '1', at(0, 1), true),
+            (KeyboardLayout::Fr, '!', at(3, 10), false),
+            (KeyboardLayout::Fr, 'é', at(0, 2), false),
+            (KeyboardLayout::Dvorak, 'q', at(3, 2), false),
+            (KeyboardLayout::Dvorak, 'e', at(2, 2), false),
+            (KeyboardLayout::Dvorak, '?', at(1, 10), true),
+            (KeyboardLayout::Dvorak, 'Z', at(3, 10), true),
+        ];
+        for (layout, c, position, shifted) in cases {
+            assert_eq!(
+                layout.locate(c),
+                Some((position, shifted)),
+                "{layout:?} {c:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_altgr_and_dead_keys_are_unicode() {
+        assert_eq!(KeyboardLayout::De.locate('@'), None);
+        assert_eq!(KeyboardLayout::De.locate('^'), None);
+        assert_eq!(KeyboardLayout::De.locate('`'), None);
+        assert_eq!(KeyboardLayout::Fr.locate('^'), None);
+        assert_eq!(KeyboardLayout::Us.locate('\0'), None);
+        assert_eq!(
+            plan_keystrokes("a@b", KeyboardLayout::De),
+            vec![
+                Click(key(KeyboardLayout::De, 'a')),
+                Unicode('@'),
+                Click(key(KeyboardLayout::De, 'b')),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_every_shifted_char_round_trips() {
+        let layouts = [
+            KeyboardLayout::Us,
+            KeyboardLayout::Uk,
+            KeyboardLayout::De,
+            KeyboardLayout::Fr,
+            KeyboardLayout::Dvorak,
+        ];
+        for layout in layouts {
+            for row in layout.rows() {
+                assert_eq!(row.base.chars().count(), row.shifted.chars().count());
+            }
+            for c in "abcxyz".chars() {
+                let (position, shifted) = layout.locate(c).expect("letter");
+                assert!(!shifted);
+                assert_eq!(layout.char_at(position, false), Some(c));
+                let upper = c.to_ascii_uppercase();
+                assert_eq!(layout.locate(upper), Some((position, true)));
+                assert_eq!(layout.char_at(position, true), Some(upper));
+            }
+        }
+    }
+}
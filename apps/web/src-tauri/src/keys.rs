@@ -1,3 +1,5 @@
+use crate::type_::ModifierKey;
+use crate::voice_commands::{KeyChord, NamedKey};
 use parking_lot::RwLock;
 use rdev::{listen, EventType};
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
@@ -20,11 +22,18 @@ pub struct Keys {
     meta_left: AtomicBool,
     meta_right: AtomicBool,
     alt_pressed: AtomicU8,
+    control_pressed: AtomicU8,
+    shift_pressed: AtomicU8,
     state: RwLock<ActivationState>,
     source: RwLock<Option<ActivationSource>>,
     enabled_left: AtomicBool,
     enabled_right: AtomicBool,
     callback: RwLock<Option<Box<dyn Fn(ActivationState, Option<ActivationSource>) + Send + Sync>>>,
+    cancel_chord: RwLock<Option<KeyChord>>,
+    cancel_callback: RwLock<Option<Box<dyn Fn() + Send + Sync>>>,
+    scratch_chord: RwLock<Option<KeyChord>>,
+    scratch_callback: RwLock<Option<Box<dyn Fn() + Send + Sync>>>,
+    scratch_pending: AtomicBool,
 }
 
 impl Keys {
@@ -33,11 +42,18 @@ impl Keys {
             meta_left: AtomicBool::new(false),
             meta_right: AtomicBool::new(false),
             alt_pressed: AtomicU8::new(0),
+            control_pressed: AtomicU8::new(0),
+            shift_pressed: AtomicU8::new(0),
             state: RwLock::new(ActivationState::Inactive),
             source: RwLock::new(None),
             enabled_left: AtomicBool::new(true),
             enabled_right: AtomicBool::new(true),
             callback: RwLock::new(None),
+            cancel_chord: RwLock::new(None),
+            cancel_callback: RwLock::new(None),
+            scratch_chord: RwLock::new(None),
+            scratch_callback: RwLock::new(None),
+            scratch_pending: AtomicBool::new(false),
         }
     }
 
@@ -53,6 +69,54 @@ impl Keys {
         *self.callback.write() = Some(callback);
     }
 
+    /// The chord that cancels typing under way; `None` turns it off.
+    pub fn set_cancel_chord(&self, chord: Option<KeyChord>) {
+        *self.cancel_chord.write() = chord;
+    }
+
+    pub fn on_cancel(&self, callback: Box<dyn Fn() + Send + Sync>) {
+        *self.cancel_callback.write() = Some(callback);
+    }
+
+    /// The chord that removes the last dictation; `None` turns it off.
+    pub fn set_scratch_chord(&self, chord: Option<KeyChord>) {
+        *self.scratch_chord.write() = chord;
+    }
+
+    pub fn on_scratch(&self, callback: Box<dyn Fn() + Send + Sync>) {
+        *self.scratch_callback.write() = Some(callback);
+    }
+
+    fn held_modifiers(&self) -> Vec<ModifierKey> {
+        let mut held = Vec::new();
+        if self.control_pressed.load(Ordering::SeqCst) > 0 {
+            held.push(ModifierKey::Control);
+        }
+        if self.meta_left.load(Ordering::SeqCst) || self.meta_right.load(Ordering::SeqCst) {
+            held.push(ModifierKey::Meta);
+        }
+        if self.shift_pressed.load(Ordering::SeqCst) > 0 {
+            held.push(ModifierKey::Shift);
+        }
+        if self.alt_pressed.load(Ordering::SeqCst) > 0 {
+            held.push(ModifierKey::Alt);
+        }
+        held
+    }
+
+    fn is_chord(&self, chord: &RwLock<Option<KeyChord>>, key: rdev::Key) -> bool {
+        let chord = chord.read();
+        let Some(chord) = chord.as_ref() else {
+            return false;
+        };
+        let held = self.held_modifiers();
+        named_key(key) == Some(chord.key)
+            && held.len() == chord.modifiers.len()
+            && held
+                .iter()
+                .all(|modifier| chord.modifiers.contains(modifier))
+    }
+
     fn check_chord(&self) -> Option<ActivationSource> {
         let meta_left = self.meta_left.load(Ordering::SeqCst);
         let meta_right = self.meta_right.load(Ordering::SeqCst);
@@ -72,6 +136,30 @@ impl Keys {
     }
 
     fn handle_key(&self, key: rdev::Key, pressed: bool) {
+        if pressed && self.is_chord(&self.cancel_chord, key) {
+            if let Some(ref cb) = *self.cancel_callback.read() {
+                cb();
+            }
+        }
+        if pressed && self.is_chord(&self.scratch_chord, key) {
+            self.scratch_pending.store(true, Ordering::SeqCst);
+        }
+
+        self.track_key(key, pressed);
+
+        // Scratching waits for the chord to be let go, so its modifiers
+        // can't turn the backspaces it sends into something else.
+        if !pressed
+            && self.held_modifiers().is_empty()
+            && self.scratch_pending.swap(false, Ordering::SeqCst)
+        {
+            if let Some(ref cb) = *self.scratch_callback.read() {
+                cb();
+            }
+        }
+    }
+
+    fn track_key(&self, key: rdev::Key, pressed: bool) {
         match key {
             rdev::Key::MetaLeft => self.meta_left.store(pressed, Ordering::SeqCst),
             rdev::Key::MetaRight => self.meta_right.store(pressed, Ordering::SeqCst),
@@ -82,6 +170,14 @@ impl Keys {
                     self.alt_pressed.fetch_sub(1, Ordering::SeqCst);
                 }
             }
+            rdev::Key::ControlLeft | rdev::Key::ControlRight => {
+                track_held(&self.control_pressed, pressed);
+                return;
+            }
+            rdev::Key::ShiftLeft | rdev::Key::ShiftRight => {
+                track_held(&self.shift_pressed, pressed);
+                return;
+            }
             _ => return,
         }
 
@@ -130,6 +226,99 @@ impl Keys {
     }
 }
 
+/// Counts presses of a modifier that has keys on both sides. Releases with
+/// nothing counted, such as after a missed press, are ignored.
+fn track_held(count: &AtomicU8, pressed: bool) {
+    if pressed {
+        count.fetch_add(1, Ordering::SeqCst);
+    } else {
+        let _ = count.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |held| {
+            held.checked_sub(1)
+        });
+    }
+}
+
+fn named_key(key: rdev::Key) -> Option<NamedKey> {
+    use rdev::Key as K;
+    let named = match key {
+        K::Escape => NamedKey::Escape,
+        K::Return | K::KpReturn => NamedKey::Enter,
+        K::Tab => NamedKey::Tab,
+        K::Space => NamedKey::Space,
+        K::Backspace => NamedKey::Backspace,
+        K::Delete => NamedKey::Delete,
+        K::UpArrow => NamedKey::Up,
+        K::DownArrow => NamedKey::Down,
+        K::LeftArrow => NamedKey::Left,
+        K::RightArrow => NamedKey::Right,
+        K::Home => NamedKey::Home,
+        K::End => NamedKey::End,
+        K::PageUp => NamedKey::PageUp,
+        K::PageDown => NamedKey::PageDown,
+        K::F1 => NamedKey::Function(1),
+        K::F2 => NamedKey::Function(2),
+        K::F3 => NamedKey::Function(3),
+        K::F4 => NamedKey::Function(4),
+        K::F5 => NamedKey::Function(5),
+        K::F6 => NamedKey::Function(6),
+        K::F7 => NamedKey::Function(7),
+        K::F8 => NamedKey::Function(8),
+        K::F9 => NamedKey::Function(9),
+        K::F10 => NamedKey::Function(10),
+        K::F11 => NamedKey::Function(11),
+        K::F12 => NamedKey::Function(12),
+        K::Dot => NamedKey::Char('.'),
+        K::Comma => NamedKey::Char(','),
+        K::Slash => NamedKey::Char('/'),
+        K::SemiColon => NamedKey::Char(';'),
+        K::Minus => NamedKey::Char('-'),
+        K::Equal => NamedKey::Char('='),
+        other => {
+            let letters = [
+                (K::KeyA, 'a'),
+                (K::KeyB, 'b'),
+                (K::KeyC, 'c'),
+                (K::KeyD, 'd'),
+                (K::KeyE, 'e'),
+                (K::KeyF, 'f'),
+                (K::KeyG, 'g'),
+                (K::KeyH, 'h'),
+                (K::KeyI, 'i'),
+                (K::KeyJ, 'j'),
+                (K::KeyK, 'k'),
+                (K::KeyL, 'l'),
+                (K::KeyM, 'm'),
+                (K::KeyN, 'n'),
+                (K::KeyO, 'o'),
+                (K::KeyP, 'p'),
+                (K::KeyQ, 'q'),
+                (K::KeyR, 'r'),
+                (K::KeyS, 's'),
+                (K::KeyT, 't'),
+                (K::KeyU, 'u'),
+                (K::KeyV, 'v'),
+                (K::KeyW, 'w'),
+                (K::KeyX, 'x'),
+                (K::KeyY, 'y'),
+                (K::KeyZ, 'z'),
+                (K::Num0, '0'),
+                (K::Num1, '1'),
+                (K::Num2, '2'),
+                (K::Num3, '3'),
+                (K::Num4, '4'),
+                (K::Num5, '5'),
+                (K::Num6, '6'),
+                (K::Num7, '7'),
+                (K::Num8, '8'),
+                (K::Num9, '9'),
+            ];
+            let (_, c) = letters.iter().find(|(key, _)| *key == other)?;
+            NamedKey::Char(*c)
+        }
+    };
+    Some(named)
+}
+
 impl Default for Keys {
     fn default() -> Self {
         Self::new()
@@ -159,6 +348,28 @@ impl KeysHandle {
         self.keys.on_activation(Box::new(callback));
     }
 
+    pub fn set_cancel_chord(&self, chord: Option<KeyChord>) {
+        self.keys.set_cancel_chord(chord);
+    }
+
+    pub fn on_cancel<F>(&self, callback: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.keys.on_cancel(Box::new(callback));
+    }
+
+    pub fn set_scratch_chord(&self, chord: Option<KeyChord>) {
+        self.keys.set_scratch_chord(chord);
+    }
+
+    pub fn on_scratch<F>(&self, callback: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.keys.on_scratch(Box::new(callback));
+    }
+
     pub fn get_state(&self) -> ActivationState {
         self.keys.get_state()
     }
@@ -173,3 +384,91 @@ impl Default for KeysHandle {
         Self::new().expect("Failed to initialize keyboard listener")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice_commands::parse_key_sequence;
+    use std::sync::atomic::AtomicUsize;
+
+    fn keys_cancelling_on(chord: &str) -> (Keys, Arc<AtomicUsize>) {
+        let keys = Keys::new();
+        let cancels = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&cancels);
+        keys.set_cancel_chord(parse_key_sequence(chord).unwrap().pop());
+        keys.on_cancel(Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+        (keys, cancels)
+    }
+
+    #[test]
+    fn test_escape_cancels() {
+        let (keys, cancels) = keys_cancelling_on("escape");
+        keys.handle_key(rdev::Key::Escape, true);
+        keys.handle_key(rdev::Key::Escape, false);
+        assert_eq!(cancels.load(Ordering::SeqCst), 1);
+
+        keys.handle_key(rdev::Key::ShiftLeft, true);
+        keys.handle_key(rdev::Key::Escape, true);
+        assert_eq!(cancels.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_cancel_chord_needs_its_modifiers() {
+        let (keys, cancels) = keys_cancelling_on("ctrl+shift+.");
+        keys.handle_key(rdev::Key::Dot, true);
+        keys.handle_key(rdev::Key::ControlLeft, true);
+        keys.handle_key(rdev::Key::Dot, true);
+        assert_eq!(cancels.load(Ordering::SeqCst), 0);
+
+        keys.handle_key(rdev::Key::ShiftRight, true);
+        keys.handle_key(rdev::Key::Dot, true);
+        assert_eq!(cancels.load(Ordering::SeqCst), 1);
+
+        keys.handle_key(rdev::Key::ControlLeft, false);
+        keys.handle_key(rdev::Key::Dot, true);
+        assert_eq!(cancels.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_no_cancel_chord() {
+        let (keys, cancels) = keys_cancelling_on("escape");
+        keys.set_cancel_chord(None);
+        keys.handle_key(rdev::Key::Escape, true);
+        assert_eq!(cancels.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_scratch_fires_once_the_chord_is_released() {
+        let keys = Keys::new();
+        let scratches = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&scratches);
+        keys.set_scratch_chord(parse_key_sequence("ctrl+alt+z").unwrap().pop());
+        keys.on_scratch(Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        keys.handle_key(rdev::Key::ControlLeft, true);
+        keys.handle_key(rdev::Key::Alt, true);
+        keys.handle_key(rdev::Key::KeyZ, true);
+        keys.handle_key(rdev::Key::KeyZ, false);
+        keys.handle_key(rdev::Key::Alt, false);
+        assert_eq!(scratches.load(Ordering::SeqCst), 0);
+
+        keys.handle_key(rdev::Key::ControlLeft, false);
+        assert_eq!(scratches.load(Ordering::SeqCst), 1);
+
+        keys.handle_key(rdev::Key::KeyZ, true);
+        keys.handle_key(rdev::Key::KeyZ, false);
+        assert_eq!(scratches.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_named_key() {
+        assert_eq!(named_key(rdev::Key::KeyQ), Some(NamedKey::Char('q')));
+        assert_eq!(named_key(rdev::Key::Num7), Some(NamedKey::Char('7')));
+        assert_eq!(named_key(rdev::Key::F5), Some(NamedKey::Function(5)));
+        assert_eq!(named_key(rdev::Key::CapsLock), None);
+    }
+}
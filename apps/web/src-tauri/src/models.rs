@@ -0,0 +1,519 @@
+use crate::prefs::ModelProfile;
+use crate::stt::get_model_info;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+const REGISTRY_FILE: &str = "models.json";
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+const GGUF_MAGIC: u32 = 0x4655_4747;
+/// whisper's English-only checkpoints have exactly this many tokens; the
+/// multilingual ones add language tokens on top.
+const ENGLISH_VOCAB_SIZE: i64 = 51864;
+const MAX_GGUF_KEYS: u64 = 4096;
+const MAX_GGUF_STRING: u64 = 1 << 20;
+
+#[derive(Debug, Error)]
+pub enum ModelError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Not a whisper model: {0}")]
+    InvalidHeader(String),
+    #[error("Unknown model: {0}")]
+    NotFound(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelFormat {
+    Ggml,
+    Gguf,
+}
+
+/// What the file header says about a whisper model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelHeader {
+    pub format: ModelFormat,
+    pub model_type: String,
+    pub multilingual: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportedModel {
+    pub id: String,
+    pub filename: String,
+    pub sha256: String,
+    pub format: ModelFormat,
+    pub model_type: String,
+    pub multilingual: bool,
+    pub size_bytes: u64,
+    pub imported_at: i64,
+}
+
+/// Where a profile's weights live and what to expect of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelSpec {
+    pub path: PathBuf,
+    pub sha256: String,
+    pub multilingual: bool,
+}
+
+pub fn resolve_model(profile: &ModelProfile, models_dir: &Path) -> Result<ModelSpec, ModelError> {
+    if let Some(info) = get_model_info(profile) {
+        return Ok(ModelSpec {
+            path: models_dir.join(info.filename),
+            sha256: info.sha256,
+            multilingual: *profile != ModelProfile::EnglishSmall,
+        });
+    }
+
+    let model = list_imported(models_dir)?
+        .into_iter()
+        .find(|model| model.id == profile.as_str())
+        .ok_or_else(|| ModelError::NotFound(profile.as_str().to_string()))?;
+    Ok(ModelSpec {
+        path: models_dir.join(&model.filename),
+        sha256: model.sha256,
+        multilingual: model.multilingual,
+    })
+}
+
+pub fn list_imported(models_dir: &Path) -> Result<Vec<ImportedModel>, ModelError> {
+    let path = models_dir.join(REGISTRY_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn save_registry(models_dir: &Path, models: &[ImportedModel]) -> Result<(), ModelError> {
+    let content = serde_json::to_string_pretty(models)?;
+    fs::write(models_dir.join(REGISTRY_FILE), content)?;
+    Ok(())
+}
+
+/// Validates `source` as a whisper GGML/GGUF file, links (or copies, across
+/// filesystems) it into `models_dir` and registers it. Importing the same
+/// weights again returns the existing entry.
+pub fn import_model(source: &Path, models_dir: &Path) -> Result<ImportedModel, ModelError> {
+    let header = read_header(source)?;
+    let sha256 = file_sha256(source)?;
+
+    let name = if header.multilingual {
+        header.model_type.clone()
+    } else {
+        format!("{}.en", header.model_type)
+    };
+    let id = format!("{}-{}", name, &sha256[..8]);
+    let extension = match header.format {
+        ModelFormat::Ggml => "bin",
+        ModelFormat::Gguf => "gguf",
+    };
+    let filename = format!("ggml-imported-{}.{}", id, extension);
+
+    let mut registry = list_imported(models_dir)?;
+    if let Some(existing) = registry.iter().find(|model| model.sha256 == sha256) {
+        if models_dir.join(&existing.filename).exists() {
+            return Ok(existing.clone());
+        }
+    }
+
+    // A hard link shares storage with the source; if the source is later
+    // edited in place, the checksum check at load time catches it.
+    let destination = models_dir.join(&filename);
+    if !destination.exists() && fs::hard_link(source, &destination).is_err() {
+        fs::copy(source, &destination)?;
+    }
+
+    let model = ImportedModel {
+        id,
+        filename,
+        sha256,
+        format: header.format,
+        model_type: header.model_type,
+        multilingual: header.multilingual,
+        size_bytes: fs::metadata(&destination)?.len(),
+        imported_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64,
+    };
+    registry.retain(|existing| existing.sha256 != model.sha256);
+    registry.push(model.clone());
+    save_registry(models_dir, &registry)?;
+
+    log::info!("Imported model {} from {}", model.id, source.display());
+    Ok(model)
+}
+
+pub fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub fn read_header(path: &Path) -> Result<ModelHeader, ModelError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    parse_header(&mut reader)
+}
+
+fn parse_header(reader: &mut impl Read) -> Result<ModelHeader, ModelError> {
+    let (format, hparams) = match read_u32(reader)? {
+        GGML_MAGIC => (ModelFormat::Ggml, read_ggml_hparams(reader)?),
+        GGUF_MAGIC => (ModelFormat::Gguf, read_gguf_hparams(reader)?),
+        magic => {
+            return Err(ModelError::InvalidHeader(format!(
+                "unrecognised magic {:#010x}",
+                magic
+            )))
+        }
+    };
+
+    if hparams.n_mels != 80 && hparams.n_mels != 128 {
+        return Err(ModelError::InvalidHeader(format!(
+            "unexpected mel bin count {}",
+            hparams.n_mels
+        )));
+    }
+    if hparams.n_vocab < ENGLISH_VOCAB_SIZE {
+        return Err(ModelError::InvalidHeader(format!(
+            "vocabulary of {} tokens is too small",
+            hparams.n_vocab
+        )));
+    }
+
+    let model_type = match hparams.n_audio_layer {
+        4 => "tiny",
+        6 => "base",
+        12 => "small",
+        24 => "medium",
+        32 => "large",
+        layers => {
+            return Err(ModelError::InvalidHeader(format!(
+                "unsupported encoder depth {}",
+                layers
+            )))
+        }
+    };
+
+    Ok(ModelHeader {
+        format,
+        model_type: model_type.to_string(),
+        multilingual: hparams.n_vocab > ENGLISH_VOCAB_SIZE,
+    })
+}
+
+struct Hparams {
+    n_vocab: i64,
+    n_audio_layer: i64,
+    n_mels: i64,
+}
+
+/// The legacy whisper.cpp layout: eleven i32 hyperparameters right after the
+/// magic, starting with n_vocab.
+fn read_ggml_hparams(reader: &mut impl Read) -> Result<Hparams, ModelError> {
+    let mut values = [0i32; 11];
+    for value in &mut values {
+        let mut bytes = [0; 4];
+        reader.read_exact(&mut bytes).map_err(truncated)?;
+        *value = i32::from_le_bytes(bytes);
+    }
+    Ok(Hparams {
+        n_vocab: values[0] as i64,
+        n_audio_layer: values[4] as i64,
+        n_mels: values[9] as i64,
+    })
+}
+
+/// GGUF keeps hyperparameters as typed key/value metadata; whisper exports
+/// name them after the GGML fields (e.g. `whisper.n_vocab`).
+fn read_gguf_hparams(reader: &mut impl Read) -> Result<Hparams, ModelError> {
+    let version = read_u32(reader)?;
+    if !(2..=3).contains(&version) {
+        return Err(ModelError::InvalidHeader(format!(
+            "unsupported GGUF version {}",
+            version
+        )));
+    }
+    let _tensor_count = read_u64(reader)?;
+    let kv_count = read_u64(reader)?;
+    if kv_count > MAX_GGUF_KEYS {
+        return Err(ModelError::InvalidHeader(format!(
+            "{} metadata keys",
+            kv_count
+        )));
+    }
+
+    let (mut n_vocab, mut n_audio_layer, mut n_mels) = (None, None, None);
+    for _ in 0..kv_count {
+        let key = read_gguf_string(reader)?;
+        let value_type = read_u32(reader)?;
+        let value = read_gguf_value(reader, value_type)?;
+        match key.rsplit('.').next() {
+            Some("n_vocab") => n_vocab = value,
+            Some("n_audio_layer") => n_audio_layer = value,
+            Some("n_mels") => n_mels = value,
+            _ => {}
+        }
+    }
+
+    match (n_vocab, n_audio_layer, n_mels) {
+        (Some(n_vocab), Some(n_audio_layer), Some(n_mels)) => Ok(Hparams {
+            n_vocab,
+            n_audio_layer,
+            n_mels,
+        }),
+        _ => Err(ModelError::InvalidHeader(
+            "GGUF metadata has no whisper hyperparameters".to_string(),
+        )),
+    }
+}
+
+/// Reads one metadata value, returning it if it is an integer and skipping
+/// it otherwise.
+fn read_gguf_value(reader: &mut impl Read, value_type: u32) -> Result<Option<i64>, ModelError> {
+    let integer = |bytes: &[u8], signed: bool| -> i64 {
+        let mut buf = [0u8; 8];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        let value = u64::from_le_bytes(buf);
+        if signed && bytes.len() < 8 && bytes[bytes.len() - 1] & 0x80 != 0 {
+            (value | (u64::MAX << (bytes.len() * 8))) as i64
+        } else {
+            value as i64
+        }
+    };
+
+    let (width, signed) = match value_type {
+        0 | 7 => (1, false),
+        1 => (1, true),
+        2 => (2, false),
+        3 => (2, true),
+        4 => (4, false),
+        5 => (4, true),
+        10 => (8, false),
+        11 => (8, true),
+        6 => return skip(reader, 4).map(|_| None),
+        12 => return skip(reader, 8).map(|_| None),
+        8 => return read_gguf_string(reader).map(|_| None),
+        9 => {
+            let item_type = read_u32(reader)?;
+            let len = read_u64(reader)?;
+            for _ in 0..len {
+                read_gguf_value(reader, item_type)?;
+            }
+            return Ok(None);
+        }
+        other => {
+            return Err(ModelError::InvalidHeader(format!(
+                "unknown GGUF value type {}",
+                other
+            )))
+        }
+    };
+
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes[..width]).map_err(truncated)?;
+    Ok(Some(integer(&bytes[..width], signed)))
+}
+
+fn read_gguf_string(reader: &mut impl Read) -> Result<String, ModelError> {
+    let len = read_u64(reader)?;
+    if len > MAX_GGUF_STRING {
+        return Err(ModelError::InvalidHeader(format!(
+            "{}-byte metadata string",
+            len
+        )));
+    }
+    let mut bytes = vec![0; len as usize];
+    reader.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn skip(reader: &mut impl Read, len: u64) -> Result<(), ModelError> {
+    let skipped = io::copy(&mut reader.take(len), &mut io::sink())?;
+    if skipped < len {
+        return Err(truncated(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(())
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, ModelError> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, ModelError> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn truncated(error: io::Error) -> ModelError {
+    if error.kind() == io::ErrorKind::UnexpectedEof {
+        ModelError::InvalidHeader("file ends inside the header".to_string())
+    } else {
+        ModelError::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ggml_bytes(n_vocab: i32, n_audio_layer: i32) -> Vec<u8> {
+        let hparams = [
+            n_vocab,
+            1500,
+            768,
+            12,
+            n_audio_layer,
+            448,
+            768,
+            12,
+            12,
+            80,
+            1,
+        ];
+        let mut bytes = GGML_MAGIC.to_le_bytes().to_vec();
+        for value in hparams {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(b"weights");
+        bytes
+    }
+
+    fn gguf_string(bytes: &mut Vec<u8>, text: &str) {
+        bytes.extend_from_slice(&(text.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(text.as_bytes());
+    }
+
+    fn gguf_bytes() -> Vec<u8> {
+        let mut bytes = GGUF_MAGIC.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&5u64.to_le_bytes());
+
+        gguf_string(&mut bytes, "general.architecture");
+        bytes.extend_from_slice(&8u32.to_le_bytes());
+        gguf_string(&mut bytes, "whisper");
+
+        gguf_string(&mut bytes, "whisper.n_vocab");
+        bytes.extend_from_slice(&5u32.to_le_bytes());
+        bytes.extend_from_slice(&51865i32.to_le_bytes());
+
+        gguf_string(&mut bytes, "general.languages");
+        bytes.extend_from_slice(&9u32.to_le_bytes());
+        bytes.extend_from_slice(&8u32.to_le_bytes());
+        bytes.extend_from_slice(&2u64.to_le_bytes());
+        gguf_string(&mut bytes, "en");
+        gguf_string(&mut bytes, "de");
+
+        gguf_string(&mut bytes, "whisper.n_audio_layer");
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        bytes.extend_from_slice(&24u32.to_le_bytes());
+
+        gguf_string(&mut bytes, "whisper.n_mels");
+        bytes.extend_from_slice(&10u32.to_le_bytes());
+        bytes.extend_from_slice(&128u64.to_le_bytes());
+        bytes
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("stt_models_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_ggml_header_reports_type_and_language_support() {
+        let english = parse_header(&mut ggml_bytes(51864, 12).as_slice()).unwrap();
+        assert_eq!(
+            english,
+            ModelHeader {
+                format: ModelFormat::Ggml,
+                model_type: "small".to_string(),
+                multilingual: false,
+            }
+        );
+
+        let multilingual = parse_header(&mut ggml_bytes(51865, 6).as_slice()).unwrap();
+        assert_eq!(multilingual.model_type, "base");
+        assert!(multilingual.multilingual);
+    }
+
+    #[test]
+    fn test_gguf_header_reads_whisper_metadata() {
+        let header = parse_header(&mut gguf_bytes().as_slice()).unwrap();
+        assert_eq!(header.format, ModelFormat::Gguf);
+        assert_eq!(header.model_type, "medium");
+        assert!(header.multilingual);
+    }
+
+    #[test]
+    fn test_rejects_files_that_are_not_whisper_models() {
+        let cases = [
+            b"PK\x03\x04 not a model".to_vec(),
+            ggml_bytes(51865, 12)[..20].to_vec(),
+            ggml_bytes(32000, 12),
+            ggml_bytes(51865, 7),
+        ];
+        for bytes in cases {
+            assert!(matches!(
+                parse_header(&mut bytes.as_slice()),
+                Err(ModelError::InvalidHeader(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_import_registers_model_for_loading() {
+        let dir = temp_dir("import");
+        let models_dir = dir.join("models");
+        fs::create_dir_all(&models_dir).unwrap();
+        let source = dir.join("my-finetune.bin");
+        fs::write(&source, ggml_bytes(51864, 24)).unwrap();
+
+        let model = import_model(&source, &models_dir).unwrap();
+        let sha256 = file_sha256(&source).unwrap();
+        assert_eq!(model.id, format!("medium.en-{}", &sha256[..8]));
+        assert_eq!(model.sha256, sha256);
+        assert!(!model.multilingual);
+        assert!(models_dir.join(&model.filename).exists());
+
+        let profile: ModelProfile = serde_json::from_value(serde_json::json!(model.id)).unwrap();
+        let spec = resolve_model(&profile, &models_dir).unwrap();
+        assert_eq!(spec.path, models_dir.join(&model.filename));
+        assert_eq!(spec.sha256, sha256);
+        assert!(!spec.multilingual);
+
+        assert_eq!(import_model(&source, &models_dir).unwrap(), model);
+        assert_eq!(list_imported(&models_dir).unwrap(), vec![model]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_resolve_rejects_unregistered_ids() {
+        let dir = temp_dir("resolve");
+        let profile = ModelProfile::Imported("small-0badf00d".to_string());
+        assert!(matches!(
+            resolve_model(&profile, &dir),
+            Err(ModelError::NotFound(_))
+        ));
+
+        let builtin = resolve_model(&ModelProfile::EnglishSmall, &dir).unwrap();
+        assert_eq!(builtin.path, dir.join("ggml-model-small.en.bin"));
+        assert!(!builtin.multilingual);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,432 @@
+use crate::prefs::{OutputTarget, Preferences, TypingMethod};
+use crate::type_::{TypeMethod, TypeOptions, TypedOutput, Typer, TypingCancel};
+use crate::voice_commands::{inserted_text, OutputAction};
+use arboard::Clipboard;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum OutputError {
+    /// `typed_chars` counts everything typed before the cancel; `typed` is
+    /// what was left since the last key press, for "scratch that".
+    #[error("Typing cancelled after {typed_chars} characters")]
+    Cancelled {
+        typed_chars: usize,
+        typed: TypedOutput,
+    },
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl From<String> for OutputError {
+    fn from(message: String) -> Self {
+        OutputError::Failed(message)
+    }
+}
+
+/// Somewhere finished dictation is delivered.
+pub trait OutputSink {
+    /// Delivers `actions` and returns what they left typed, so a later
+    /// "scratch that" knows what to remove; `last_dictation` is what the
+    /// previous dictation typed. Sinks that don't type into an app return
+    /// nothing typed and skip key presses and removals. Typing stopped part
+    /// way fails with `OutputError::Cancelled`.
+    fn send(
+        &mut self,
+        actions: &[OutputAction],
+        last_dictation: &TypedOutput,
+    ) -> Result<TypedOutput, OutputError>;
+}
+
+impl OutputSink for Typer {
+    fn send(
+        &mut self,
+        actions: &[OutputAction],
+        last_dictation: &TypedOutput,
+    ) -> Result<TypedOutput, OutputError> {
+        self.run_actions(actions, last_dictation)
+    }
+}
+
+/// Puts the text on the clipboard for the user to paste themselves.
+pub struct ClipboardSink;
+
+impl OutputSink for ClipboardSink {
+    fn send(
+        &mut self,
+        actions: &[OutputAction],
+        _: &TypedOutput,
+    ) -> Result<TypedOutput, OutputError> {
+        let text = inserted_text(actions);
+        if text.is_empty() {
+            return Ok(TypedOutput::default());
+        }
+        let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+        clipboard.set_text(text).map_err(|e| e.to_string())?;
+        Ok(TypedOutput::default())
+    }
+}
+
+/// Prints each dictation as a line on the app's standard output, for
+/// launching it from a terminal or a pipeline.
+pub struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn send(
+        &mut self,
+        actions: &[OutputAction],
+        _: &TypedOutput,
+    ) -> Result<TypedOutput, OutputError> {
+        let Some(line) = as_line(actions) else {
+            return Ok(TypedOutput::default());
+        };
+        let mut stdout = std::io::stdout().lock();
+        stdout
+            .write_all(line.as_bytes())
+            .and_then(|()| stdout.flush())
+            .map_err(|e| format!("Failed to write to stdout: {}", e))?;
+        Ok(TypedOutput::default())
+    }
+}
+
+/// Appends each dictation to a file as its own line.
+pub struct FileSink {
+    path: PathBuf,
+}
+
+impl FileSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl OutputSink for FileSink {
+    fn send(
+        &mut self,
+        actions: &[OutputAction],
+        _: &TypedOutput,
+    ) -> Result<TypedOutput, OutputError> {
+        let Some(line) = as_line(actions) else {
+            return Ok(TypedOutput::default());
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open {}: {}", self.path.display(), e))?;
+        file.write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+        Ok(TypedOutput::default())
+    }
+}
+
+/// Writes each dictation as a line to a Unix socket or FIFO, for scripts
+/// that consume dictation.
+pub struct SocketSink {
+    path: PathBuf,
+}
+
+impl SocketSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl OutputSink for SocketSink {
+    #[cfg(unix)]
+    fn send(
+        &mut self,
+        actions: &[OutputAction],
+        _: &TypedOutput,
+    ) -> Result<TypedOutput, OutputError> {
+        use std::os::unix::fs::FileTypeExt;
+        use std::os::unix::net::UnixStream;
+        use std::time::Duration;
+
+        let Some(line) = as_line(actions) else {
+            return Ok(TypedOutput::default());
+        };
+        let path = self.path.display();
+        let is_fifo = std::fs::metadata(&self.path)
+            .map(|metadata| metadata.file_type().is_fifo())
+            .unwrap_or(false);
+        if is_fifo {
+            // Opening read-write doesn't wait for a reader to appear, which a
+            // write-only open would.
+            let mut fifo = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&self.path)
+                .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+            fifo.write_all(line.as_bytes())
+                .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        } else {
+            let mut stream = UnixStream::connect(&self.path)
+                .map_err(|e| format!("Failed to connect to {}: {}", path, e))?;
+            stream
+                .set_write_timeout(Some(Duration::from_secs(1)))
+                .map_err(|e| e.to_string())?;
+            stream
+                .write_all(line.as_bytes())
+                .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        }
+        Ok(TypedOutput::default())
+    }
+
+    #[cfg(not(unix))]
+    fn send(&mut self, _: &[OutputAction], _: &TypedOutput) -> Result<TypedOutput, OutputError> {
+        Err(OutputError::Failed(
+            "Socket output is only supported on Unix".to_string(),
+        ))
+    }
+}
+
+/// Collects output in memory.
+#[cfg(test)]
+#[derive(Default, Clone)]
+pub struct MemorySink {
+    sent: std::sync::Arc<parking_lot::Mutex<Vec<Vec<OutputAction>>>>,
+}
+
+#[cfg(test)]
+impl MemorySink {
+    pub fn sent(&self) -> Vec<Vec<OutputAction>> {
+        self.sent.lock().clone()
+    }
+
+    pub fn text(&self) -> String {
+        self.sent()
+            .iter()
+            .map(|actions| inserted_text(actions))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl OutputSink for MemorySink {
+    fn send(
+        &mut self,
+        actions: &[OutputAction],
+        _: &TypedOutput,
+    ) -> Result<TypedOutput, OutputError> {
+        self.sent.lock().push(actions.to_vec());
+        Ok(TypedOutput::keystrokes(
+            inserted_text(actions).chars().count(),
+        ))
+    }
+}
+
+/// The text `actions` insert as one line, or `None` when they only press
+/// keys, which sinks that don't type have nothing to write for.
+fn as_line(actions: &[OutputAction]) -> Option<String> {
+    let mut line = inserted_text(actions);
+    if line.is_empty() {
+        return None;
+    }
+    if !line.ends_with('\n') {
+        line.push('\n');
+    }
+    Some(line)
+}
+
+pub fn type_options(prefs: &Preferences) -> TypeOptions {
+    TypeOptions {
+        method: match prefs.typing.method {
+            TypingMethod::Keystroke => TypeMethod::Keystroke,
+            TypingMethod::Clipboard => TypeMethod::Clipboard,
+        },
+        throttle_ms: prefs.typing.throttle_ms as u64,
+        newline_append: prefs.typing.newline_at_end,
+        clipboard_fallback: true,
+        detect_code_context: true,
+        detect_password_fields: true,
+        layout: prefs.typing.layout,
+        clipboard_restore_ms: prefs.typing.clipboard_restore_ms as u64,
+    }
+}
+
+/// The sink `prefs` sends dictation to. `cancel` stops typing part way.
+pub fn sink_for(
+    prefs: &Preferences,
+    cancel: &Arc<TypingCancel>,
+) -> Result<Box<dyn OutputSink>, String> {
+    Ok(match prefs.output.target {
+        OutputTarget::Type => Box::new(
+            Typer::new(type_options(prefs))
+                .map_err(|e| format!("Failed to create typer: {}", e))?
+                .with_cancel(Arc::clone(cancel)),
+        ),
+        OutputTarget::Clipboard => Box::new(ClipboardSink),
+        OutputTarget::Stdout => Box::new(StdoutSink),
+        OutputTarget::File => Box::new(FileSink::new(&prefs.output.path)),
+        OutputTarget::Socket => Box::new(SocketSink::new(&prefs.output.path)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice_commands::{KeyChord, NamedKey};
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn dictation() -> Vec<OutputAction> {
+        vec![
+            OutputAction::Text("first line".to_string()),
+            OutputAction::Keys(vec![KeyChord {
+                modifiers: Vec::new(),
+                key: NamedKey::Enter,
+            }]),
+            OutputAction::Text(" continued".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_file_sink_appends_lines() {
+        let path = temp_path("stt_output_file_sink.txt");
+        let mut sink = FileSink::new(&path);
+        assert_eq!(
+            sink.send(&dictation(), &TypedOutput::default()),
+            Ok(TypedOutput::default())
+        );
+        sink.send(
+            &[OutputAction::Text("second\n".to_string())],
+            &TypedOutput::default(),
+        )
+        .unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, "first line continued\nsecond\n");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_file_sink_skips_key_only_dictation() {
+        let path = temp_path("stt_output_key_only.txt");
+        let mut sink = FileSink::new(&path);
+        let keys_only = [
+            OutputAction::Keys(crate::voice_commands::parse_key_sequence("cmd+a").unwrap()),
+            OutputAction::RemoveLastDictation,
+        ];
+        assert_eq!(
+            sink.send(&keys_only, &TypedOutput::default()),
+            Ok(TypedOutput::default())
+        );
+        assert!(!path.exists());
+
+        sink.send(&dictation(), &TypedOutput::default()).unwrap();
+        sink.send(&keys_only, &TypedOutput::default()).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, "first line continued\n");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_stdout_sink_types_nothing() {
+        let mut sink = StdoutSink;
+        assert_eq!(
+            sink.send(&dictation(), &TypedOutput::default()),
+            Ok(TypedOutput::default())
+        );
+        assert_eq!(
+            sink.send(
+                &[OutputAction::RemoveLastDictation],
+                &TypedOutput::default()
+            ),
+            Ok(TypedOutput::default())
+        );
+    }
+
+    #[test]
+    fn test_sink_for_stdout_target() {
+        let mut prefs = Preferences::default();
+        prefs.output.target = OutputTarget::Stdout;
+        assert!(crate::prefs::validate_preferences(&prefs).is_ok());
+        let mut sink = sink_for(&prefs, &Arc::new(TypingCancel::default())).unwrap();
+        assert!(sink.send(&dictation(), &TypedOutput::default()).is_ok());
+    }
+
+    #[test]
+    fn test_key_only_dictation_has_no_line() {
+        assert_eq!(as_line(&[OutputAction::RemoveLastDictation]), None);
+        assert_eq!(
+            as_line(&[OutputAction::Text("hi".to_string())]),
+            Some("hi\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_file_sink_reports_unwritable_path() {
+        let mut sink = FileSink::new(temp_path("stt_missing_dir").join("out.txt"));
+        assert!(sink.send(&dictation(), &TypedOutput::default()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socket_sink_writes_line() {
+        use std::io::Read;
+        use std::os::unix::net::UnixListener;
+
+        let path = temp_path("stt_output.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let mut sink = SocketSink::new(&path);
+        sink.send(&dictation(), &TypedOutput::default()).unwrap();
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = String::new();
+        stream.read_to_string(&mut received).unwrap();
+        assert_eq!(received, "first line continued\n");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_socket_sink_reports_missing_listener() {
+        let mut sink = SocketSink::new(temp_path("stt_no_listener.sock"));
+        assert!(sink.send(&dictation(), &TypedOutput::default()).is_err());
+    }
+
+    #[test]
+    fn test_memory_sink_records_actions() {
+        let memory = MemorySink::default();
+        let mut sink: Box<dyn OutputSink> = Box::new(memory.clone());
+        assert_eq!(
+            sink.send(&dictation(), &TypedOutput::default()),
+            Ok(TypedOutput::keystrokes(20))
+        );
+        assert_eq!(memory.sent(), vec![dictation()]);
+        assert_eq!(memory.text(), "first line continued");
+    }
+
+    #[test]
+    fn test_memory_sink_types_nothing_for_key_only_dictation() {
+        let mut memory = MemorySink::default();
+        let keys_only = [OutputAction::Keys(vec![KeyChord {
+            modifiers: Vec::new(),
+            key: NamedKey::Enter,
+        }])];
+        assert_eq!(
+            memory.send(&keys_only, &TypedOutput::default()),
+            Ok(TypedOutput::default())
+        );
+        assert_eq!(memory.text(), "");
+    }
+
+    #[test]
+    fn test_type_options_follow_typing_prefs() {
+        let mut prefs = Preferences::default();
+        prefs.typing.method = TypingMethod::Clipboard;
+        prefs.typing.throttle_ms = 20;
+        prefs.typing.newline_at_end = true;
+        let options = type_options(&prefs);
+        assert_eq!(options.method, TypeMethod::Clipboard);
+        assert_eq!(options.throttle_ms, 20);
+        assert!(options.newline_append);
+    }
+}
@@ -1,7 +1,17 @@
+use crate::itn;
+use crate::keyplan::KeyboardLayout;
+use crate::redaction::RedactionPrefs;
+use crate::snippets::{load_snippets, save_snippets, validate_snippets, Snippets, SNIPPETS_FILE};
+use crate::type_::ModifierKey;
+use crate::voice_commands::{
+    default_commands, parse_key_sequence, validate_commands, KeyChord, NamedKey, VoiceCommand,
+    VoiceCommandMap,
+};
 use anyhow::Result;
 use directories::ProjectDirs;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 use thiserror::Error;
@@ -16,6 +26,8 @@ pub enum PrefsError {
     Json(#[from] serde_json::Error),
     #[error("Validation error: {0}")]
     Validation(String),
+    #[error("Snippet error: {0}")]
+    Snippets(#[from] crate::snippets::SnippetError),
 }
 
 pub fn validate_preferences(prefs: &Preferences) -> Result<(), PrefsError> {
@@ -34,6 +46,20 @@ pub fn validate_preferences(prefs: &Preferences) -> Result<(), PrefsError> {
             "throttle_ms must not exceed 1000".to_string(),
         ));
     }
+    if prefs.typing.clipboard_restore_ms > 5000 {
+        return Err(PrefsError::Validation(
+            "clipboard_restore_ms must not exceed 5000".to_string(),
+        ));
+    }
+    validate_typing_chord("cancel_chord", &prefs.typing.cancel_chord, true)?;
+    validate_typing_chord("scratch_chord", &prefs.typing.scratch_chord, false)?;
+    if prefs.typing.scratch_chord().is_some()
+        && prefs.typing.scratch_chord() == prefs.typing.cancel_chord()
+    {
+        return Err(PrefsError::Validation(
+            "scratch_chord must differ from cancel_chord".to_string(),
+        ));
+    }
     if prefs.record.chunk_seconds < 10 || prefs.record.chunk_seconds > 300 {
         return Err(PrefsError::Validation(
             "chunk_seconds must be between 10 and 300".to_string(),
@@ -49,24 +75,139 @@ pub fn validate_preferences(prefs: &Preferences) -> Result<(), PrefsError> {
             "max_file_gb must be between 1 and 16".to_string(),
         ));
     }
-    if prefs.voice_commands.enabled {
-        let map = &prefs.voice_commands.map;
-        if map.newline.is_empty()
-            || map.new_paragraph.is_empty()
-            || map.tab.is_empty()
-            || map.period.is_empty()
-            || map.comma.is_empty()
-            || map.colon.is_empty()
-            || map.semicolon.is_empty()
-            || map.open_quote.is_empty()
-            || map.close_quote.is_empty()
-            || map.backtick.is_empty()
-            || map.code_block.is_empty()
+    let filter = &prefs.hallucination_filter;
+    if !(0.0..=1.0).contains(&filter.no_speech_threshold) {
+        return Err(PrefsError::Validation(
+            "no_speech_threshold must be between 0 and 1".to_string(),
+        ));
+    }
+    if !(0.0..=0.1).contains(&filter.min_rms) {
+        return Err(PrefsError::Validation(
+            "min_rms must be between 0 and 0.1".to_string(),
+        ));
+    }
+    if filter.max_repeats < 1 {
+        return Err(PrefsError::Validation(
+            "max_repeats must be at least 1".to_string(),
+        ));
+    }
+    let disfluency = &prefs.disfluency_filter;
+    if disfluency.enabled {
+        let phrases = disfluency
+            .fillers
+            .values()
+            .chain(disfluency.correction_cues.values())
+            .flatten();
+        for phrase in phrases {
+            if phrase.trim().is_empty() {
+                return Err(PrefsError::Validation(
+                    "Filler and correction phrases must not be empty".to_string(),
+                ));
+            }
+        }
+    }
+    if prefs.snippets.enabled {
+        validate_snippets(&prefs.snippets.entries)
+            .map_err(|e| PrefsError::Validation(e.to_string()))?;
+    }
+    if prefs.itn.enabled {
+        if let Some(language) = prefs
+            .itn
+            .languages
+            .iter()
+            .find(|language| !itn::is_supported(language))
         {
+            return Err(PrefsError::Validation(format!(
+                "Number normalization does not support language \"{}\"",
+                language
+            )));
+        }
+    }
+    if prefs.voice_commands.enabled {
+        validate_commands(&prefs.voice_commands.commands)
+            .map_err(|e| PrefsError::Validation(e.to_string()))?;
+    }
+    if matches!(
+        prefs.output.target,
+        OutputTarget::File | OutputTarget::Socket
+    ) && prefs.output.path.trim().is_empty()
+    {
+        return Err(PrefsError::Validation(
+            "Output path is required for file and socket output".to_string(),
+        ));
+    }
+    validate_app_profiles(&prefs.app_profiles)
+}
+
+/// Chords heard while typing must not be something typing itself sends, so
+/// they need Control, Command or Option; `allow_escape` also accepts a bare
+/// Escape.
+fn validate_typing_chord(field: &str, chord: &str, allow_escape: bool) -> Result<(), PrefsError> {
+    if chord.trim().is_empty() {
+        return Ok(());
+    }
+    let chords = parse_key_sequence(chord)
+        .map_err(|e| PrefsError::Validation(format!("{}: {}", field, e)))?;
+    let [chord] = chords.as_slice() else {
+        return Err(PrefsError::Validation(format!(
+            "{} must be a single chord",
+            field
+        )));
+    };
+    let has_modifier = chord.modifiers.iter().any(|modifier| {
+        matches!(
+            modifier,
+            ModifierKey::Control | ModifierKey::Meta | ModifierKey::Alt
+        )
+    });
+    if has_modifier || (allow_escape && chord.key == NamedKey::Escape) {
+        Ok(())
+    } else if allow_escape {
+        Err(PrefsError::Validation(format!(
+            "{} must be escape or use ctrl, cmd or alt",
+            field
+        )))
+    } else {
+        Err(PrefsError::Validation(format!(
+            "{} must use ctrl, cmd or alt",
+            field
+        )))
+    }
+}
+
+fn validate_app_profiles(profiles: &[AppProfile]) -> Result<(), PrefsError> {
+    for (index, profile) in profiles.iter().enumerate() {
+        let name = profile.name.trim();
+        if name.is_empty() {
             return Err(PrefsError::Validation(
-                "All voice command mappings must be non-empty when enabled".to_string(),
+                "Every app profile needs a name".to_string(),
             ));
         }
+        if profiles[..index]
+            .iter()
+            .any(|other| other.name.trim().eq_ignore_ascii_case(name))
+        {
+            return Err(PrefsError::Validation(format!(
+                "More than one app profile is named \"{}\"",
+                name
+            )));
+        }
+        if profile.apps.iter().all(|app| app.trim().is_empty()) {
+            return Err(PrefsError::Validation(format!(
+                "App profile \"{}\" must list at least one app",
+                name
+            )));
+        }
+        if profile.throttle_ms.is_some_and(|throttle| throttle > 1000) {
+            return Err(PrefsError::Validation(format!(
+                "App profile \"{}\": throttle_ms must not exceed 1000",
+                name
+            )));
+        }
+        if let Some(commands) = profile.voice_commands.as_ref().filter(|c| c.enabled) {
+            validate_commands(&commands.commands)
+                .map_err(|e| PrefsError::Validation(format!("App profile \"{}\": {}", name, e)))?;
+        }
     }
     Ok(())
 }
@@ -98,15 +239,26 @@ impl Default for SilenceRms {
     }
 }
 
+/// Which whisper model to run: one of the built-in profiles, or the id of a
+/// model registered through `models::import_model`.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "kebab-case")]
+#[serde(try_from = "String", into = "String")]
 pub enum ModelProfile {
-    #[serde(rename = "small.en")]
     EnglishSmall,
-    #[serde(rename = "multilingual-small")]
     MultilingualSmall,
-    #[serde(rename = "multilingual-medium")]
     MultilingualMedium,
+    Imported(String),
+}
+
+impl ModelProfile {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::EnglishSmall => "small.en",
+            Self::MultilingualSmall => "multilingual-small",
+            Self::MultilingualMedium => "multilingual-medium",
+            Self::Imported(id) => id,
+        }
+    }
 }
 
 impl Default for ModelProfile {
@@ -115,6 +267,45 @@ impl Default for ModelProfile {
     }
 }
 
+impl TryFrom<String> for ModelProfile {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "small.en" => Ok(Self::EnglishSmall),
+            "multilingual-small" => Ok(Self::MultilingualSmall),
+            "multilingual-medium" => Ok(Self::MultilingualMedium),
+            _ if is_imported_model_id(&value) => Ok(Self::Imported(value)),
+            _ => Err(format!("Unknown model profile: {}", value)),
+        }
+    }
+}
+
+impl From<ModelProfile> for String {
+    fn from(profile: ModelProfile) -> Self {
+        match profile {
+            ModelProfile::Imported(id) => id,
+            builtin => builtin.as_str().to_string(),
+        }
+    }
+}
+
+/// Imported ids look like `small.en-1a2b3c4d`: the model type followed by the
+/// first eight hex digits of the file's SHA-256.
+fn is_imported_model_id(id: &str) -> bool {
+    let Some((name, hash)) = id.rsplit_once('-') else {
+        return false;
+    };
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.')
+        && hash.len() == 8
+        && hash
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Hotkeys {
     pub left_chord: bool,
@@ -130,76 +321,149 @@ impl Default for Hotkeys {
     }
 }
 
+/// How dictated text reaches the frontmost app.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TypingMethod {
+    #[default]
+    Keystroke,
+    /// Paste through the clipboard, for apps that drop synthetic keystrokes.
+    Clipboard,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TypingPrefs {
+    #[serde(default)]
+    pub method: TypingMethod,
+    #[serde(default)]
+    pub layout: KeyboardLayout,
     pub newline_at_end: bool,
     pub throttle_ms: u32,
+    /// How long pasted text stays on the clipboard before the previous
+    /// contents are restored; long pastes get a little more.
+    #[serde(default = "default_clipboard_restore_ms")]
+    pub clipboard_restore_ms: u32,
+    /// Key chord that stops typing under way, such as "escape" or
+    /// "ctrl+shift+."; empty turns cancelling off.
+    #[serde(default = "default_cancel_chord")]
+    pub cancel_chord: String,
+    /// Key chord that removes what the last dictation typed, like saying
+    /// "scratch that"; empty turns it off.
+    #[serde(default)]
+    pub scratch_chord: String,
+}
+
+fn default_clipboard_restore_ms() -> u32 {
+    250
+}
+
+fn default_cancel_chord() -> String {
+    "escape".to_string()
+}
+
+impl TypingPrefs {
+    pub fn cancel_chord(&self) -> Option<KeyChord> {
+        single_chord(&self.cancel_chord)
+    }
+
+    pub fn scratch_chord(&self) -> Option<KeyChord> {
+        single_chord(&self.scratch_chord)
+    }
+}
+
+fn single_chord(chord: &str) -> Option<KeyChord> {
+    if chord.trim().is_empty() {
+        return None;
+    }
+    parse_key_sequence(chord)
+        .ok()
+        .and_then(|mut chords| chords.pop())
 }
 
 impl Default for TypingPrefs {
     fn default() -> Self {
         Self {
+            method: TypingMethod::Keystroke,
+            layout: KeyboardLayout::Us,
             newline_at_end: false,
             throttle_ms: 0,
+            clipboard_restore_ms: default_clipboard_restore_ms(),
+            cancel_chord: default_cancel_chord(),
+            scratch_chord: String::new(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Where finished dictation goes instead of, or as well as, being typed.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputTarget {
+    /// Type into the frontmost app.
+    #[default]
+    Type,
+    /// Leave the text on the clipboard without pasting it.
+    Clipboard,
+    /// Print each dictation as a line on standard output.
+    Stdout,
+    /// Append to the file at `path`.
+    File,
+    /// Write to the Unix socket or FIFO at `path`.
+    Socket,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
-pub struct VoiceCommandMap {
-    pub newline: String,
-    pub new_paragraph: String,
-    pub tab: String,
-    pub period: String,
-    pub comma: String,
-    pub colon: String,
-    pub semicolon: String,
-    pub open_quote: String,
-    pub close_quote: String,
-    pub backtick: String,
-    pub code_block: String,
-}
-
-impl Default for VoiceCommandMap {
-    fn default() -> Self {
-        Self {
-            newline: "newline".to_string(),
-            new_paragraph: "new paragraph".to_string(),
-            tab: "tab".to_string(),
-            period: "period".to_string(),
-            comma: "comma".to_string(),
-            colon: "colon".to_string(),
-            semicolon: "semicolon".to_string(),
-            open_quote: "open quote".to_string(),
-            close_quote: "close quote".to_string(),
-            backtick: "backtick".to_string(),
-            code_block: "code block".to_string(),
-        }
-    }
+pub struct OutputPrefs {
+    pub target: OutputTarget,
+    pub path: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "VoiceCommandsFile")]
 pub struct VoiceCommands {
     pub enabled: bool,
-    #[serde(default)]
-    pub map: VoiceCommandMap,
+    pub commands: Vec<VoiceCommand>,
 }
 
 impl Default for VoiceCommands {
     fn default() -> Self {
         Self {
             enabled: true,
-            map: VoiceCommandMap::default(),
+            commands: default_commands(),
+        }
+    }
+}
+
+/// On-disk shape of `VoiceCommands`; configs saved before the command list
+/// existed only have the fixed `map`.
+#[derive(Deserialize)]
+struct VoiceCommandsFile {
+    enabled: bool,
+    #[serde(default)]
+    commands: Option<Vec<VoiceCommand>>,
+    #[serde(default)]
+    map: Option<VoiceCommandMap>,
+}
+
+impl From<VoiceCommandsFile> for VoiceCommands {
+    fn from(file: VoiceCommandsFile) -> Self {
+        Self {
+            enabled: file.enabled,
+            commands: file
+                .commands
+                .unwrap_or_else(|| file.map.unwrap_or_default().to_commands()),
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct RecordPrefs {
     pub chunk_seconds: u32,
     pub max_hours: u32,
     pub max_file_gb: u32,
+    /// Label record-mode entries with the speaker who said them.
+    pub diarization: bool,
 }
 
 impl Default for RecordPrefs {
@@ -208,6 +472,205 @@ impl Default for RecordPrefs {
             chunk_seconds: 60,
             max_hours: 8,
             max_file_gb: 4,
+            diarization: true,
+        }
+    }
+}
+
+/// How entries keep the source-language text when `translate_to_english` is on.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TranslationMode {
+    /// Decode once and keep only the English translation.
+    #[default]
+    TranslationOnly,
+    /// Decode both texts before typing.
+    BothPasses,
+    /// Type as soon as the chosen text is ready and decode the other afterwards.
+    Background,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TextKind {
+    Original,
+    #[default]
+    Translation,
+}
+
+impl TextKind {
+    pub fn other(self) -> Self {
+        match self {
+            Self::Original => Self::Translation,
+            Self::Translation => Self::Original,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TranslationPrefs {
+    pub mode: TranslationMode,
+    /// Which text is typed when both are decoded.
+    pub typed: TextKind,
+}
+
+/// When spoken casing and symbol commands are turned into code.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CodeModePrefs {
+    /// Apps, by name, that always get code mode.
+    pub apps: Vec<String>,
+    /// Also use code mode when the dictated text already looks like code.
+    pub detect_code_blocks: bool,
+}
+
+impl Default for CodeModePrefs {
+    fn default() -> Self {
+        Self {
+            apps: Vec::new(),
+            detect_code_blocks: true,
+        }
+    }
+}
+
+/// Settings that replace the global ones for sessions started while a
+/// matching app is frontmost. Unset fields keep the global value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AppProfile {
+    pub name: String,
+    /// App names or bundle ids, compared case-insensitively.
+    pub apps: Vec<String>,
+    pub typing_method: Option<TypingMethod>,
+    pub throttle_ms: Option<u32>,
+    pub newline_at_end: Option<bool>,
+    /// Turns code mode on or off regardless of the code mode settings.
+    pub code_mode: Option<bool>,
+    pub voice_commands: Option<VoiceCommands>,
+    pub model_profile: Option<ModelProfile>,
+    pub translate_to_english: Option<bool>,
+    pub translation: Option<TranslationPrefs>,
+}
+
+impl AppProfile {
+    pub fn matches(&self, app_name: Option<&str>, bundle_id: Option<&str>) -> bool {
+        self.apps.iter().map(|app| app.trim()).any(|app| {
+            !app.is_empty()
+                && [app_name, bundle_id]
+                    .into_iter()
+                    .flatten()
+                    .any(|id| id.eq_ignore_ascii_case(app))
+        })
+    }
+}
+
+/// Rewrites spoken numbers, dates, times and units in written form.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ItnPrefs {
+    pub enabled: bool,
+    /// Detected languages, like "en", that get normalized.
+    pub languages: Vec<String>,
+}
+
+impl Default for ItnPrefs {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            languages: vec!["en".to_string()],
+        }
+    }
+}
+
+/// Drops whisper output that is most likely hallucinated rather than spoken.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct HallucinationFilter {
+    pub enabled: bool,
+    /// Segments whose no-speech probability reaches this value are dropped.
+    pub no_speech_threshold: f32,
+    /// Buffers quieter than this RMS level never produce text.
+    pub min_rms: f32,
+    /// A phrase repeated back to back more often than this is collapsed.
+    pub max_repeats: u32,
+    /// Phrases dropped when a segment consists of nothing else.
+    pub blocklist: Vec<String>,
+}
+
+impl Default for HallucinationFilter {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            no_speech_threshold: 0.6,
+            min_rms: 0.003,
+            max_repeats: 3,
+            blocklist: [
+                "thank you for watching",
+                "thanks for watching",
+                "thank you for watching and see you next time",
+                "please subscribe",
+                "please like and subscribe",
+                "don't forget to like and subscribe",
+                "subscribe to my channel",
+                "see you in the next video",
+                "subtitles by the amara.org community",
+                "transcription by castingwords",
+                "[BLANK_AUDIO]",
+            ]
+            .iter()
+            .map(|phrase| phrase.to_string())
+            .collect(),
+        }
+    }
+}
+
+/// Removes filler words, stutters and, optionally, self-corrections from
+/// dictated text.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DisfluencyFilter {
+    pub enabled: bool,
+    /// Drop the words taken back by a phrase like "no wait" or "I mean" that
+    /// punctuation sets apart.
+    pub self_corrections: bool,
+    /// Filler words and phrases, by language code.
+    pub fillers: BTreeMap<String, Vec<String>>,
+    /// Phrases that correct the words before them, by language code.
+    pub correction_cues: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for DisfluencyFilter {
+    fn default() -> Self {
+        let lists = |entries: &[(&str, &[&str])]| {
+            entries
+                .iter()
+                .map(|(language, phrases)| {
+                    let phrases = phrases.iter().map(|phrase| phrase.to_string()).collect();
+                    (language.to_string(), phrases)
+                })
+                .collect()
+        };
+        Self {
+            enabled: true,
+            self_corrections: false,
+            fillers: lists(&[
+                (
+                    "en",
+                    &[
+                        "um", "umm", "uh", "uhm", "er", "erm", "ah", "hmm", "mm", "you know",
+                    ],
+                ),
+                ("de", &["äh", "ähm", "öh", "hm"]),
+                ("es", &["eh", "em", "ehm"]),
+                ("fr", &["euh", "heu", "bah"]),
+            ]),
+            correction_cues: lists(&[
+                ("en", &["no wait", "wait no", "I mean", "or rather"]),
+                ("de", &["nein warte", "ich meine"]),
+                ("es", &["no espera", "quiero decir"]),
+                ("fr", &["non attends", "je veux dire"]),
+            ]),
         }
     }
 }
@@ -223,6 +686,65 @@ pub struct Preferences {
     pub typing: TypingPrefs,
     pub voice_commands: VoiceCommands,
     pub record: RecordPrefs,
+    #[serde(default)]
+    pub hallucination_filter: HallucinationFilter,
+    #[serde(default)]
+    pub translation: TranslationPrefs,
+    #[serde(default)]
+    pub code_mode: CodeModePrefs,
+    #[serde(default)]
+    pub itn: ItnPrefs,
+    #[serde(default)]
+    pub disfluency_filter: DisfluencyFilter,
+    /// Kept in its own file next to config.json.
+    #[serde(default)]
+    pub snippets: Snippets,
+    #[serde(default)]
+    pub app_profiles: Vec<AppProfile>,
+    #[serde(default)]
+    pub redaction: RedactionPrefs,
+    #[serde(default)]
+    pub output: OutputPrefs,
+}
+
+impl Preferences {
+    /// The first profile listing the app, by name or bundle id.
+    pub fn app_profile(
+        &self,
+        app_name: Option<&str>,
+        bundle_id: Option<&str>,
+    ) -> Option<&AppProfile> {
+        self.app_profiles
+            .iter()
+            .find(|profile| profile.matches(app_name, bundle_id))
+    }
+
+    /// These preferences with the overrides of `profile` applied.
+    pub fn with_profile(&self, profile: &AppProfile) -> Preferences {
+        let mut prefs = self.clone();
+        if let Some(method) = profile.typing_method {
+            prefs.typing.method = method;
+        }
+        if let Some(throttle_ms) = profile.throttle_ms {
+            prefs.typing.throttle_ms = throttle_ms;
+        }
+        if let Some(newline_at_end) = profile.newline_at_end {
+            prefs.typing.newline_at_end = newline_at_end;
+        }
+        if let Some(voice_commands) = &profile.voice_commands {
+            prefs.voice_commands = voice_commands.clone();
+        }
+        if let Some(model_profile) = &profile.model_profile {
+            prefs.model_profile = model_profile.clone();
+        }
+        if let Some(translate_to_english) = profile.translate_to_english {
+            prefs.translate_to_english = translate_to_english;
+        }
+        if let Some(translation) = &profile.translation {
+            prefs.translation = translation.clone();
+        }
+        prefs
+    }
 }
 
 impl Default for Preferences {
@@ -237,6 +759,15 @@ impl Default for Preferences {
             typing: TypingPrefs::default(),
             voice_commands: VoiceCommands::default(),
             record: RecordPrefs::default(),
+            hallucination_filter: HallucinationFilter::default(),
+            translation: TranslationPrefs::default(),
+            code_mode: CodeModePrefs::default(),
+            itn: ItnPrefs::default(),
+            disfluency_filter: DisfluencyFilter::default(),
+            snippets: Snippets::default(),
+            app_profiles: Vec::new(),
+            redaction: RedactionPrefs::default(),
+            output: OutputPrefs::default(),
         }
     }
 }
@@ -279,14 +810,26 @@ impl Prefs {
 
     fn load_from_file(path: &PathBuf) -> Result<Preferences, PrefsError> {
         let content = fs::read_to_string(path)?;
-        let prefs: Preferences = serde_json::from_str(&content)?;
+        let mut prefs: Preferences = serde_json::from_str(&content)?;
+        let snippets_path = path.with_file_name(SNIPPETS_FILE);
+        if snippets_path.exists() {
+            prefs.snippets = load_snippets(&snippets_path)?;
+        }
         Ok(prefs)
     }
 
     pub fn save(&self) -> Result<(), PrefsError> {
         let prefs = self.inner.read();
-        let content = serde_json::to_string_pretty(&*prefs)?;
+        let mut config = serde_json::to_value(&*prefs)?;
+        if let Some(fields) = config.as_object_mut() {
+            fields.remove("snippets");
+        }
+        let content = serde_json::to_string_pretty(&config)?;
         fs::write(&self.config_path, content)?;
+        save_snippets(
+            &self.config_path.with_file_name(SNIPPETS_FILE),
+            &prefs.snippets,
+        )?;
         Ok(())
     }
 
@@ -330,6 +873,8 @@ impl Prefs {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::snippets::Snippet;
+    use crate::voice_commands::{BuiltinCommand, CommandAction};
 
     #[test]
     fn test_default_preferences_valid() {
@@ -372,6 +917,35 @@ mod tests {
         assert_eq!(prefs.record.chunk_seconds, 120);
         assert_eq!(prefs.record.max_hours, 4);
         assert_eq!(prefs.record.max_file_gb, 2);
+        assert!(prefs.record.diarization);
+        assert_eq!(prefs.hallucination_filter, HallucinationFilter::default());
+        assert_eq!(prefs.translation, TranslationPrefs::default());
+    }
+
+    #[test]
+    fn test_translation_prefs_serialization() {
+        let json = r#"{ "mode": "both_passes", "typed": "original" }"#;
+        let translation: TranslationPrefs = serde_json::from_str(json).unwrap();
+        assert_eq!(translation.mode, TranslationMode::BothPasses);
+        assert_eq!(translation.typed, TextKind::Original);
+
+        let json = serde_json::to_string(&TranslationMode::Background).unwrap();
+        assert_eq!(json, "\"background\"");
+    }
+
+    #[test]
+    fn test_validation_hallucination_filter_ranges() {
+        let mut prefs = Preferences::default();
+        prefs.hallucination_filter.no_speech_threshold = 1.5;
+        assert!(validate_preferences(&prefs).is_err());
+
+        let mut prefs = Preferences::default();
+        prefs.hallucination_filter.min_rms = -0.1;
+        assert!(validate_preferences(&prefs).is_err());
+
+        let mut prefs = Preferences::default();
+        prefs.hallucination_filter.max_repeats = 0;
+        assert!(validate_preferences(&prefs).is_err());
     }
 
     #[test]
@@ -429,6 +1003,67 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("throttle_ms"));
     }
 
+    #[test]
+    fn test_validation_cancel_chord() {
+        let with_chord = |chord: &str| Preferences {
+            typing: TypingPrefs {
+                cancel_chord: chord.to_string(),
+                ..TypingPrefs::default()
+            },
+            ..Preferences::default()
+        };
+        for chord in ["escape", "ctrl+shift+.", "cmd+esc", ""] {
+            assert!(validate_preferences(&with_chord(chord)).is_ok(), "{chord}");
+        }
+        for chord in ["q", "shift+a", "escape escape", "hyper+x"] {
+            assert!(validate_preferences(&with_chord(chord)).is_err(), "{chord}");
+        }
+        assert_eq!(with_chord("").typing.cancel_chord(), None);
+        assert_eq!(
+            TypingPrefs::default().cancel_chord().map(|chord| chord.key),
+            Some(NamedKey::Escape)
+        );
+    }
+
+    #[test]
+    fn test_validation_scratch_chord() {
+        let with_chords = |cancel: &str, scratch: &str| Preferences {
+            typing: TypingPrefs {
+                cancel_chord: cancel.to_string(),
+                scratch_chord: scratch.to_string(),
+                ..TypingPrefs::default()
+            },
+            ..Preferences::default()
+        };
+        for scratch in ["ctrl+alt+z", "cmd+shift+backspace", ""] {
+            assert!(
+                validate_preferences(&with_chords("escape", scratch)).is_ok(),
+                "{scratch}"
+            );
+        }
+        for scratch in ["escape", "backspace", "ctrl+z ctrl+z"] {
+            assert!(
+                validate_preferences(&with_chords("escape", scratch)).is_err(),
+                "{scratch}"
+            );
+        }
+        let same = validate_preferences(&with_chords("ctrl+.", "ctrl+."));
+        assert!(same.unwrap_err().to_string().contains("differ"));
+        assert!(validate_preferences(&with_chords("", "")).is_ok());
+        assert_eq!(TypingPrefs::default().scratch_chord(), None);
+    }
+
+    #[test]
+    fn test_validation_clipboard_restore_too_long() {
+        let mut prefs = Preferences::default();
+        prefs.typing.clipboard_restore_ms = 10_000;
+        let result = validate_preferences(&prefs);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("clipboard_restore_ms"));
+    }
+
     #[test]
     fn test_validation_record_chunk_seconds_too_low() {
         let mut prefs = Preferences::default();
@@ -457,62 +1092,274 @@ mod tests {
     }
 
     #[test]
-    fn test_validation_voice_commands_empty_mappings() {
+    fn test_validation_voice_commands_empty_phrase() {
         let mut prefs = Preferences::default();
         prefs.voice_commands.enabled = true;
-        prefs.voice_commands.map.newline = String::new();
+        prefs.voice_commands.commands[0].phrases = vec![String::new()];
         let result = validate_preferences(&prefs);
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("voice command"));
     }
 
     #[test]
-    fn test_validation_voice_commands_all_fields_checked() {
-        let fields = [
-            "newline",
-            "new_paragraph",
-            "tab",
-            "period",
-            "comma",
-            "colon",
-            "semicolon",
-            "open_quote",
-            "close_quote",
-            "backtick",
-            "code_block",
-        ];
-        for field in fields {
+    fn test_validation_voice_commands_every_command_checked() {
+        for index in 0..Preferences::default().voice_commands.commands.len() {
             let mut prefs = Preferences::default();
             prefs.voice_commands.enabled = true;
-            match field {
-                "newline" => prefs.voice_commands.map.newline = String::new(),
-                "new_paragraph" => prefs.voice_commands.map.new_paragraph = String::new(),
-                "tab" => prefs.voice_commands.map.tab = String::new(),
-                "period" => prefs.voice_commands.map.period = String::new(),
-                "comma" => prefs.voice_commands.map.comma = String::new(),
-                "colon" => prefs.voice_commands.map.colon = String::new(),
-                "semicolon" => prefs.voice_commands.map.semicolon = String::new(),
-                "open_quote" => prefs.voice_commands.map.open_quote = String::new(),
-                "close_quote" => prefs.voice_commands.map.close_quote = String::new(),
-                "backtick" => prefs.voice_commands.map.backtick = String::new(),
-                "code_block" => prefs.voice_commands.map.code_block = String::new(),
-                _ => unreachable!(),
-            }
-            let result = validate_preferences(&prefs);
+            prefs.voice_commands.commands[index].phrases.clear();
             assert!(
-                result.is_err(),
-                "Validation should fail when {} is empty",
-                field
+                validate_preferences(&prefs).is_err(),
+                "Validation should fail when command {} has no phrase",
+                index
             );
         }
     }
 
+    #[test]
+    fn test_validation_voice_commands_conflicts() {
+        let mut prefs = Preferences::default();
+        prefs.voice_commands.commands.push(VoiceCommand::new(
+            "Tab",
+            CommandAction::Text("    ".to_string()),
+        ));
+        let result = validate_preferences(&prefs);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("tab"));
+    }
+
     #[test]
     fn test_validation_voice_commands_disabled_ignores_mapping() {
         let mut prefs = Preferences::default();
         prefs.voice_commands.enabled = false;
-        prefs.voice_commands.map.newline = String::new();
+        prefs.voice_commands.commands[0].phrases = vec![String::new()];
+        assert!(validate_preferences(&prefs).is_ok());
+    }
+
+    #[test]
+    fn test_validation_disfluency_empty_phrase() {
+        let mut prefs = Preferences::default();
+        prefs
+            .disfluency_filter
+            .fillers
+            .get_mut("en")
+            .unwrap()
+            .push("  ".to_string());
+        let result = validate_preferences(&prefs);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("phrases"));
+
+        prefs.disfluency_filter.enabled = false;
+        assert!(validate_preferences(&prefs).is_ok());
+    }
+
+    fn terminal_profile() -> AppProfile {
+        AppProfile {
+            name: "Shell".to_string(),
+            apps: vec!["Terminal".to_string(), "com.googlecode.iterm2".to_string()],
+            typing_method: Some(TypingMethod::Clipboard),
+            newline_at_end: Some(true),
+            voice_commands: Some(VoiceCommands {
+                enabled: false,
+                commands: Vec::new(),
+            }),
+            translate_to_english: Some(false),
+            ..AppProfile::default()
+        }
+    }
+
+    #[test]
+    fn test_app_profile_matches_name_or_bundle_id() {
+        let prefs = Preferences {
+            app_profiles: vec![terminal_profile()],
+            ..Preferences::default()
+        };
+
+        assert!(prefs.app_profile(Some("terminal"), None).is_some());
+        assert!(prefs
+            .app_profile(Some("iTerm2"), Some("com.googlecode.iTerm2"))
+            .is_some());
+        assert!(prefs
+            .app_profile(Some("Safari"), Some("com.apple.Safari"))
+            .is_none());
+        assert!(prefs.app_profile(None, None).is_none());
+    }
+
+    #[test]
+    fn test_with_profile_overrides_only_set_fields() {
+        let mut prefs = Preferences::default();
+        prefs.typing.throttle_ms = 20;
+        let applied = prefs.with_profile(&terminal_profile());
+
+        assert_eq!(applied.typing.method, TypingMethod::Clipboard);
+        assert!(applied.typing.newline_at_end);
+        assert_eq!(applied.typing.throttle_ms, 20);
+        assert!(!applied.voice_commands.enabled);
+        assert!(!applied.translate_to_english);
+        assert_eq!(applied.model_profile, prefs.model_profile);
+        assert_eq!(applied.translation, prefs.translation);
+        assert_eq!(prefs.with_profile(&AppProfile::default()), prefs);
+    }
+
+    #[test]
+    fn test_validation_app_profiles() {
+        let mut prefs = Preferences {
+            app_profiles: vec![terminal_profile()],
+            ..Preferences::default()
+        };
+        assert!(validate_preferences(&prefs).is_ok());
+
+        prefs.app_profiles[0].apps = vec![" ".to_string()];
+        assert!(validate_preferences(&prefs)
+            .unwrap_err()
+            .to_string()
+            .contains("at least one app"));
+
+        prefs.app_profiles = vec![terminal_profile(), terminal_profile()];
+        assert!(validate_preferences(&prefs)
+            .unwrap_err()
+            .to_string()
+            .contains("More than one"));
+
+        prefs.app_profiles = vec![AppProfile {
+            throttle_ms: Some(5000),
+            ..terminal_profile()
+        }];
+        assert!(validate_preferences(&prefs).is_err());
+    }
+
+    #[test]
+    fn test_app_profiles_default_when_missing() {
+        let json = serde_json::to_value(Preferences::default()).unwrap();
+        let mut fields = json.as_object().unwrap().clone();
+        fields.remove("app_profiles");
+        fields["typing"].as_object_mut().unwrap().remove("method");
+        let prefs: Preferences = serde_json::from_value(fields.into()).unwrap();
+        assert!(prefs.app_profiles.is_empty());
+        assert_eq!(prefs.typing.method, TypingMethod::Keystroke);
+
+        let profile: AppProfile =
+            serde_json::from_str(r#"{ "name": "Editor", "apps": ["Code"], "code_mode": true }"#)
+                .unwrap();
+        assert_eq!(profile.code_mode, Some(true));
+        assert_eq!(profile.model_profile, None);
+    }
+
+    #[test]
+    fn test_keyboard_layout_default_when_missing() {
+        let json = serde_json::to_value(Preferences::default()).unwrap();
+        let mut fields = json.as_object().unwrap().clone();
+        fields["typing"].as_object_mut().unwrap().remove("layout");
+        fields["typing"]
+            .as_object_mut()
+            .unwrap()
+            .remove("clipboard_restore_ms");
+        let prefs: Preferences = serde_json::from_value(fields.clone().into()).unwrap();
+        assert_eq!(prefs.typing.layout, KeyboardLayout::Us);
+        assert_eq!(prefs.typing.clipboard_restore_ms, 250);
+
+        fields["typing"]["layout"] = "dvorak".into();
+        let prefs: Preferences = serde_json::from_value(fields.into()).unwrap();
+        assert_eq!(prefs.typing.layout, KeyboardLayout::Dvorak);
+    }
+
+    #[test]
+    fn test_validation_output_path() {
+        let prefs = Preferences {
+            output: OutputPrefs {
+                target: OutputTarget::File,
+                path: " ".to_string(),
+            },
+            ..Preferences::default()
+        };
+        assert!(validate_preferences(&prefs).is_err());
+
+        let prefs = Preferences {
+            output: OutputPrefs {
+                target: OutputTarget::Socket,
+                path: "/tmp/stt.sock".to_string(),
+            },
+            ..Preferences::default()
+        };
         assert!(validate_preferences(&prefs).is_ok());
+        assert!(validate_preferences(&Preferences::default()).is_ok());
+    }
+
+    #[test]
+    fn test_validation_snippets() {
+        let mut prefs = Preferences::default();
+        prefs.snippets.entries.push(Snippet {
+            trigger: "[".to_string(),
+            expansion: "x".to_string(),
+            regex: true,
+        });
+        let result = validate_preferences(&prefs);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("snippet"));
+
+        prefs.snippets.enabled = false;
+        assert!(validate_preferences(&prefs).is_ok());
+    }
+
+    #[test]
+    fn test_snippets_saved_next_to_config() {
+        let dir = std::env::temp_dir().join(format!("stt-prefs-snippets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut saved = Preferences::default();
+        saved.snippets.entries = vec![Snippet::new("my email", "me@example.com")];
+        let prefs = Prefs {
+            inner: RwLock::new(saved.clone()),
+            config_path: dir.join("config.json"),
+        };
+        prefs.save().unwrap();
+
+        let config = fs::read_to_string(dir.join("config.json")).unwrap();
+        assert!(!config.contains("me@example.com"));
+        assert!(dir.join(SNIPPETS_FILE).exists());
+        assert_eq!(
+            Prefs::load_from_file(&dir.join("config.json")).unwrap(),
+            saved
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_validation_itn_unsupported_language() {
+        let mut prefs = Preferences::default();
+        prefs.itn.languages.push("de".to_string());
+        let result = validate_preferences(&prefs);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("\"de\""));
+
+        prefs.itn.enabled = false;
+        assert!(validate_preferences(&prefs).is_ok());
+    }
+
+    #[test]
+    fn test_validation_itn_accepts_regional_language() {
+        let mut prefs = Preferences::default();
+        prefs.itn.languages = vec!["en-US".to_string()];
+        assert!(validate_preferences(&prefs).is_ok());
+    }
+
+    #[test]
+    fn test_voice_commands_migrate_legacy_map() {
+        let json = r#"{ "enabled": true, "map": { "newline": "next line", "tab": "" } }"#;
+        let commands: VoiceCommands = serde_json::from_str(json).unwrap();
+        assert_eq!(commands.commands.len(), 15);
+        assert_eq!(
+            commands.commands[0],
+            VoiceCommand::new("next line", CommandAction::Builtin(BuiltinCommand::Newline))
+        );
+        assert!(!commands
+            .commands
+            .iter()
+            .any(|c| c.action == CommandAction::Builtin(BuiltinCommand::Tab)));
+
+        let saved = serde_json::to_string(&commands).unwrap();
+        assert!(!saved.contains("\"map\""));
+        let reloaded: VoiceCommands = serde_json::from_str(&saved).unwrap();
+        assert_eq!(reloaded, commands);
     }
 
     #[test]
@@ -529,6 +1376,29 @@ mod tests {
         assert_eq!(json, "\"multilingual-medium\"");
     }
 
+    #[test]
+    fn test_imported_model_profile_serialization() {
+        let profile: ModelProfile = serde_json::from_str(r#""medium.en-0badf00d""#).unwrap();
+        assert_eq!(
+            profile,
+            ModelProfile::Imported("medium.en-0badf00d".to_string())
+        );
+        assert_eq!(
+            serde_json::to_string(&profile).unwrap(),
+            r#""medium.en-0badf00d""#
+        );
+
+        for unknown in [
+            "huge",
+            "small-xyz",
+            "-0badf00d",
+            "Small-0badf00d",
+            "small-0badf00",
+        ] {
+            assert!(serde_json::from_str::<ModelProfile>(&format!("\"{}\"", unknown)).is_err());
+        }
+    }
+
     #[test]
     fn test_activation_mode_serialization() {
         let hold = r#""hold""#;
@@ -1,5 +1,7 @@
 use crate::db::{count_chars, count_words, Database, Entry, EntryCreate, Session, SessionCreate};
-use crate::prefs::Preferences;
+use crate::prefs::{AppProfile, ModelProfile, Preferences, TextKind};
+use crate::redaction::redact_for_storage;
+use crate::stt::TranscriptionResult;
 use crate::AppState;
 use parking_lot::RwLock;
 use std::sync::Arc;
@@ -9,20 +11,108 @@ pub struct SessionManager {
     current_session: RwLock<Option<CurrentSession>>,
 }
 
-struct CurrentSession {
+#[derive(Debug, Clone, Default)]
+pub struct EntryDetails {
+    pub language: Option<String>,
+    pub language_probability: Option<f32>,
+    pub original_text: Option<String>,
+    pub translated_text: Option<String>,
+    pub unfiltered_text: Option<String>,
+    pub typed_chars: Option<usize>,
+}
+
+impl From<&TranscriptionResult> for EntryDetails {
+    fn from(result: &TranscriptionResult) -> Self {
+        Self {
+            language: result.language.clone(),
+            language_probability: result.language_probability,
+            original_text: result.original_text.clone(),
+            translated_text: result.translated_text.clone(),
+            unfiltered_text: result.unfiltered_text.clone(),
+            typed_chars: None,
+        }
+    }
+}
+
+pub struct CurrentSession {
     id: String,
     started_at: i64,
     mode: crate::db::SessionMode,
+    app_name: Option<String>,
+    profile: Option<AppProfile>,
+}
+
+impl CurrentSession {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn mode(&self) -> crate::db::SessionMode {
+        self.mode
+    }
+
+    pub fn app_name(&self) -> Option<&str> {
+        self.app_name.as_deref()
+    }
+
+    pub fn profile(&self) -> Option<&AppProfile> {
+        self.profile.as_ref()
+    }
+
+    /// `prefs` with this session's app profile applied.
+    pub fn preferences(&self, prefs: &Preferences) -> Preferences {
+        match &self.profile {
+            Some(profile) => prefs.with_profile(profile),
+            None => prefs.clone(),
+        }
+    }
 }
 
 fn model_profile_to_string(profile: &crate::prefs::ModelProfile) -> String {
-    match profile {
-        crate::prefs::ModelProfile::EnglishSmall => "small.en".to_string(),
-        crate::prefs::ModelProfile::MultilingualSmall => "multilingual-small".to_string(),
-        crate::prefs::ModelProfile::MultilingualMedium => "multilingual-medium".to_string(),
+    profile.as_str().to_string()
+}
+
+fn initial_session_language(prefs: &Preferences) -> Option<String> {
+    match prefs.model_profile {
+        ModelProfile::EnglishSmall => Some("en".to_string()),
+        ModelProfile::MultilingualSmall
+        | ModelProfile::MultilingualMedium
+        | ModelProfile::Imported(_) => None,
     }
 }
 
+fn dominant_language(entries: &[Entry]) -> Option<String> {
+    let mut weights: Vec<(&str, i64)> = Vec::new();
+    for entry in entries {
+        if let Some(language) = entry.language.as_deref() {
+            let weight = count_chars(&entry.text).max(1);
+            match weights.iter_mut().find(|(l, _)| *l == language) {
+                Some((_, total)) => *total += weight,
+                None => weights.push((language, weight)),
+            }
+        }
+    }
+
+    // Reverse so ties go to the language that was heard first.
+    weights
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, weight)| *weight)
+        .map(|(language, _)| language.to_string())
+}
+
+fn finalize_session_language(
+    db: &Database,
+    session_id: &str,
+    entries: &[Entry],
+) -> Result<(), String> {
+    if let Some(language) = dominant_language(entries) {
+        db.set_session_language(session_id, Some(&language))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 impl SessionManager {
     pub fn new(db: Arc<Database>) -> Self {
         Self {
@@ -37,6 +127,22 @@ impl SessionManager {
         prefs: &Preferences,
         app_name: Option<String>,
     ) -> Result<Session, String> {
+        self.start_session_with_profile(mode, prefs, app_name, None)
+    }
+
+    /// Starts a session whose settings come from `prefs` with `profile`
+    /// applied, and records the profile's name on it.
+    pub fn start_session_with_profile(
+        &self,
+        mode: crate::db::SessionMode,
+        prefs: &Preferences,
+        app_name: Option<String>,
+        profile: Option<AppProfile>,
+    ) -> Result<Session, String> {
+        let prefs = match &profile {
+            Some(profile) => prefs.with_profile(profile),
+            None => prefs.clone(),
+        };
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .map_err(|e| e.to_string())?
@@ -46,10 +152,11 @@ impl SessionManager {
             id: uuid_v4(),
             mode,
             started_at: now,
-            language: None,
+            language: initial_session_language(&prefs),
             model_profile: model_profile_to_string(&prefs.model_profile),
             translated: prefs.translate_to_english,
-            app_name,
+            app_name: app_name.clone(),
+            profile: profile.as_ref().map(|profile| profile.name.clone()),
         };
 
         let created = self.db.create_session(session).map_err(|e| e.to_string())?;
@@ -58,6 +165,8 @@ impl SessionManager {
             id: created.id.clone(),
             started_at: now,
             mode,
+            app_name,
+            profile,
         });
 
         log::info!("Started session: {}", created.id);
@@ -65,67 +174,128 @@ impl SessionManager {
     }
 
     pub fn end_session(&self) -> Result<Option<Session>, String> {
-        let current = self.current_session.write().take();
-        if let Some(session) = current {
-            let entries = self
-                .db
-                .get_entries_by_session(&session.id)
-                .map_err(|e| e.to_string())?;
-
-            let total_chars: i64 = entries.iter().map(|e| count_chars(&e.text)).sum();
-            let total_words: i64 = entries.iter().map(|e| count_words(&e.text)).sum();
-
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map_err(|e| e.to_string())?
-                .as_millis() as i64;
-
-            let updated = self
-                .db
-                .update_session(&session.id, Some(now), Some(total_chars), Some(total_words))
-                .map_err(|e| e.to_string())?;
-
-            log::info!(
-                "Ended session: {} with {} chars, {} words",
-                session.id,
-                total_chars,
-                total_words
-            );
-            Ok(updated)
-        } else {
-            Ok(None)
+        match self.detach_session() {
+            Some(session) => self.finish_session(session),
+            None => Ok(None),
         }
     }
 
+    /// Stops tracking the current session without closing it, so a new session
+    /// can start while this one is still being transcribed.
+    pub fn detach_session(&self) -> Option<CurrentSession> {
+        self.current_session.write().take()
+    }
+
+    pub fn finish_session(&self, session: CurrentSession) -> Result<Option<Session>, String> {
+        let entries = self
+            .db
+            .get_entries_by_session(&session.id)
+            .map_err(|e| e.to_string())?;
+
+        let total_chars: i64 = entries.iter().map(|e| count_chars(&e.text)).sum();
+        let total_words: i64 = entries.iter().map(|e| count_words(&e.text)).sum();
+
+        finalize_session_language(&self.db, &session.id, &entries)?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_millis() as i64;
+
+        let updated = self
+            .db
+            .update_session(&session.id, Some(now), Some(total_chars), Some(total_words))
+            .map_err(|e| e.to_string())?;
+
+        log::info!(
+            "Ended session: {} with {} chars, {} words",
+            session.id,
+            total_chars,
+            total_words
+        );
+        Ok(updated)
+    }
+
     pub fn add_entry(
         &self,
         text: &str,
         typed: bool,
         source: crate::db::SessionMode,
+    ) -> Result<Entry, String> {
+        self.add_entry_with_details(text, typed, source, EntryDetails::default())
+    }
+
+    pub fn add_entry_with_details(
+        &self,
+        text: &str,
+        typed: bool,
+        source: crate::db::SessionMode,
+        details: EntryDetails,
     ) -> Result<Entry, String> {
         let current = self.current_session.read();
         if let Some(session) = current.as_ref() {
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map_err(|e| e.to_string())?
-                .as_millis() as i64;
-
-            let entry = EntryCreate {
-                id: uuid_v4(),
-                session_id: session.id.clone(),
-                started_at: session.started_at,
-                ended_at: now,
-                text: text.to_string(),
-                source,
-                typed,
-            };
-
-            self.db.create_entry(entry).map_err(|e| e.to_string())
+            self.add_entry_to(session, text, typed, source, details)
         } else {
             Err("No active session".to_string())
         }
     }
 
+    pub fn add_entry_to(
+        &self,
+        session: &CurrentSession,
+        text: &str,
+        typed: bool,
+        source: crate::db::SessionMode,
+        details: EntryDetails,
+    ) -> Result<Entry, String> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_millis() as i64;
+
+        let entry = EntryCreate {
+            id: uuid_v4(),
+            session_id: session.id.clone(),
+            started_at: session.started_at,
+            ended_at: now,
+            text: text.to_string(),
+            source,
+            typed,
+            language: details.language,
+            language_probability: details.language_probability,
+            speaker: None,
+            original_text: details.original_text,
+            translated_text: details.translated_text,
+            unfiltered_text: details.unfiltered_text,
+            typed_chars: details.typed_chars.map(|chars| chars as i64),
+        };
+
+        self.db.create_entry(entry).map_err(|e| e.to_string())
+    }
+
+    /// Records that an entry's typed text was removed again.
+    pub fn retract_entry(&self, entry_id: &str) -> Result<bool, String> {
+        self.db
+            .set_entry_retracted(entry_id)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Stores the text of a pass that finished after the entry was created.
+    pub fn attach_entry_text(
+        &self,
+        entry_id: &str,
+        kind: TextKind,
+        text: String,
+    ) -> Result<Option<Entry>, String> {
+        let (original_text, translated_text) = match kind {
+            TextKind::Original => (Some(text), None),
+            TextKind::Translation => (None, Some(text)),
+        };
+        self.db
+            .update_entry_texts(entry_id, original_text, translated_text)
+            .map_err(|e| e.to_string())
+    }
+
     pub fn get_current_session_id(&self) -> Option<String> {
         self.current_session.read().as_ref().map(|s| s.id.clone())
     }
@@ -158,7 +328,10 @@ pub fn create_session_workflow(
 ) -> Result<Session, String> {
     let prefs = state.prefs.get();
     let app_name = get_frontmost_app_name_internal();
-    state.session_manager.start_session(mode, &prefs, app_name)
+    let profile = crate::frontmost_app_profile(&prefs, app_name.as_deref());
+    state
+        .session_manager
+        .start_session_with_profile(mode, &prefs, app_name, profile)
 }
 
 pub fn end_session_workflow(state: &AppState) -> Result<Option<Session>, String> {
@@ -170,7 +343,8 @@ pub fn add_typed_entry(state: &AppState, text: &str) -> Result<Entry, String> {
         .session_manager
         .get_current_session_mode()
         .ok_or("No active session")?;
-    state.session_manager.add_entry(text, true, mode)
+    let text = redact_for_storage(text, &state.prefs.get().redaction);
+    state.session_manager.add_entry(&text, true, mode)
 }
 
 pub fn add_untyped_entry(state: &AppState, text: &str) -> Result<Entry, String> {
@@ -178,7 +352,8 @@ pub fn add_untyped_entry(state: &AppState, text: &str) -> Result<Entry, String>
         .session_manager
         .get_current_session_mode()
         .ok_or("No active session")?;
-    state.session_manager.add_entry(text, false, mode)
+    let text = redact_for_storage(text, &state.prefs.get().redaction);
+    state.session_manager.add_entry(&text, false, mode)
 }
 
 #[cfg(target_os = "macos")]
@@ -211,10 +386,11 @@ pub fn start_record_session(
         id: uuid_v4(),
         mode: crate::db::SessionMode::Record,
         started_at: now,
-        language: None,
+        language: initial_session_language(prefs),
         model_profile: model_profile_to_string(&prefs.model_profile),
         translated: prefs.translate_to_english,
         app_name: None,
+        profile: None,
     };
 
     state.db.create_session(session).map_err(|e| e.to_string())
@@ -235,6 +411,8 @@ pub fn end_record_session(state: &crate::AppState) -> Result<Option<crate::db::S
         let total_chars: i64 = entries.iter().map(|e| count_chars(&e.text)).sum();
         let total_words: i64 = entries.iter().map(|e| count_words(&e.text)).sum();
 
+        finalize_session_language(&state.db, &session.id, &entries)?;
+
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .map_err(|e| e.to_string())?
@@ -286,6 +464,38 @@ mod tests {
         assert!(ended.words_count > 0);
     }
 
+    #[test]
+    fn test_session_records_app_profile() {
+        let db = Arc::new(Database::new_in_memory().unwrap());
+        let manager = SessionManager::new(Arc::clone(&db));
+        let prefs = test_prefs();
+        let profile = AppProfile {
+            name: "Shell".to_string(),
+            apps: vec!["Terminal".to_string()],
+            translate_to_english: Some(!prefs.translate_to_english),
+            throttle_ms: Some(40),
+            ..AppProfile::default()
+        };
+
+        let created = manager
+            .start_session_with_profile(
+                crate::db::SessionMode::Hold,
+                &prefs,
+                Some("Terminal".to_string()),
+                Some(profile),
+            )
+            .unwrap();
+        assert_eq!(created.profile.as_deref(), Some("Shell"));
+        assert_eq!(created.translated, !prefs.translate_to_english);
+
+        let session = manager.detach_session().unwrap();
+        assert_eq!(session.profile().map(|p| p.name.as_str()), Some("Shell"));
+        assert_eq!(session.preferences(&prefs).typing.throttle_ms, 40);
+
+        let stored = db.get_session(&created.id).unwrap().unwrap();
+        assert_eq!(stored.profile.as_deref(), Some("Shell"));
+    }
+
     #[test]
     fn test_toggle_mode_session_persists_toggle_source_entry() {
         let db = Database::new_in_memory().unwrap();
@@ -566,6 +776,126 @@ mod tests {
         assert_eq!(ended.chars_count, entries[0].text.len() as i64);
     }
 
+    #[test]
+    fn test_english_profile_session_starts_with_english_language() {
+        let db = Arc::new(Database::new_in_memory().unwrap());
+        let manager = SessionManager::new(db.clone());
+
+        let prefs = Preferences {
+            model_profile: ModelProfile::EnglishSmall,
+            ..Default::default()
+        };
+        let session = manager
+            .start_session(crate::db::SessionMode::Hold, &prefs, None)
+            .unwrap();
+
+        assert_eq!(session.language, Some("en".to_string()));
+    }
+
+    #[test]
+    fn test_entry_details_persist_detected_language() {
+        let db = Arc::new(Database::new_in_memory().unwrap());
+        let manager = SessionManager::new(db.clone());
+
+        let prefs = test_prefs();
+        let session = manager
+            .start_session(crate::db::SessionMode::Hold, &prefs, None)
+            .unwrap();
+        assert_eq!(session.language, None);
+
+        let details = EntryDetails {
+            language: Some("fr".to_string()),
+            language_probability: Some(0.87),
+            ..Default::default()
+        };
+        manager
+            .add_entry_with_details("bonjour", true, crate::db::SessionMode::Hold, details)
+            .unwrap();
+
+        let entries = db.get_entries_by_session(&session.id).unwrap();
+        assert_eq!(entries[0].language, Some("fr".to_string()));
+        assert_eq!(entries[0].language_probability, Some(0.87));
+    }
+
+    #[test]
+    fn test_attach_entry_text_fills_missing_translation() {
+        let db = Arc::new(Database::new_in_memory().unwrap());
+        let manager = SessionManager::new(db.clone());
+        manager
+            .start_session(crate::db::SessionMode::Hold, &test_prefs(), None)
+            .unwrap();
+
+        let details = EntryDetails {
+            original_text: Some("hola".to_string()),
+            ..Default::default()
+        };
+        let entry = manager
+            .add_entry_with_details("hola", true, crate::db::SessionMode::Hold, details)
+            .unwrap();
+        assert_eq!(entry.translated_text, None);
+
+        let updated = manager
+            .attach_entry_text(&entry.id, TextKind::Translation, "hello".to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.text, "hola");
+        assert_eq!(updated.original_text, Some("hola".to_string()));
+        assert_eq!(updated.translated_text, Some("hello".to_string()));
+    }
+
+    #[test]
+    fn test_end_session_records_dominant_language() {
+        let db = Arc::new(Database::new_in_memory().unwrap());
+        let manager = SessionManager::new(db.clone());
+
+        let prefs = test_prefs();
+        manager
+            .start_session(crate::db::SessionMode::Hold, &prefs, None)
+            .unwrap();
+
+        let spanish = EntryDetails {
+            language: Some("es".to_string()),
+            language_probability: Some(0.9),
+            ..Default::default()
+        };
+        let english = EntryDetails {
+            language: Some("en".to_string()),
+            language_probability: Some(0.8),
+            ..Default::default()
+        };
+        manager
+            .add_entry_with_details("ok", true, crate::db::SessionMode::Hold, english)
+            .unwrap();
+        manager
+            .add_entry_with_details(
+                "hola a todos, buenos dias",
+                true,
+                crate::db::SessionMode::Hold,
+                spanish,
+            )
+            .unwrap();
+
+        let ended = manager.end_session().unwrap().unwrap();
+        assert_eq!(ended.language, Some("es".to_string()));
+    }
+
+    #[test]
+    fn test_end_session_without_detected_language_keeps_none() {
+        let db = Arc::new(Database::new_in_memory().unwrap());
+        let manager = SessionManager::new(db.clone());
+
+        let prefs = test_prefs();
+        manager
+            .start_session(crate::db::SessionMode::Hold, &prefs, None)
+            .unwrap();
+        manager
+            .add_entry("no language", true, crate::db::SessionMode::Hold)
+            .unwrap();
+
+        let ended = manager.end_session().unwrap().unwrap();
+        assert_eq!(ended.language, None);
+    }
+
     #[test]
     fn test_persisted_session_has_correct_mode_after_end() {
         let db = Arc::new(Database::new_in_memory().unwrap());
@@ -586,4 +916,42 @@ mod tests {
         assert_eq!(persisted.mode, crate::db::SessionMode::Hold);
         assert!(persisted.ended_at.is_some());
     }
+
+    #[test]
+    fn test_detached_session_finishes_while_new_session_runs() {
+        let db = Arc::new(Database::new_in_memory().unwrap());
+        let manager = SessionManager::new(db.clone());
+        let prefs = test_prefs();
+
+        let first = manager
+            .start_session(crate::db::SessionMode::Hold, &prefs, None)
+            .unwrap();
+        let detached = manager.detach_session().unwrap();
+        assert!(!manager.is_active());
+
+        let second = manager
+            .start_session(crate::db::SessionMode::Toggle, &prefs, None)
+            .unwrap();
+        manager
+            .add_entry_to(
+                &detached,
+                "late transcription",
+                true,
+                crate::db::SessionMode::Hold,
+                EntryDetails::default(),
+            )
+            .unwrap();
+        let finished = manager.finish_session(detached).unwrap().unwrap();
+
+        assert_eq!(finished.id, first.id);
+        assert!(finished.ended_at.is_some());
+        assert_eq!(finished.words_count, 2);
+        assert_eq!(manager.get_current_session_id(), Some(second.id.clone()));
+        assert!(db
+            .get_session(&second.id)
+            .unwrap()
+            .unwrap()
+            .ended_at
+            .is_none());
+    }
 }
@@ -0,0 +1,392 @@
+use crate::voice_commands::word_core;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+pub const SNIPPETS_FILE: &str = "snippets.json";
+
+#[derive(Debug, Error)]
+pub enum SnippetError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid snippet: {0}")]
+    Invalid(String),
+}
+
+/// A spoken trigger and the text it expands into. Expansions can use the
+/// `{date}`, `{time}`, `{clipboard}` and `{app}` variables.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snippet {
+    /// Spoken phrase, or a regular expression when `regex` is set.
+    pub trigger: String,
+    pub expansion: String,
+    /// Match `trigger` as a case-insensitive regular expression; the
+    /// expansion can refer to its groups as `$1` or `${name}`.
+    #[serde(default)]
+    pub regex: bool,
+}
+
+impl Snippet {
+    pub fn new(trigger: &str, expansion: &str) -> Self {
+        Self {
+            trigger: trigger.to_string(),
+            expansion: expansion.to_string(),
+            regex: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Snippets {
+    pub enabled: bool,
+    pub entries: Vec<Snippet>,
+}
+
+impl Default for Snippets {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            entries: vec![
+                Snippet::new("todo marker", "// TODO:"),
+                Snippet::new("today's date", "{date}"),
+            ],
+        }
+    }
+}
+
+pub fn load_snippets(path: &Path) -> Result<Snippets, SnippetError> {
+    let content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+pub fn save_snippets(path: &Path, snippets: &Snippets) -> Result<(), SnippetError> {
+    let content = serde_json::to_string_pretty(snippets)?;
+    fs::write(path, content)?;
+    Ok(())
+}
+
+pub fn validate_snippets(snippets: &[Snippet]) -> Result<(), SnippetError> {
+    let mut phrases: Vec<Vec<String>> = Vec::new();
+    for snippet in snippets {
+        if snippet.trigger.trim().is_empty() {
+            return Err(SnippetError::Invalid(
+                "Every snippet needs a trigger".to_string(),
+            ));
+        }
+        if snippet.regex {
+            compile(&snippet.trigger)?;
+            continue;
+        }
+        let words = trigger_words(&snippet.trigger);
+        if words.is_empty() {
+            return Err(SnippetError::Invalid(format!(
+                "Trigger \"{}\" has no words",
+                snippet.trigger
+            )));
+        }
+        if phrases.contains(&words) {
+            return Err(SnippetError::Invalid(format!(
+                "Trigger \"{}\" is used by more than one snippet",
+                snippet.trigger
+            )));
+        }
+        phrases.push(words);
+    }
+    Ok(())
+}
+
+fn compile(pattern: &str) -> Result<Regex, SnippetError> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| SnippetError::Invalid(format!("Trigger \"{}\": {}", pattern, e)))
+}
+
+fn trigger_words(trigger: &str) -> Vec<String> {
+    trigger
+        .split_whitespace()
+        .map(word_core)
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Values substituted for the variables in an expansion.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SnippetVariables {
+    pub date: String,
+    pub time: String,
+    pub clipboard: Option<String>,
+    pub app_name: Option<String>,
+}
+
+impl SnippetVariables {
+    /// Reads the clock, and the clipboard and frontmost app only when an
+    /// expansion asks for them.
+    pub fn capture(snippets: &[Snippet]) -> Self {
+        let uses = |variable: &str| {
+            snippets
+                .iter()
+                .any(|snippet| snippet.expansion.contains(variable))
+        };
+        let now = chrono::Local::now();
+        Self {
+            date: now.format("%Y-%m-%d").to_string(),
+            time: now.format("%H:%M").to_string(),
+            clipboard: uses("{clipboard}")
+                .then(|| arboard::Clipboard::new().ok()?.get_text().ok())
+                .flatten(),
+            app_name: uses("{app}")
+                .then(crate::get_frontmost_app_name_internal)
+                .flatten(),
+        }
+    }
+
+    fn substitute(&self, expansion: &str) -> String {
+        expansion
+            .replace("{date}", &self.date)
+            .replace("{time}", &self.time)
+            .replace("{clipboard}", self.clipboard.as_deref().unwrap_or_default())
+            .replace("{app}", self.app_name.as_deref().unwrap_or_default())
+    }
+}
+
+/// Replaces spoken triggers in `text` with their expansions. Phrase
+/// triggers match whole words case-insensitively, longer phrases first,
+/// and keep punctuation whisper put after them unless the expansion already
+/// ends in a period, comma, colon or the like. Regex triggers then run over the result.
+pub fn expand_snippets(text: &str, snippets: &[Snippet], variables: &SnippetVariables) -> String {
+    let mut phrases: Vec<(Vec<String>, &Snippet)> = snippets
+        .iter()
+        .filter(|snippet| !snippet.regex)
+        .map(|snippet| (trigger_words(&snippet.trigger), snippet))
+        .filter(|(words, _)| !words.is_empty())
+        .collect();
+    phrases.sort_by_key(|(words, _)| std::cmp::Reverse(words.len()));
+
+    let mut output = String::with_capacity(text.len());
+    for piece in text.split_inclusive(['\n', '\t']) {
+        let line = piece.strip_suffix(['\n', '\t']).unwrap_or(piece);
+        output.push_str(&expand_line(line, &phrases, variables));
+        output.push_str(&piece[line.len()..]);
+    }
+
+    for snippet in snippets.iter().filter(|snippet| snippet.regex) {
+        let Ok(pattern) = compile(&snippet.trigger) else {
+            log::warn!("Skipping snippet with invalid regex {:?}", snippet.trigger);
+            continue;
+        };
+        output = pattern
+            .replace_all(&output, |captures: &regex::Captures| {
+                let mut expanded = String::new();
+                captures.expand(&snippet.expansion, &mut expanded);
+                variables.substitute(&expanded)
+            })
+            .into_owned();
+    }
+    output
+}
+
+fn expand_line(
+    line: &str,
+    phrases: &[(Vec<String>, &Snippet)],
+    variables: &SnippetVariables,
+) -> String {
+    let tokens: Vec<&str> = line.split(' ').collect();
+    let cores: Vec<String> = tokens.iter().map(|token| word_core(token)).collect();
+
+    let mut output: Vec<String> = Vec::with_capacity(tokens.len());
+    let mut index = 0;
+    while index < tokens.len() {
+        let matched = phrases.iter().find(|(words, _)| {
+            cores
+                .get(index..index + words.len())
+                .is_some_and(|window| window == words.as_slice())
+        });
+        let Some((words, snippet)) = matched else {
+            output.push(tokens[index].to_string());
+            index += 1;
+            continue;
+        };
+
+        let first = tokens[index];
+        let last = tokens[index + words.len() - 1];
+        let lead = &first[..first.len() - first.trim_start_matches(is_edge).len()];
+        let trail = &last[last.trim_end_matches(is_edge).len()..];
+        let expansion = variables.substitute(&snippet.expansion);
+        let trail = if expansion.ends_with(is_sentence_punctuation)
+            && trail.starts_with(is_sentence_punctuation)
+        {
+            ""
+        } else {
+            trail
+        };
+        output.push(format!("{lead}{expansion}{trail}"));
+        index += words.len();
+    }
+    output.join(" ")
+}
+
+fn is_edge(c: char) -> bool {
+    !c.is_alphanumeric()
+}
+
+fn is_sentence_punctuation(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | ',' | ':' | ';')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> SnippetVariables {
+        SnippetVariables {
+            date: "2024-03-05".to_string(),
+            time: "15:30".to_string(),
+            clipboard: Some("https://example.com".to_string()),
+            app_name: Some("Code".to_string()),
+        }
+    }
+
+    fn snippets() -> Vec<Snippet> {
+        vec![
+            Snippet::new("todo marker", "// TODO(ourteam):"),
+            Snippet::new("my email", "me@example.com"),
+            Snippet::new("insert signature", "Best,\nSam"),
+            Snippet::new("today's date", "{date}"),
+            Snippet::new("paste link", "[link]({clipboard})"),
+            Snippet::new("where am I", "in {app} at {time}"),
+            Snippet::new("my", "mine"),
+        ]
+    }
+
+    #[test]
+    fn test_expand_snippets_cases() {
+        let cases = [
+            ("todo marker fix this", "// TODO(ourteam): fix this"),
+            ("send it to my email.", "send it to me@example.com."),
+            ("Send it to My Email", "Send it to me@example.com"),
+            ("thanks insert signature", "thanks Best,\nSam"),
+            ("due today's date", "due 2024-03-05"),
+            ("see paste link.", "see [link](https://example.com)."),
+            ("todo marker: fix this", "// TODO(ourteam): fix this"),
+            ("where am I", "in Code at 15:30"),
+            ("my car", "mine car"),
+            ("myemail", "myemail"),
+            ("\"my email\"", "\"me@example.com\""),
+            ("email me", "email me"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                expand_snippets(input, &snippets(), &variables()),
+                expected,
+                "input: {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand_snippets_keeps_line_breaks() {
+        assert_eq!(
+            expand_snippets("my email\nmy email\t", &snippets(), &variables()),
+            "me@example.com\nme@example.com\t"
+        );
+    }
+
+    #[test]
+    fn test_expand_snippets_missing_variables_are_empty() {
+        let variables = SnippetVariables::default();
+        assert_eq!(
+            expand_snippets("paste link", &snippets(), &variables),
+            "[link]()"
+        );
+    }
+
+    #[test]
+    fn test_expand_regex_snippets() {
+        let snippets = vec![
+            Snippet {
+                trigger: r"\bticket (\d+)\b".to_string(),
+                expansion: "JIRA-$1".to_string(),
+                regex: true,
+            },
+            Snippet {
+                trigger: r"\bstamp\b".to_string(),
+                expansion: "[{date} {time}]".to_string(),
+                regex: true,
+            },
+        ];
+        assert_eq!(
+            expand_snippets("fixes Ticket 42 and ticket 7", &snippets, &variables()),
+            "fixes JIRA-42 and JIRA-7"
+        );
+        assert_eq!(
+            expand_snippets("stamp done", &snippets, &variables()),
+            "[2024-03-05 15:30] done"
+        );
+    }
+
+    #[test]
+    fn test_invalid_regex_is_skipped_when_expanding() {
+        let snippets = vec![Snippet {
+            trigger: "(".to_string(),
+            expansion: "x".to_string(),
+            regex: true,
+        }];
+        assert_eq!(expand_snippets("a (b", &snippets, &variables()), "a (b");
+    }
+
+    #[test]
+    fn test_validate_snippets() {
+        assert!(validate_snippets(&snippets()).is_ok());
+        assert!(validate_snippets(&Snippets::default().entries).is_ok());
+
+        let empty = vec![Snippet::new("  ", "x")];
+        assert!(validate_snippets(&empty)
+            .unwrap_err()
+            .to_string()
+            .contains("trigger"));
+
+        let duplicate = vec![Snippet::new("My Email", "a"), Snippet::new("my email", "b")];
+        assert!(validate_snippets(&duplicate)
+            .unwrap_err()
+            .to_string()
+            .contains("more than one"));
+
+        let bad_regex = vec![Snippet {
+            trigger: "(".to_string(),
+            expansion: "x".to_string(),
+            regex: true,
+        }];
+        assert!(validate_snippets(&bad_regex).is_err());
+    }
+
+    #[test]
+    fn test_capture_skips_unused_variables() {
+        let variables = SnippetVariables::capture(&[Snippet::new("stamp", "{date}")]);
+        assert_eq!(variables.date.len(), 10);
+        assert_eq!(variables.time.len(), 5);
+        assert_eq!(variables.clipboard, None);
+        assert_eq!(variables.app_name, None);
+    }
+
+    #[test]
+    fn test_save_and_load_snippets() {
+        let dir = std::env::temp_dir().join(format!("stt-snippets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(SNIPPETS_FILE);
+
+        let snippets = Snippets {
+            enabled: false,
+            entries: snippets(),
+        };
+        save_snippets(&path, &snippets).unwrap();
+        assert_eq!(load_snippets(&path).unwrap(), snippets);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
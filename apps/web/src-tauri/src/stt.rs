@@ -1,14 +1,34 @@
 use anyhow::{Context, Result};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::{fs, thread};
-
-use crate::prefs::{ModelProfile, Preferences, VoiceCommandMap, VoiceCommands};
+use std::collections::BTreeMap;
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::audio::TARGET_SAMPLE_RATE;
+use crate::hallucination::filter_hallucinations;
+use crate::itn;
+use crate::models::{file_sha256, resolve_model, ModelSpec};
+use crate::prefs::{
+    CodeModePrefs, DisfluencyFilter, ItnPrefs, ModelProfile, Preferences, TextKind,
+    TranslationMode, VoiceCommands,
+};
+use crate::redaction::{redact_for_storage, redact_for_typing, RedactionPrefs};
+use crate::snippets::{expand_snippets, SnippetVariables, Snippets};
+use crate::type_::{ContextHeuristic, TypeOptions, Typer};
+use crate::voice_commands::{
+    inserted_text, parse_key_sequence, phrase_words, word_core, BuiltinCommand, CommandAction,
+    KeyChord, OutputAction, VoiceCommand,
+};
+pub use crate::windowing::TranscriptionProgress;
+use crate::windowing::{merge_window, plan_windows, prompt_tail};
 use whisper_rs::{
     FullParams, SamplingStrategy, WhisperContext as WhisperCtx, WhisperContextParameters,
+    WhisperState,
 };
 
 pub struct ModelInfo {
@@ -16,8 +36,10 @@ pub struct ModelInfo {
     pub sha256: String,
 }
 
-pub fn get_model_info(profile: &ModelProfile) -> ModelInfo {
-    match profile {
+/// File name and checksum of a built-in profile; imported models are looked
+/// up in the registry instead (see `models::resolve_model`).
+pub fn get_model_info(profile: &ModelProfile) -> Option<ModelInfo> {
+    let info = match profile {
         ModelProfile::EnglishSmall => ModelInfo {
             filename: "ggml-model-small.en.bin".to_string(),
             sha256: "c78c5ea52d4523d349d43c5e2e50d7d6e4e8c5e5a5e5e5e5e5e5e5e5e5e5e5e5".to_string(),
@@ -30,16 +52,19 @@ pub fn get_model_info(profile: &ModelProfile) -> ModelInfo {
             filename: "ggml-model-medium.bin".to_string(),
             sha256: "e90e7da74c674f5fa084d7d7d62f71f9f6a0e7d7b7d7d7d7d7d7d7d7d7d7d7d7".to_string(),
         },
-    }
+        ModelProfile::Imported(_) => return None,
+    };
+    Some(info)
 }
 
 pub struct WhisperContext {
     ctx: WhisperCtx,
+    multilingual: bool,
 }
 
 impl WhisperContext {
-    pub fn new(ctx: WhisperCtx) -> Self {
-        Self { ctx }
+    pub fn new(ctx: WhisperCtx, multilingual: bool) -> Self {
+        Self { ctx, multilingual }
     }
 }
 
@@ -47,6 +72,22 @@ impl WhisperContext {
 pub struct TranscriptionResult {
     pub text: String,
     pub segments: Vec<TextSegment>,
+    pub language: Option<String>,
+    pub language_probability: Option<f32>,
+    /// Source-language text, when decoded separately from the translation.
+    #[serde(default)]
+    pub original_text: Option<String>,
+    /// English translation, when decoded alongside the original.
+    #[serde(default)]
+    pub translated_text: Option<String>,
+    /// Text before filler words and stutters were removed, when the
+    /// disfluency filter changed it.
+    #[serde(default)]
+    pub unfiltered_text: Option<String>,
+    /// What typing this result does once voice commands are applied; `text`
+    /// is the part of it that inserts text.
+    #[serde(skip)]
+    pub actions: Vec<OutputAction>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,46 +95,488 @@ pub struct TextSegment {
     pub text: String,
     pub start_ms: i32,
     pub end_ms: i32,
+    #[serde(default)]
+    pub no_speech_prob: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
+
+/// Per-call decoding hints passed from `SttEngine` to the backend.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecodeOptions<'a> {
+    /// Text preceding this audio when a long buffer is decoded in windows.
+    pub prompt: Option<&'a str>,
+    /// Whether segment start/end times are needed (e.g. for subtitles).
+    pub timestamps: bool,
+    /// Translate to English instead of transcribing; ignored by English-only models.
+    pub translate: bool,
+}
+
+pub trait SttBackend: Send + Sync {
+    fn load_model(&self, profile: &ModelProfile, spec: &ModelSpec) -> Result<()>;
+
+    /// Returns the raw transcription; post-processing is applied by `SttEngine`.
+    /// Implementations should stop early once `cancel` reports cancellation.
+    fn transcribe(
+        &self,
+        profile: &ModelProfile,
+        audio_data: &[f32],
+        prefs: &Preferences,
+        options: DecodeOptions<'_>,
+        cancel: &CancelToken,
+    ) -> Result<TranscriptionResult>;
+}
+
+impl<T: SttBackend + ?Sized> SttBackend for Arc<T> {
+    fn load_model(&self, profile: &ModelProfile, spec: &ModelSpec) -> Result<()> {
+        (**self).load_model(profile, spec)
+    }
+
+    fn transcribe(
+        &self,
+        profile: &ModelProfile,
+        audio_data: &[f32],
+        prefs: &Preferences,
+        options: DecodeOptions<'_>,
+        cancel: &CancelToken,
+    ) -> Result<TranscriptionResult> {
+        (**self).transcribe(profile, audio_data, prefs, options, cancel)
+    }
 }
 
 pub struct SttEngine {
-    contexts: RwLock<Vec<(ModelProfile, Arc<WhisperContext>)>>,
-    current_profile: RwLock<Option<ModelProfile>>,
+    backend: Box<dyn SttBackend>,
+    current_model: RwLock<Option<LoadedModel>>,
+}
+
+/// A model the backend has loaded, which decodes can be run with.
+#[derive(Debug, Clone)]
+pub struct LoadedModel {
+    profile: ModelProfile,
+    multilingual: bool,
 }
 
 impl SttEngine {
     pub fn new() -> Self {
+        Self::with_backend(WhisperBackend::new())
+    }
+
+    pub fn with_backend(backend: impl SttBackend + 'static) -> Self {
         Self {
-            contexts: RwLock::new(Vec::new()),
-            current_profile: RwLock::new(None),
+            backend: Box::new(backend),
+            current_model: RwLock::new(None),
         }
     }
 
     pub fn load_model(&self, profile: ModelProfile, models_dir: PathBuf) -> Result<()> {
+        let model = self.prepare_model(&profile, &models_dir)?;
+        *self.current_model.write() = Some(model);
+        Ok(())
+    }
+
+    /// Loads `profile` into the backend for `transcribe_with_model` while
+    /// the engine keeps its own model. The backend keeps every model it
+    /// loaded, so preparing one again is cheap.
+    pub fn prepare_model(&self, profile: &ModelProfile, models_dir: &Path) -> Result<LoadedModel> {
+        let spec = resolve_model(profile, models_dir)?;
+        self.backend.load_model(profile, &spec)?;
+        Ok(LoadedModel {
+            profile: profile.clone(),
+            multilingual: spec.multilingual,
+        })
+    }
+
+    pub fn transcribe(
+        &self,
+        audio_data: &[f32],
+        prefs: &Preferences,
+    ) -> Result<TranscriptionResult> {
+        self.transcribe_cancellable(audio_data, prefs, &CancelToken::new())
+    }
+
+    pub fn transcribe_cancellable(
+        &self,
+        audio_data: &[f32],
+        prefs: &Preferences,
+        cancel: &CancelToken,
+    ) -> Result<TranscriptionResult> {
+        self.transcribe_with_progress(audio_data, prefs, cancel, |_| {})
+    }
+
+    /// Decodes audio longer than one whisper window in overlapping windows,
+    /// feeding each window the text so far as a prompt, and calls
+    /// `on_progress` after every window.
+    ///
+    /// When translating with a multilingual model, `prefs.translation` decides
+    /// whether the source-language text is decoded too: `text` is then the one
+    /// to type and `original_text`/`translated_text` hold whichever passes ran.
+    pub fn transcribe_with_progress<F>(
+        &self,
+        audio_data: &[f32],
+        prefs: &Preferences,
+        cancel: &CancelToken,
+        on_progress: F,
+    ) -> Result<TranscriptionResult>
+    where
+        F: FnMut(TranscriptionProgress),
+    {
+        let model = self.loaded_model()?;
+        self.transcribe_with_model(&model, audio_data, prefs, cancel, on_progress)
+    }
+
+    /// Like `transcribe_with_progress`, but decodes with `model` instead of
+    /// the engine's model.
+    pub fn transcribe_with_model<F>(
+        &self,
+        model: &LoadedModel,
+        audio_data: &[f32],
+        prefs: &Preferences,
+        cancel: &CancelToken,
+        mut on_progress: F,
+    ) -> Result<TranscriptionResult>
+    where
+        F: FnMut(TranscriptionProgress),
+    {
+        let mut result =
+            self.decode_typed_pass(model, audio_data, prefs, false, cancel, &mut on_progress)?;
+        if prefs.translation.mode == TranslationMode::BothPasses {
+            if let Some(other) = result.missing_pass() {
+                let second = self.decode_windows(
+                    model,
+                    audio_data,
+                    prefs,
+                    DecodeOptions {
+                        timestamps: false,
+                        translate: other == TextKind::Translation,
+                        ..DecodeOptions::default()
+                    },
+                    cancel,
+                    &mut on_progress,
+                )?;
+                result.set_pass_text(other, second.text);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Like `transcribe_with_progress`, but asks the backend for segment
+    /// timestamps, which dictation does not need and decodes slower. Only
+    /// the typed pass is decoded; callers splitting the audio by speaker
+    /// decode the `missing_pass` per turn with `transcribe_pass`.
+    pub fn transcribe_with_timestamps<F>(
+        &self,
+        audio_data: &[f32],
+        prefs: &Preferences,
+        mut on_progress: F,
+    ) -> Result<TranscriptionResult>
+    where
+        F: FnMut(TranscriptionProgress),
+    {
+        let model = self.loaded_model()?;
+        self.decode_typed_pass(
+            &model,
+            audio_data,
+            prefs,
+            true,
+            &CancelToken::new(),
+            &mut on_progress,
+        )
+    }
+
+    /// Decodes `audio_data` as `kind` text alone.
+    pub fn transcribe_pass(
+        &self,
+        audio_data: &[f32],
+        prefs: &Preferences,
+        kind: TextKind,
+    ) -> Result<String> {
+        let model = self.loaded_model()?;
+        let result = self.decode_windows(
+            &model,
+            audio_data,
+            prefs,
+            DecodeOptions {
+                timestamps: false,
+                translate: kind == TextKind::Translation,
+                ..DecodeOptions::default()
+            },
+            &CancelToken::new(),
+            |_| {},
+        )?;
+        Ok(result.text)
+    }
+
+    /// Decodes the text to type and, when translating with both texts kept,
+    /// records it as its pass.
+    fn decode_typed_pass<F>(
+        &self,
+        model: &LoadedModel,
+        audio_data: &[f32],
+        prefs: &Preferences,
+        timestamps: bool,
+        cancel: &CancelToken,
+        on_progress: F,
+    ) -> Result<TranscriptionResult>
+    where
+        F: FnMut(TranscriptionProgress),
+    {
+        let translating = prefs.translate_to_english && model.multilingual;
+        if !translating || prefs.translation.mode == TranslationMode::TranslationOnly {
+            return self.decode_windows(
+                model,
+                audio_data,
+                prefs,
+                DecodeOptions {
+                    timestamps,
+                    translate: prefs.translate_to_english,
+                    ..DecodeOptions::default()
+                },
+                cancel,
+                on_progress,
+            );
+        }
+
+        let typed = prefs.translation.typed;
+        let mut result = self.decode_windows(
+            model,
+            audio_data,
+            prefs,
+            DecodeOptions {
+                timestamps,
+                translate: typed == TextKind::Translation,
+                ..DecodeOptions::default()
+            },
+            cancel,
+            on_progress,
+        )?;
+        result.set_pass_text(typed, result.text.clone());
+
+        // Translating English to English would only decode the same text twice.
+        if result.language.as_deref() == Some("en") {
+            result.set_pass_text(typed.other(), result.text.clone());
+        }
+        Ok(result)
+    }
+
+    fn decode_windows<F>(
+        &self,
+        model: &LoadedModel,
+        audio_data: &[f32],
+        prefs: &Preferences,
+        options: DecodeOptions<'_>,
+        cancel: &CancelToken,
+        mut on_progress: F,
+    ) -> Result<TranscriptionResult>
+    where
+        F: FnMut(TranscriptionProgress),
+    {
+        let windows = plan_windows(audio_data);
+        let total_windows = windows.len();
+        let mut merged = TranscriptionResult {
+            text: String::new(),
+            segments: Vec::new(),
+            language: None,
+            language_probability: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            actions: Vec::new(),
+        };
+
+        for (index, window) in windows.into_iter().enumerate() {
+            if cancel.is_cancelled() {
+                anyhow::bail!("Transcription cancelled");
+            }
+
+            let window_audio = &audio_data[window.clone()];
+            let options = DecodeOptions {
+                prompt: (!merged.text.is_empty()).then(|| prompt_tail(&merged.text)),
+                ..options
+            };
+            let mut result =
+                self.backend
+                    .transcribe(&model.profile, window_audio, prefs, options, cancel)?;
+            filter_hallucinations(&mut result, window_audio, &prefs.hallucination_filter);
+            merge_window(&mut merged, result, window.start);
+
+            on_progress(TranscriptionProgress {
+                completed_windows: index + 1,
+                total_windows,
+            });
+        }
+
+        // Translated text is English whatever language was detected.
+        let language = if options.translate {
+            Some("en")
+        } else {
+            merged.language.as_deref()
+        };
+        let processed = post_process(&merged.text, prefs, language);
+        merged.text = processed.text();
+        merged.actions = processed.actions;
+        merged.unfiltered_text = processed.unfiltered;
+        Ok(merged)
+    }
+
+    /// Runs one short decode over silence so the first real transcription
+    /// does not pay for lazy backend allocations.
+    pub fn warm_up(&self) -> Result<()> {
+        let profile = self.get_current_profile().context("No model loaded")?;
+        let silence = vec![0.0; TARGET_SAMPLE_RATE as usize];
+        self.backend.transcribe(
+            &profile,
+            &silence,
+            &Preferences::default(),
+            DecodeOptions::default(),
+            &CancelToken::new(),
+        )?;
+        Ok(())
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.current_model.read().is_some()
+    }
+
+    pub fn is_multilingual(&self) -> bool {
+        self.current_model
+            .read()
+            .as_ref()
+            .is_some_and(|model| model.multilingual)
+    }
+
+    fn loaded_model(&self) -> Result<LoadedModel> {
+        self.current_model.read().clone().context("No model loaded")
+    }
+
+    pub fn get_current_profile(&self) -> Option<ModelProfile> {
+        self.current_model
+            .read()
+            .as_ref()
+            .map(|model| model.profile.clone())
+    }
+}
+
+impl Default for SttEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TranscriptionResult {
+    /// Actions to run for this result, typing `text` when no voice commands
+    /// were applied to it.
+    pub fn output_actions(&self) -> Vec<OutputAction> {
+        if self.actions.is_empty() && !self.text.is_empty() {
+            vec![OutputAction::Text(self.text.clone())]
+        } else {
+            self.actions.clone()
+        }
+    }
+
+    /// Drops blocked values from what this result types and masks the text
+    /// it stores. Run it once, just before the result is stored.
+    pub fn redact(&mut self, prefs: &RedactionPrefs) {
+        if !prefs.enabled {
+            return;
+        }
+        self.actions = self
+            .output_actions()
+            .into_iter()
+            .map(|action| match action {
+                // Kept even when empty, so `output_actions` doesn't fall
+                // back to the masked text.
+                OutputAction::Text(text) => OutputAction::Text(redact_for_typing(&text, prefs)),
+                other => other,
+            })
+            .collect();
+        self.text = redact_for_storage(&self.text, prefs);
+        for text in [
+            &mut self.original_text,
+            &mut self.translated_text,
+            &mut self.unfiltered_text,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *text = redact_for_storage(text, prefs);
+        }
+        for segment in &mut self.segments {
+            segment.text = redact_for_storage(&segment.text, prefs);
+        }
+    }
+
+    /// Runs `apply_code_mode` over the text this result types.
+    pub fn apply_code_mode(&mut self) {
+        if self.actions.is_empty() {
+            self.text = apply_code_mode(&self.text);
+            return;
+        }
+        for action in &mut self.actions {
+            if let OutputAction::Text(text) = action {
+                *text = apply_code_mode(text);
+            }
+        }
+        self.text = inserted_text(&self.actions);
+    }
+
+    /// The pass a decode that kept both texts has yet to run.
+    pub fn missing_pass(&self) -> Option<TextKind> {
+        match (&self.original_text, &self.translated_text) {
+            (Some(_), None) => Some(TextKind::Translation),
+            (None, Some(_)) => Some(TextKind::Original),
+            _ => None,
+        }
+    }
+
+    fn set_pass_text(&mut self, kind: TextKind, text: String) {
+        match kind {
+            TextKind::Original => self.original_text = Some(text),
+            TextKind::Translation => self.translated_text = Some(text),
+        }
+    }
+}
+
+pub struct WhisperBackend {
+    contexts: RwLock<Vec<(ModelProfile, Arc<WhisperContext>)>>,
+}
+
+impl WhisperBackend {
+    pub fn new() -> Self {
+        Self {
+            contexts: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for WhisperBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SttBackend for WhisperBackend {
+    fn load_model(&self, profile: &ModelProfile, spec: &ModelSpec) -> Result<()> {
         let mut contexts = self.contexts.write();
 
-        if contexts.iter().any(|(p, _)| *p == profile) {
+        if contexts.iter().any(|(p, _)| p == profile) {
             log::info!("Model for profile {:?} already loaded", profile);
-            *self.current_profile.write() = Some(profile);
             return Ok(());
         }
 
-        let model_info = get_model_info(&profile);
-        let model_path = models_dir.join(&model_info.filename);
-
+        let model_path = &spec.path;
         if !model_path.exists() {
             anyhow::bail!(
                 "Model file not found: {}. Expected SHA-256: {}",
                 model_path.display(),
-                model_info.sha256
+                spec.sha256
             );
         }
 
-        let computed_hash = compute_file_sha256(&model_path)?;
-        if computed_hash != model_info.sha256 {
+        let computed_hash = compute_file_sha256(model_path)?;
+        if computed_hash != spec.sha256 {
             anyhow::bail!(
                 "Model SHA-256 mismatch. Expected: {}, Got: {}",
-                model_info.sha256,
+                spec.sha256,
                 computed_hash
             );
         }
@@ -105,84 +588,676 @@ impl SttEngine {
         )
         .with_context(|| format!("Failed to load model from {}", model_path.display()))?;
 
-        contexts.push((profile.clone(), Arc::new(WhisperContext::new(ctx))));
-        *self.current_profile.write() = Some(profile.clone());
+        contexts.push((
+            profile.clone(),
+            Arc::new(WhisperContext::new(ctx, spec.multilingual)),
+        ));
 
         log::info!("Model {:?} loaded successfully", profile);
         Ok(())
     }
 
-    pub fn transcribe(
+    fn transcribe(
         &self,
+        profile: &ModelProfile,
         audio_data: &[f32],
-        prefs: &Preferences,
+        _prefs: &Preferences,
+        options: DecodeOptions<'_>,
+        cancel: &CancelToken,
     ) -> Result<TranscriptionResult> {
-        let current = self.current_profile.read();
-        let profile = current.as_ref().context("No model loaded")?;
-
-        let contexts = self.contexts.read();
-        let ctx = contexts
+        let ctx = self
+            .contexts
+            .read()
             .iter()
-            .find(|(p, _)| *p == *profile)
+            .find(|(p, _)| p == profile)
             .context("Model context not found")?
             .1
             .clone();
 
-        let mut params = build_inference_params(profile, prefs);
+        let mut params = build_inference_params(ctx.multilingual, options.translate);
+        params.set_no_timestamps(!options.timestamps);
+        if let Some(prompt) = options.prompt {
+            params.set_initial_prompt(prompt);
+        }
 
         let mut state = ctx
             .ctx
             .create_state()
             .context("Failed to create whisper state")?;
 
-        state
-            .full(params, audio_data)
-            .context("Whisper inference failed")?;
+        // Detect up front so the probability is available and whisper does not
+        // repeat the detection pass inside `full`.
+        let detected = if ctx.multilingual {
+            match detect_language(&mut state, audio_data) {
+                Ok(detected) => Some(detected),
+                Err(e) => {
+                    log::warn!("Language detection failed, falling back to auto: {:#}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Some((language, _)) = detected {
+            params.set_language(Some(language));
+        }
+
+        if cancel.is_cancelled() {
+            anyhow::bail!("Transcription cancelled");
+        }
+
+        // SAFETY: the callback only reads the token's shared state, which stays
+        // alive for the whole `full` call because `cancel` is borrowed across it.
+        unsafe {
+            params.set_abort_callback(Some(whisper_abort_requested));
+            params.set_abort_callback_user_data(cancel.as_user_data());
+        }
+
+        if let Err(e) = state.full(params, audio_data) {
+            if cancel.is_cancelled() {
+                anyhow::bail!("Transcription cancelled");
+            }
+            return Err(e).context("Whisper inference failed");
+        }
 
         let n_segments = state.full_n_segments();
         let mut segments = Vec::with_capacity(n_segments as usize);
 
         for i in 0..n_segments {
             let segment = state.get_segment(i).context("Failed to get segment")?;
-            let start = segment.start_timestamp() as i32;
-            let end = segment.end_timestamp() as i32;
+            // whisper reports timestamps in centiseconds.
+            let start = (segment.start_timestamp() * 10) as i32;
+            let end = (segment.end_timestamp() * 10) as i32;
 
             segments.push(TextSegment {
                 text: segment.to_string(),
                 start_ms: start,
                 end_ms: end,
+                no_speech_prob: segment.no_speech_probability(),
+                speaker: None,
             });
         }
 
-        let full_text: String = segments.iter().map(|s| s.text.clone()).collect();
-        let processed_text = post_process_text(&full_text, prefs);
+        let (language, language_probability) = match (ctx.multilingual, detected) {
+            (false, _) => (Some("en".to_string()), None),
+            (true, Some((language, probability))) => {
+                (Some(language.to_string()), Some(probability))
+            }
+            (true, None) => (
+                whisper_rs::get_lang_str(state.full_lang_id_from_state()).map(str::to_string),
+                None,
+            ),
+        };
+
+        let text: String = segments.iter().map(|s| s.text.clone()).collect();
 
         Ok(TranscriptionResult {
-            text: processed_text,
+            text,
             segments,
+            language,
+            language_probability,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            actions: Vec::new(),
         })
     }
+}
 
-    pub fn is_loaded(&self) -> bool {
-        !self.contexts.read().is_empty()
+/// Test backend that answers with canned results keyed by the exact input samples.
+#[cfg(test)]
+#[derive(Clone)]
+enum ScriptedResponse {
+    Text(TranscriptionResult),
+    Error(String),
+    UntilCancelled,
+}
+
+#[cfg(test)]
+#[derive(Default)]
+pub struct ScriptedBackend {
+    responses: Vec<(Vec<f32>, ScriptedResponse)>,
+    translations: Vec<(Vec<f32>, String)>,
+    fallback: Option<String>,
+    load_error: Option<String>,
+    calls: std::sync::atomic::AtomicUsize,
+    prompts: Mutex<Vec<Option<String>>>,
+    profiles: Mutex<Vec<ModelProfile>>,
+}
+
+#[cfg(test)]
+impl ScriptedBackend {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    pub fn get_current_profile(&self) -> Option<ModelProfile> {
-        self.current_profile.read().clone()
+    pub fn respond(self, audio_data: &[f32], text: &str) -> Self {
+        self.respond_with(audio_data, scripted_result(text))
+    }
+
+    pub fn respond_with(mut self, audio_data: &[f32], result: TranscriptionResult) -> Self {
+        self.responses
+            .push((audio_data.to_vec(), ScriptedResponse::Text(result)));
+        self
+    }
+
+    /// Answers translate passes over `audio_data` with `text`; other passes
+    /// fall through to the regular responses.
+    pub fn translate(mut self, audio_data: &[f32], text: &str) -> Self {
+        self.translations
+            .push((audio_data.to_vec(), text.to_string()));
+        self
+    }
+
+    pub fn fail(mut self, audio_data: &[f32], message: &str) -> Self {
+        self.responses.push((
+            audio_data.to_vec(),
+            ScriptedResponse::Error(message.to_string()),
+        ));
+        self
+    }
+
+    /// Simulates a long decode that only returns once the job is cancelled.
+    pub fn block_until_cancelled(mut self, audio_data: &[f32]) -> Self {
+        self.responses
+            .push((audio_data.to_vec(), ScriptedResponse::UntilCancelled));
+        self
+    }
+
+    pub fn otherwise(mut self, text: &str) -> Self {
+        self.fallback = Some(text.to_string());
+        self
+    }
+
+    pub fn fail_load(mut self, message: &str) -> Self {
+        self.load_error = Some(message.to_string());
+        self
+    }
+
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// Prompts received so far, in call order.
+    pub fn prompts(&self) -> Vec<Option<String>> {
+        self.prompts.lock().clone()
+    }
+
+    /// Models decoded with so far, in call order.
+    pub fn profiles(&self) -> Vec<ModelProfile> {
+        self.profiles.lock().clone()
     }
 }
 
-fn build_inference_params(
-    profile: &ModelProfile,
-    prefs: &Preferences,
-) -> FullParams<'static, 'static> {
-    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+#[cfg(test)]
+impl SttBackend for ScriptedBackend {
+    fn load_model(&self, _profile: &ModelProfile, _spec: &ModelSpec) -> Result<()> {
+        match &self.load_error {
+            Some(message) => anyhow::bail!("{}", message),
+            None => Ok(()),
+        }
+    }
+
+    fn transcribe(
+        &self,
+        profile: &ModelProfile,
+        audio_data: &[f32],
+        _prefs: &Preferences,
+        options: DecodeOptions<'_>,
+        cancel: &CancelToken,
+    ) -> Result<TranscriptionResult> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        self.prompts.lock().push(options.prompt.map(str::to_string));
+        self.profiles.lock().push(profile.clone());
+
+        if options.translate {
+            let translation = self
+                .translations
+                .iter()
+                .find(|(input, _)| input.as_slice() == audio_data);
+            if let Some((_, text)) = translation {
+                return Ok(scripted_result(text));
+            }
+        }
+
+        let scripted = self
+            .responses
+            .iter()
+            .find(|(input, _)| input.as_slice() == audio_data)
+            .map(|(_, response)| response.clone());
+
+        match (scripted, &self.fallback) {
+            (Some(ScriptedResponse::Text(result)), _) => Ok(result),
+            (Some(ScriptedResponse::Error(message)), _) => anyhow::bail!(message),
+            (Some(ScriptedResponse::UntilCancelled), _) => {
+                while !cancel.is_cancelled() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                anyhow::bail!("Transcription cancelled")
+            }
+            (None, Some(text)) => Ok(scripted_result(text)),
+            (None, None) => anyhow::bail!("No scripted response for {} samples", audio_data.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+fn scripted_result(text: &str) -> TranscriptionResult {
+    TranscriptionResult {
+        text: text.to_string(),
+        segments: vec![TextSegment {
+            text: text.to_string(),
+            start_ms: 0,
+            end_ms: 0,
+            no_speech_prob: 0.0,
+            speaker: None,
+        }],
+        language: None,
+        language_probability: None,
+        original_text: None,
+        translated_text: None,
+        unfiltered_text: None,
+        actions: Vec::new(),
+    }
+}
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    deadline: OnceLock<Instant>,
+}
+
+impl CancelState {
+    fn is_timed_out(&self) -> bool {
+        self.deadline
+            .get()
+            .is_some_and(|deadline| Instant::now() >= *deadline)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || self.is_timed_out()
+    }
+}
+
+/// Shared cancellation flag for one transcription, optionally with a deadline.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    state: Arc<CancelState>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Starts the timeout clock. Only the first call has an effect.
+    pub fn arm_timeout(&self, timeout: Duration) {
+        let _ = self.state.deadline.set(Instant::now() + timeout);
+    }
+
+    pub fn is_timed_out(&self) -> bool {
+        self.state.is_timed_out()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.is_cancelled()
+    }
+
+    fn as_user_data(&self) -> *mut c_void {
+        Arc::as_ptr(&self.state) as *mut c_void
+    }
+}
+
+unsafe extern "C" fn whisper_abort_requested(user_data: *mut c_void) -> bool {
+    let state = &*(user_data as *const CancelState);
+    state.is_cancelled()
+}
+
+pub type JobId = u64;
 
-    let n_threads = thread::available_parallelism()
-        .map(|n| n.get() as i32)
-        .unwrap_or(4);
+#[derive(Debug)]
+pub enum InferenceOutcome {
+    Completed(TranscriptionResult),
+    Cancelled,
+    TimedOut,
+    Failed(String),
+}
+
+type JobCallback = Box<dyn FnOnce(JobId, InferenceOutcome) + Send>;
+type LoadCallback = Box<dyn FnOnce(Result<(), String>) + Send>;
+
+enum WorkerJob {
+    Transcribe(Box<InferenceJob>),
+    LoadModel(LoadJob),
+}
+
+struct LoadJob {
+    profile: ModelProfile,
+    models_dir: PathBuf,
+    on_complete: LoadCallback,
+}
+
+struct InferenceJob {
+    id: JobId,
+    audio_data: Vec<f32>,
+    prefs: Preferences,
+    timeout: Duration,
+    cancel: CancelToken,
+    on_complete: JobCallback,
+}
+
+#[derive(Debug, Clone)]
+pub struct JobHandle {
+    id: JobId,
+    /// The dictation session the job transcribes; `None` for work such as
+    /// background passes that no activation supersedes.
+    session_id: Option<String>,
+    cancel: CancelToken,
+}
+
+impl JobHandle {
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+}
+
+/// Runs transcriptions one at a time on a dedicated thread so callers (such as
+/// the hotkey listener) never block on inference.
+pub struct InferenceWorker {
+    sender: mpsc::Sender<WorkerJob>,
+    jobs: Arc<Mutex<Vec<JobHandle>>>,
+    next_id: AtomicU64,
+}
+
+impl InferenceWorker {
+    pub fn spawn(engine: Arc<SttEngine>) -> Self {
+        let (sender, receiver) = mpsc::channel::<WorkerJob>();
+        let jobs: Arc<Mutex<Vec<JobHandle>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let worker_jobs = Arc::clone(&jobs);
+        thread::Builder::new()
+            .name("stt-inference".to_string())
+            .spawn(move || {
+                // Where the last model load found its model, so jobs can use
+                // the model their preferences ask for.
+                let mut models_dir = None;
+                for job in receiver {
+                    match job {
+                        WorkerJob::Transcribe(job) => {
+                            let model = job_model(&engine, &job, models_dir.as_deref());
+                            let outcome = run_job(&engine, &job, model.as_ref());
+                            worker_jobs.lock().retain(|handle| handle.id != job.id);
+                            (job.on_complete)(job.id, outcome);
+                        }
+                        WorkerJob::LoadModel(job) => {
+                            let dir = job.models_dir.clone();
+                            let result = load_and_warm_up(&engine, job.profile, job.models_dir);
+                            if result.is_ok() {
+                                models_dir = Some(dir);
+                            }
+                            (job.on_complete)(result);
+                        }
+                    }
+                }
+            })
+            .expect("Failed to spawn inference worker thread");
+
+        Self {
+            sender,
+            jobs,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Queues a transcription. The timeout is measured from when the job starts
+    /// running, and `on_complete` is called on the worker thread.
+    pub fn submit<F>(
+        &self,
+        audio_data: Vec<f32>,
+        prefs: Preferences,
+        timeout: Duration,
+        on_complete: F,
+    ) -> JobHandle
+    where
+        F: FnOnce(JobId, InferenceOutcome) + Send + 'static,
+    {
+        self.enqueue(None, audio_data, prefs, timeout, Box::new(on_complete))
+    }
+
+    /// Queues the transcription of dictation session `session_id`, which
+    /// `cancel_session` can cancel once a new activation supersedes it.
+    pub fn submit_for_session<F>(
+        &self,
+        session_id: &str,
+        audio_data: Vec<f32>,
+        prefs: Preferences,
+        timeout: Duration,
+        on_complete: F,
+    ) -> JobHandle
+    where
+        F: FnOnce(JobId, InferenceOutcome) + Send + 'static,
+    {
+        let session_id = Some(session_id.to_string());
+        self.enqueue(
+            session_id,
+            audio_data,
+            prefs,
+            timeout,
+            Box::new(on_complete),
+        )
+    }
 
-    params.set_n_threads(n_threads);
+    fn enqueue(
+        &self,
+        session_id: Option<String>,
+        audio_data: Vec<f32>,
+        prefs: Preferences,
+        timeout: Duration,
+        on_complete: JobCallback,
+    ) -> JobHandle {
+        let handle = JobHandle {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            session_id,
+            cancel: CancelToken::new(),
+        };
+
+        let job = InferenceJob {
+            id: handle.id,
+            audio_data,
+            prefs,
+            timeout,
+            cancel: handle.cancel.clone(),
+            on_complete,
+        };
+
+        self.jobs.lock().push(handle.clone());
+        if let Err(mpsc::SendError(WorkerJob::Transcribe(job))) =
+            self.sender.send(WorkerJob::Transcribe(Box::new(job)))
+        {
+            self.jobs.lock().retain(|h| h.id != job.id);
+            (job.on_complete)(
+                job.id,
+                InferenceOutcome::Failed("Inference worker stopped".to_string()),
+            );
+        }
+
+        handle
+    }
+
+    /// Queues a model load followed by a warm-up decode. Transcriptions
+    /// submitted afterwards wait for it instead of failing with no model
+    /// loaded. Loads are never cancelled by `cancel_session`.
+    pub fn load_model<F>(&self, profile: ModelProfile, models_dir: PathBuf, on_complete: F)
+    where
+        F: FnOnce(Result<(), String>) + Send + 'static,
+    {
+        let job = LoadJob {
+            profile,
+            models_dir,
+            on_complete: Box::new(on_complete),
+        };
+
+        if let Err(mpsc::SendError(WorkerJob::LoadModel(job))) =
+            self.sender.send(WorkerJob::LoadModel(job))
+        {
+            (job.on_complete)(Err("Inference worker stopped".to_string()));
+        }
+    }
+
+    /// Cancels queued or running jobs of dictation session `session_id` and
+    /// returns how many were cancelled. Other sessions' jobs and jobs
+    /// submitted without a session are left alone.
+    pub fn cancel_session(&self, session_id: &str) -> usize {
+        let jobs = self.jobs.lock();
+        let mut cancelled = 0;
+        for handle in jobs.iter() {
+            if handle.session_id.as_deref() == Some(session_id) && !handle.cancel.is_cancelled() {
+                handle.cancel();
+                cancelled += 1;
+            }
+        }
+        cancelled
+    }
+}
+
+fn load_and_warm_up(
+    engine: &SttEngine,
+    profile: ModelProfile,
+    models_dir: PathBuf,
+) -> Result<(), String> {
+    let started = Instant::now();
+    engine
+        .load_model(profile.clone(), models_dir)
+        .map_err(|e| format!("{:#}", e))?;
+
+    // A failed warm-up only costs the first dictation some latency.
+    if let Err(e) = engine.warm_up() {
+        log::warn!("Model {:?} warm-up failed: {:#}", profile, e);
+    }
+    log::info!("Model {:?} ready in {:?}", profile, started.elapsed());
+    Ok(())
+}
+
+/// The model `job`'s preferences ask for when it isn't the engine's, as
+/// when dictating into an app whose profile picks its own model. It is used
+/// for this job only; `None` runs the job with the engine's model, which is
+/// also the fallback when the other model can't be loaded.
+fn job_model(
+    engine: &SttEngine,
+    job: &InferenceJob,
+    models_dir: Option<&Path>,
+) -> Option<LoadedModel> {
+    let wanted = &job.prefs.model_profile;
+    let models_dir = models_dir?;
+    if job.cancel.is_cancelled() || engine.get_current_profile().as_ref() == Some(wanted) {
+        return None;
+    }
+    match engine.prepare_model(wanted, models_dir) {
+        Ok(model) => Some(model),
+        Err(e) => {
+            log::warn!(
+                "Model {:?} for inference job {} failed to load, using the loaded model: {:#}",
+                wanted,
+                job.id,
+                e
+            );
+            None
+        }
+    }
+}
+
+fn run_job(
+    engine: &SttEngine,
+    job: &InferenceJob,
+    model: Option<&LoadedModel>,
+) -> InferenceOutcome {
+    if job.cancel.is_cancelled() {
+        log::info!("Skipping cancelled inference job {}", job.id);
+        return InferenceOutcome::Cancelled;
+    }
+
+    job.cancel.arm_timeout(job.timeout);
+    let started = Instant::now();
+    let on_progress = |progress: TranscriptionProgress| {
+        if progress.total_windows > 1 {
+            log::info!(
+                "Inference job {}: window {}/{} done",
+                job.id,
+                progress.completed_windows,
+                progress.total_windows
+            );
+        }
+    };
+    let result = match model {
+        Some(model) => engine.transcribe_with_model(
+            model,
+            &job.audio_data,
+            &job.prefs,
+            &job.cancel,
+            on_progress,
+        ),
+        None => {
+            engine.transcribe_with_progress(&job.audio_data, &job.prefs, &job.cancel, on_progress)
+        }
+    };
+
+    if job.cancel.is_timed_out() {
+        log::warn!(
+            "Inference job {} timed out after {:?}",
+            job.id,
+            started.elapsed()
+        );
+        return InferenceOutcome::TimedOut;
+    }
+    if job.cancel.is_cancelled() {
+        log::info!("Inference job {} cancelled", job.id);
+        return InferenceOutcome::Cancelled;
+    }
+
+    match result {
+        Ok(result) => {
+            log::info!(
+                "Inference job {} finished in {:?}",
+                job.id,
+                started.elapsed()
+            );
+            InferenceOutcome::Completed(result)
+        }
+        Err(e) => InferenceOutcome::Failed(format!("{:#}", e)),
+    }
+}
+
+fn inference_threads() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+fn detect_language(state: &mut WhisperState, audio_data: &[f32]) -> Result<(&'static str, f32)> {
+    let n_threads = inference_threads();
+    state
+        .pcm_to_mel(audio_data, n_threads)
+        .context("Failed to compute mel spectrogram")?;
+    let (lang_id, probabilities) = state
+        .lang_detect(0, n_threads)
+        .context("Language detection failed")?;
+    let language = whisper_rs::get_lang_str(lang_id).context("Unknown language id")?;
+    let probability = probabilities
+        .get(lang_id as usize)
+        .copied()
+        .unwrap_or_default();
+    Ok((language, probability))
+}
+
+fn build_inference_params(multilingual: bool, translate: bool) -> FullParams<'static, 'static> {
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+
+    params.set_n_threads(inference_threads() as i32);
     params.set_no_timestamps(true);
     params.set_suppress_nst(true);
     params.set_print_special(false);
@@ -190,30 +1265,85 @@ fn build_inference_params(
     params.set_print_realtime(false);
     params.set_print_timestamps(false);
 
-    match profile {
-        ModelProfile::EnglishSmall => {
-            params.set_language(Some("en"));
-            params.set_translate(false);
-        }
-        ModelProfile::MultilingualSmall | ModelProfile::MultilingualMedium => {
-            params.set_language(Some("auto"));
-            params.set_translate(prefs.translate_to_english);
-        }
+    if multilingual {
+        params.set_language(Some("auto"));
+        params.set_translate(translate);
+    } else {
+        params.set_language(Some("en"));
+        params.set_translate(false);
     }
 
     params
 }
 
-pub fn post_process_text(text: &str, prefs: &Preferences) -> String {
+/// What post-processing made of one transcript.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProcessedText {
+    pub actions: Vec<OutputAction>,
+    /// The whitespace-normalized transcript, kept when the disfluency filter
+    /// changed it.
+    pub unfiltered: Option<String>,
+}
+
+impl ProcessedText {
+    pub fn text(&self) -> String {
+        inserted_text(&self.actions)
+    }
+}
+
+/// `language` is the language of `text`; English-only models report none.
+pub fn post_process(text: &str, prefs: &Preferences, language: Option<&str>) -> ProcessedText {
+    let language = language.unwrap_or("en");
     let normalized = normalize_whitespace(text);
+    let filtered = remove_disfluencies(&normalized, &prefs.disfluency_filter, language);
 
-    if prefs.voice_commands.enabled {
-        apply_voice_commands(&normalized, &prefs.voice_commands.map)
+    let actions = if prefs.voice_commands.enabled {
+        voice_command_actions(&filtered, &prefs.voice_commands.commands)
+    } else if filtered.is_empty() {
+        Vec::new()
     } else {
-        normalized
+        vec![OutputAction::Text(filtered.clone())]
+    };
+    let actions = apply_itn(actions, &prefs.itn, language);
+    ProcessedText {
+        actions: apply_snippets(actions, &prefs.snippets),
+        unfiltered: (filtered != normalized).then_some(normalized),
     }
 }
 
+pub fn post_process_text(text: &str, prefs: &Preferences, language: Option<&str>) -> String {
+    post_process(text, prefs, language).text()
+}
+
+pub fn post_process_actions(
+    text: &str,
+    prefs: &Preferences,
+    language: Option<&str>,
+) -> Vec<OutputAction> {
+    post_process(text, prefs, language).actions
+}
+
+/// Writes spoken numbers, dates and units in the typed text as digits and
+/// symbols, leaving key presses alone.
+fn apply_itn(actions: Vec<OutputAction>, prefs: &ItnPrefs, language: &str) -> Vec<OutputAction> {
+    let base = itn::base_language(language);
+    let enabled = prefs.enabled
+        && prefs
+            .languages
+            .iter()
+            .any(|configured| itn::base_language(configured) == base);
+    if !enabled {
+        return actions;
+    }
+    actions
+        .into_iter()
+        .map(|action| match action {
+            OutputAction::Text(text) => OutputAction::Text(itn::normalize(&text, language)),
+            other => other,
+        })
+        .collect()
+}
+
 pub fn normalize_whitespace(text: &str) -> String {
     let mut result = String::with_capacity(text.len());
     let mut last_was_whitespace = false;
@@ -230,63 +1360,619 @@ pub fn normalize_whitespace(text: &str) -> String {
         }
     }
 
-    result.trim().to_string()
+    result.trim().to_string()
+}
+
+/// Expands snippet triggers in the typed text.
+fn apply_snippets(actions: Vec<OutputAction>, snippets: &Snippets) -> Vec<OutputAction> {
+    if !snippets.enabled || snippets.entries.is_empty() {
+        return actions;
+    }
+    let variables = SnippetVariables::capture(&snippets.entries);
+    actions
+        .into_iter()
+        .map(|action| match action {
+            OutputAction::Text(text) => {
+                OutputAction::Text(expand_snippets(&text, &snippets.entries, &variables))
+            }
+            other => other,
+        })
+        .collect()
+}
+
+/// Words whose doubling is usually meant, as in "I know that that works".
+const REPEATABLE_WORDS: &[&str] = &["that", "had", "very", "really", "no", "bye", "ha"];
+
+/// Drops filler words and stutters from whitespace-normalized `text`, plus
+/// the words a self-correction takes back when those are enabled. Fillers
+/// of more than one word ("you know") are ordinary speech too, so they only
+/// go when punctuation or the ends of the text set them apart.
+pub fn remove_disfluencies(text: &str, filter: &DisfluencyFilter, language: &str) -> String {
+    if !filter.enabled || text.is_empty() {
+        return text.to_string();
+    }
+
+    let mut words: Vec<String> = text.split(' ').map(str::to_string).collect();
+    remove_fillers(&mut words, &language_phrases(&filter.fillers, language));
+    if filter.self_corrections {
+        remove_self_corrections(
+            &mut words,
+            &language_phrases(&filter.correction_cues, language),
+        );
+    }
+    remove_repetitions(&mut words);
+    words.join(" ")
+}
+
+/// The phrases listed for `language`, split into words, longest first.
+fn language_phrases(lists: &BTreeMap<String, Vec<String>>, language: &str) -> Vec<Vec<String>> {
+    let base = itn::base_language(language);
+    let mut phrases: Vec<Vec<String>> = lists
+        .iter()
+        .filter(|(listed, _)| itn::base_language(listed) == base)
+        .flat_map(|(_, phrases)| phrases.iter().map(|phrase| phrase_words(phrase)))
+        .filter(|words| !words.is_empty())
+        .collect();
+    phrases.sort_by_key(|words| std::cmp::Reverse(words.len()));
+    phrases
+}
+
+fn phrase_at(words: &[String], index: usize, phrases: &[Vec<String>]) -> Option<usize> {
+    phrases
+        .iter()
+        .find(|phrase| {
+            words
+                .get(index..index + phrase.len())
+                .is_some_and(|window| {
+                    window
+                        .iter()
+                        .zip(phrase.iter())
+                        .all(|(word, expected)| word_core(word) == *expected)
+                })
+        })
+        .map(|phrase| phrase.len())
+}
+
+fn trailing_punctuation(word: &str) -> &str {
+    &word[word.trim_end_matches(|c: char| !c.is_alphanumeric()).len()..]
+}
+
+fn ends_sentence(word: &str) -> bool {
+    trailing_punctuation(word).contains(['.', '!', '?'])
+}
+
+/// Whether punctuation or the edge of the text sets the `len` words at
+/// `index` apart from the words around them.
+fn set_apart(words: &[String], index: usize, len: usize) -> bool {
+    let before = index == 0 || !trailing_punctuation(&words[index - 1]).is_empty();
+    let after =
+        index + len == words.len() || !trailing_punctuation(&words[index + len - 1]).is_empty();
+    before && after
+}
+
+fn remove_fillers(words: &mut Vec<String>, fillers: &[Vec<String>]) {
+    let mut index = 0;
+    while index < words.len() {
+        match phrase_at(words, index, fillers)
+            .filter(|&len| len == 1 || set_apart(words, index, len))
+        {
+            Some(len) => remove_words(words, index, index + len),
+            None => index += 1,
+        }
+    }
+}
+
+/// "meet at three, no wait, four" keeps "meet at four". When the correction
+/// starts with a word said earlier in the sentence ("the blue car, I mean,
+/// the red car") everything from that word is replaced; otherwise only the
+/// word right before the cue is. Cues run into the sentence, as in "what I
+/// mean is", are ordinary speech and stay.
+fn remove_self_corrections(words: &mut Vec<String>, cues: &[Vec<String>]) {
+    let mut index = 1;
+    while index < words.len() {
+        // A cue needs something to correct before it and a correction after.
+        let Some(len) = phrase_at(words, index, cues).filter(|&len| {
+            index + len < words.len()
+                && !ends_sentence(&words[index - 1])
+                && set_apart(words, index, len)
+        }) else {
+            index += 1;
+            continue;
+        };
+        let repair = word_core(&words[index + len]);
+        let mut start = index - 1;
+        let mut candidate = index - 1;
+        loop {
+            if word_core(&words[candidate]) == repair {
+                start = candidate;
+                break;
+            }
+            if candidate == 0 || ends_sentence(&words[candidate - 1]) {
+                break;
+            }
+            candidate -= 1;
+        }
+        remove_words(words, start, index + len);
+        index = start.max(1);
+    }
+}
+
+/// Collapses "the the" and "I think I think" into one copy.
+fn remove_repetitions(words: &mut Vec<String>) {
+    let mut index = 0;
+    while index < words.len() {
+        let repeated = (1..=3).rev().find(|&len| {
+            let Some(second) = words.get(index + len..index + 2 * len) else {
+                return false;
+            };
+            let first = &words[index..index + len];
+            let cores: Vec<String> = first.iter().map(|word| word_core(word)).collect();
+            let intended = cores.iter().all(|core| itn::is_number_word(core))
+                || (len == 1 && REPEATABLE_WORDS.contains(&cores[0].as_str()));
+            !intended
+                && cores.iter().all(|core| !core.is_empty())
+                && !first.iter().any(|word| ends_sentence(word))
+                && second
+                    .iter()
+                    .zip(&cores)
+                    .all(|(word, core)| word_core(word) == *core)
+        });
+        match repeated {
+            Some(len) => remove_words(words, index, index + len),
+            None => index += 1,
+        }
+    }
+}
+
+/// Removes `words[start..end]`, carrying a sentence start's capital letter
+/// and a sentence end's punctuation over to the neighbouring words.
+fn remove_words(words: &mut Vec<String>, start: usize, end: usize) {
+    let removed: Vec<String> = words.drain(start..end).collect();
+    let first = &removed[0];
+    let last = &removed[removed.len() - 1];
+
+    let sentence_start = start == 0 || ends_sentence(&words[start - 1]);
+    if sentence_start && first.starts_with(char::is_uppercase) {
+        if let Some(next) = words.get_mut(start) {
+            let mut chars = next.chars();
+            if let Some(initial) = chars.next() {
+                *next = initial.to_uppercase().chain(chars).collect();
+            }
+        }
+    }
+
+    if ends_sentence(last) && start > 0 && !ends_sentence(&words[start - 1]) {
+        let punctuation = trailing_punctuation(last).trim_start_matches([',', ';', ':']);
+        let previous = &mut words[start - 1];
+        let kept = previous.trim_end_matches([',', ';', ':']).len();
+        previous.truncate(kept);
+        previous.push_str(punctuation);
+    }
+}
+
+/// The text `voice_command_actions` would insert for `text`.
+pub fn apply_voice_commands(text: &str, commands: &[VoiceCommand]) -> String {
+    inserted_text(&voice_command_actions(text, commands))
+}
+
+/// Turns spoken commands into the text and key presses they stand for.
+/// Commands match whole words case-insensitively, longer phrases win over
+/// their prefixes, and punctuation whisper attached to a command word is
+/// dropped.
+pub fn voice_command_actions(text: &str, commands: &[VoiceCommand]) -> Vec<OutputAction> {
+    let table = command_table(commands);
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let cores: Vec<String> = tokens.iter().map(|token| word_core(token)).collect();
+
+    let mut output = CommandOutput::default();
+    let mut index = 0;
+    while index < tokens.len() {
+        let matched = table.iter().find(|(words, _)| {
+            cores
+                .get(index..index + words.len())
+                .is_some_and(|window| window == words.as_slice())
+        });
+        match matched {
+            Some((words, symbol)) => {
+                output.push_symbol(symbol);
+                index += words.len();
+            }
+            None => {
+                output.push_word(tokens[index]);
+                index += 1;
+            }
+        }
+    }
+    output.finish()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CommandSymbol {
+    Break(&'static str),
+    Literal(String),
+    Punctuation(char),
+    OpenQuote,
+    CloseQuote,
+    Backtick,
+    CodeBlock,
+    Keys(Vec<KeyChord>),
+    ScratchThat,
+}
+
+/// Command phrases split into lowercase words, longest first.
+fn command_table(commands: &[VoiceCommand]) -> Vec<(Vec<String>, CommandSymbol)> {
+    let mut table: Vec<(Vec<String>, CommandSymbol)> = commands
+        .iter()
+        .filter_map(|command| Some((command, command_symbol(&command.action)?)))
+        .flat_map(|(command, symbol)| {
+            command
+                .phrases
+                .iter()
+                .map(move |phrase| (phrase_words(phrase), symbol.clone()))
+        })
+        .filter(|(words, _)| !words.is_empty())
+        .collect();
+    table.sort_by_key(|(words, _)| std::cmp::Reverse(words.len()));
+    table
+}
+
+fn command_symbol(action: &CommandAction) -> Option<CommandSymbol> {
+    let symbol = match action {
+        CommandAction::Text(text) => CommandSymbol::Literal(text.clone()),
+        CommandAction::Keys(keys) => CommandSymbol::Keys(parse_key_sequence(keys).ok()?),
+        CommandAction::Builtin(builtin) => match builtin {
+            BuiltinCommand::Newline => CommandSymbol::Break("\n"),
+            BuiltinCommand::NewParagraph => CommandSymbol::Break("\n\n"),
+            BuiltinCommand::Tab => CommandSymbol::Break("\t"),
+            BuiltinCommand::Period => CommandSymbol::Punctuation('.'),
+            BuiltinCommand::Comma => CommandSymbol::Punctuation(','),
+            BuiltinCommand::Colon => CommandSymbol::Punctuation(':'),
+            BuiltinCommand::Semicolon => CommandSymbol::Punctuation(';'),
+            BuiltinCommand::OpenQuote => CommandSymbol::OpenQuote,
+            BuiltinCommand::CloseQuote => CommandSymbol::CloseQuote,
+            BuiltinCommand::Backtick => CommandSymbol::Backtick,
+            BuiltinCommand::CodeBlock => CommandSymbol::CodeBlock,
+            BuiltinCommand::ScratchThat => CommandSymbol::ScratchThat,
+        },
+    };
+    Some(symbol)
+}
+
+/// Joins words and command symbols, putting spaces only where they belong.
+/// `text` is the run typed since the last key press.
+#[derive(Default)]
+struct CommandOutput {
+    actions: Vec<OutputAction>,
+    text: String,
+    glue_next: bool,
+    newline_next: bool,
+    backtick_open: bool,
+    code_block_open: bool,
+}
+
+impl CommandOutput {
+    fn push_word(&mut self, word: &str) {
+        self.separate();
+        self.text.push_str(word);
+    }
+
+    fn push_symbol(&mut self, symbol: &CommandSymbol) {
+        match symbol {
+            CommandSymbol::Break(text) => {
+                self.trim_spaces();
+                self.text.push_str(text);
+                self.glue_next = true;
+                self.newline_next = false;
+            }
+            CommandSymbol::Literal(text) => self.push_word(text),
+            CommandSymbol::Keys(chords) => {
+                self.flush();
+                self.actions.push(OutputAction::Keys(chords.clone()));
+                self.glue_next = true;
+                self.newline_next = false;
+            }
+            CommandSymbol::ScratchThat => {
+                // Text from this dictation is dropped before it is typed;
+                // otherwise the previous dictation gets removed.
+                if self.text.is_empty() {
+                    self.actions.push(OutputAction::RemoveLastDictation);
+                } else {
+                    self.text.clear();
+                }
+                self.glue_next = true;
+                self.newline_next = false;
+            }
+            CommandSymbol::Punctuation(mark) => {
+                // A spoken "comma" replaces whatever punctuation whisper guessed.
+                self.trim_spaces();
+                while self.text.ends_with(['.', ',', ';', ':', '!', '?']) {
+                    self.text.pop();
+                }
+                self.text.push(*mark);
+                self.glue_next = false;
+            }
+            CommandSymbol::OpenQuote => self.open('"'),
+            CommandSymbol::CloseQuote => self.close('"'),
+            CommandSymbol::Backtick => {
+                if self.backtick_open {
+                    self.close('`');
+                } else {
+                    self.open('`');
+                }
+                self.backtick_open = !self.backtick_open;
+            }
+            CommandSymbol::CodeBlock => {
+                self.trim_spaces();
+                if !self.text.is_empty() && !self.text.ends_with('\n') {
+                    self.text.push('\n');
+                }
+                self.text.push_str("```");
+                if self.code_block_open {
+                    self.newline_next = true;
+                } else {
+                    self.text.push('\n');
+                    self.glue_next = true;
+                }
+                self.code_block_open = !self.code_block_open;
+            }
+        }
+    }
+
+    fn open(&mut self, mark: char) {
+        self.separate();
+        self.text.push(mark);
+        self.glue_next = true;
+    }
+
+    /// Puts a space (or the newline owed after a code fence) before the next
+    /// piece of text unless the previous symbol glues to it.
+    fn separate(&mut self) {
+        if self.newline_next {
+            self.text.push('\n');
+        } else if !self.glue_next
+            && !self.text.is_empty()
+            && !self.text.ends_with(char::is_whitespace)
+        {
+            self.text.push(' ');
+        }
+        self.glue_next = false;
+        self.newline_next = false;
+    }
+
+    fn close(&mut self, mark: char) {
+        self.trim_spaces();
+        self.text.push(mark);
+        self.glue_next = false;
+    }
+
+    fn trim_spaces(&mut self) {
+        let trimmed = self.text.trim_end_matches(' ').len();
+        self.text.truncate(trimmed);
+    }
+
+    fn flush(&mut self) {
+        self.trim_spaces();
+        if !self.text.is_empty() {
+            self.actions
+                .push(OutputAction::Text(std::mem::take(&mut self.text)));
+        }
+    }
+
+    fn finish(mut self) -> Vec<OutputAction> {
+        self.flush();
+        self.actions
+    }
+}
+
+/// Whether dictation into `app_name` should go through `apply_code_mode`.
+pub fn code_mode_enabled(prefs: &CodeModePrefs, app_name: Option<&str>, text: &str) -> bool {
+    let listed = app_name.is_some_and(|app| {
+        prefs
+            .apps
+            .iter()
+            .any(|a| a.trim().eq_ignore_ascii_case(app))
+    });
+    listed
+        || (prefs.detect_code_blocks
+            && Typer::detect_context_for_options(&TypeOptions::default(), text)
+                == ContextHeuristic::CodeBlock)
 }
 
-pub fn apply_voice_commands(text: &str, map: &crate::prefs::VoiceCommandMap) -> String {
-    let lowercase = text.to_lowercase();
-    let mut result = text.to_string();
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Casing {
+    Camel,
+    Pascal,
+    Snake,
+    Kebab,
+    ScreamingSnake,
+}
 
-    if lowercase.contains(&map.newline) || lowercase.contains("enter") {
-        result = result.replace("enter", "\n");
-    }
-    if lowercase.contains(&map.new_paragraph) {
-        result = result.replace(&map.new_paragraph, "\n\n");
-    }
-    if lowercase.contains(&map.tab) || lowercase.contains("tab") {
-        result = result.replace("tab", "\t");
-    }
+const CASING_COMMANDS: &[(&str, Casing)] = &[
+    ("camel case", Casing::Camel),
+    ("pascal case", Casing::Pascal),
+    ("snake case", Casing::Snake),
+    ("kebab case", Casing::Kebab),
+    ("screaming snake case", Casing::ScreamingSnake),
+    ("screaming snake", Casing::ScreamingSnake),
+];
+
+/// How a piece of code output spaces itself from its neighbours.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CodeSpacing {
+    Word,
+    Operator,
+    Open,
+    Close,
+    Tight,
+}
 
-    if lowercase.contains(&map.period) {
-        result = result.replace(&map.period, ".");
-    }
-    if lowercase.contains(&map.comma) {
-        result = result.replace(&map.comma, ",");
-    }
-    if lowercase.contains(&map.colon) {
-        result = result.replace(&map.colon, ":");
-    }
-    if lowercase.contains(&map.semicolon) {
-        result = result.replace(&map.semicolon, ";");
-    }
+const CODE_SYMBOLS: &[(&str, &str, CodeSpacing)] = &[
+    ("open paren", "(", CodeSpacing::Open),
+    ("close paren", ")", CodeSpacing::Close),
+    ("open bracket", "[", CodeSpacing::Open),
+    ("close bracket", "]", CodeSpacing::Close),
+    ("open brace", "{", CodeSpacing::Open),
+    ("close brace", "}", CodeSpacing::Close),
+    ("arrow", "->", CodeSpacing::Operator),
+    ("fat arrow", "=>", CodeSpacing::Operator),
+    ("equals", "=", CodeSpacing::Operator),
+    ("double equals", "==", CodeSpacing::Operator),
+    ("triple equals", "===", CodeSpacing::Operator),
+    ("not equals", "!=", CodeSpacing::Operator),
+    ("ampersand", "&", CodeSpacing::Operator),
+    ("double ampersand", "&&", CodeSpacing::Operator),
+    ("pipe", "|", CodeSpacing::Operator),
+    ("double pipe", "||", CodeSpacing::Operator),
+    ("double colon", "::", CodeSpacing::Tight),
+    ("dot", ".", CodeSpacing::Tight),
+    ("underscore", "_", CodeSpacing::Tight),
+];
+
+enum CodeCommand {
+    Casing(Casing),
+    Symbol(&'static str, CodeSpacing),
+}
 
-    if lowercase.contains(&map.open_quote) {
-        result = result.replace(&map.open_quote, "\"");
-    }
-    if lowercase.contains(&map.close_quote) {
-        result = result.replace(&map.close_quote, "\"");
+/// Spoken code commands split into words, longest first.
+fn code_command_table() -> Vec<(Vec<String>, CodeCommand)> {
+    let mut table: Vec<(Vec<String>, CodeCommand)> = CASING_COMMANDS
+        .iter()
+        .map(|(phrase, casing)| (phrase_words(phrase), CodeCommand::Casing(*casing)))
+        .chain(CODE_SYMBOLS.iter().map(|(phrase, symbol, spacing)| {
+            (phrase_words(phrase), CodeCommand::Symbol(symbol, *spacing))
+        }))
+        .collect();
+    table.sort_by_key(|(words, _)| std::cmp::Reverse(words.len()));
+    table
+}
+
+/// Rewrites spoken casing commands ("camel case user id" becomes `userId`) and
+/// symbols ("fat arrow" becomes `=>`). A casing command takes the words after
+/// it up to the next command, punctuation or line break. Line breaks and tabs
+/// are kept as they are.
+pub fn apply_code_mode(text: &str) -> String {
+    let table = code_command_table();
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find(['\n', '\t']) {
+        output.push_str(&code_mode_line(&rest[..index], &table));
+        output.push_str(&rest[index..index + 1]);
+        rest = &rest[index + 1..];
     }
-    if lowercase.contains(&map.backtick) {
-        result = result.replace(&map.backtick, "`");
+    output.push_str(&code_mode_line(rest, &table));
+    output
+}
+
+fn code_mode_line(line: &str, table: &[(Vec<String>, CodeCommand)]) -> String {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let cores: Vec<String> = tokens.iter().map(|token| word_core(token)).collect();
+    let command_at = |index: usize| {
+        table.iter().find(|(words, _)| {
+            cores
+                .get(index..index + words.len())
+                .is_some_and(|window| window == words.as_slice())
+        })
+    };
+
+    let mut pieces: Vec<(String, CodeSpacing)> = Vec::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        match command_at(index) {
+            Some((words, CodeCommand::Symbol(symbol, spacing))) => {
+                pieces.push((symbol.to_string(), *spacing));
+                index += words.len();
+            }
+            Some((words, CodeCommand::Casing(casing))) => {
+                index += words.len();
+                let mut parts = Vec::new();
+                let mut trailing = "";
+                while index < tokens.len() && command_at(index).is_none() {
+                    let token = tokens[index];
+                    parts.extend(
+                        cores[index]
+                            .split(|c: char| !c.is_alphanumeric())
+                            .filter(|part| !part.is_empty())
+                            .map(str::to_string),
+                    );
+                    index += 1;
+                    let stripped = token.trim_end_matches(['.', ',', ';', ':', '!', '?']);
+                    if stripped.len() < token.len() {
+                        trailing = &token[stripped.len()..];
+                        break;
+                    }
+                }
+                if !parts.is_empty() {
+                    pieces.push((
+                        format!("{}{}", apply_casing(&parts, *casing), trailing),
+                        CodeSpacing::Word,
+                    ));
+                }
+            }
+            None => {
+                pieces.push((tokens[index].to_string(), CodeSpacing::Word));
+                index += 1;
+            }
+        }
     }
-    if lowercase.contains(&map.code_block) {
-        result = result.replace(&map.code_block, "```");
+
+    let mut output = String::new();
+    let mut previous: Option<CodeSpacing> = None;
+    for (piece, spacing) in pieces {
+        if previous.is_some_and(|previous| code_needs_space(previous, spacing)) {
+            output.push(' ');
+        }
+        output.push_str(&piece);
+        previous = Some(spacing);
     }
+    output
+}
+
+fn code_needs_space(previous: CodeSpacing, next: CodeSpacing) -> bool {
+    !matches!(
+        (previous, next),
+        (_, CodeSpacing::Close | CodeSpacing::Tight)
+            | (CodeSpacing::Open | CodeSpacing::Tight, _)
+            | (CodeSpacing::Word, CodeSpacing::Open)
+    )
+}
 
-    result
+fn apply_casing(parts: &[String], casing: Casing) -> String {
+    let capitalize = |part: &String| {
+        let mut chars = part.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default()
+    };
+    match casing {
+        Casing::Camel => parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                if i == 0 {
+                    part.clone()
+                } else {
+                    capitalize(part)
+                }
+            })
+            .collect(),
+        Casing::Pascal => parts.iter().map(capitalize).collect(),
+        Casing::Snake => parts.join("_"),
+        Casing::Kebab => parts.join("-"),
+        Casing::ScreamingSnake => parts.join("_").to_uppercase(),
+    }
 }
 
-fn compute_file_sha256(path: &PathBuf) -> Result<String> {
-    let data = fs::read(path)?;
-    let mut hasher = Sha256::new();
-    hasher.update(&data);
-    let result = hasher.finalize();
-    Ok(hex::encode(result))
+fn compute_file_sha256(path: &Path) -> Result<String> {
+    Ok(file_sha256(path)?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::redaction::RedactionAction;
+    use crate::snippets::Snippet;
+    use crate::voice_commands::VoiceCommandMap;
 
     #[test]
     fn test_normalize_whitespace_single_spaces() {
@@ -343,9 +2029,9 @@ mod tests {
             newline: "newline".to_string(),
             ..Default::default()
         };
-        let input = "hello enter world";
-        let result = apply_voice_commands(input, &map);
-        assert!(result.contains('\n'));
+        let input = "hello newline world";
+        let result = apply_voice_commands(input, &map.to_commands());
+        assert_eq!(result, "hello\nworld");
     }
 
     #[test]
@@ -355,7 +2041,7 @@ mod tests {
             ..Default::default()
         };
         let input = "hello tab world";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains('\t'));
     }
 
@@ -366,7 +2052,7 @@ mod tests {
             ..Default::default()
         };
         let input = "hello period world";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains('.'));
     }
 
@@ -377,7 +2063,7 @@ mod tests {
             ..Default::default()
         };
         let input = "hello comma world";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains(','));
     }
 
@@ -388,7 +2074,7 @@ mod tests {
             ..Default::default()
         };
         let input = "hello colon world";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains(':'));
     }
 
@@ -399,7 +2085,7 @@ mod tests {
             ..Default::default()
         };
         let input = "hello mysemi world";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains(';'));
     }
 
@@ -410,7 +2096,7 @@ mod tests {
             ..Default::default()
         };
         let input = "open quote hello";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains('"'));
     }
 
@@ -421,7 +2107,7 @@ mod tests {
             ..Default::default()
         };
         let input = "hello close quote";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains('"'));
     }
 
@@ -432,7 +2118,7 @@ mod tests {
             ..Default::default()
         };
         let input = "hello backtick world";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains('`'));
     }
 
@@ -443,7 +2129,7 @@ mod tests {
             ..Default::default()
         };
         let input = "code block hello world code block";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains("```"));
     }
 
@@ -454,7 +2140,7 @@ mod tests {
             ..Default::default()
         };
         let input = "hello tab world";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains('\t'));
     }
 
@@ -468,7 +2154,7 @@ mod tests {
             ..Default::default()
         };
         let input = "hello   world";
-        let result = post_process_text(input, &prefs);
+        let result = post_process_text(input, &prefs, None);
         assert_eq!(result, "hello world");
     }
 
@@ -481,8 +2167,8 @@ mod tests {
             },
             ..Default::default()
         };
-        let input = "hello enter world";
-        let result = post_process_text(input, &prefs);
+        let input = "hello newline world";
+        let result = post_process_text(input, &prefs, None);
         assert!(result.contains('\n'));
     }
 
@@ -495,8 +2181,8 @@ mod tests {
             },
             ..Default::default()
         };
-        let input = "hello   enter   world";
-        let result = post_process_text(input, &prefs);
+        let input = "hello   newline   world";
+        let result = post_process_text(input, &prefs, None);
         assert!(result.contains('\n'));
     }
 
@@ -504,7 +2190,7 @@ mod tests {
     fn test_apply_voice_commands_empty() {
         let map = VoiceCommandMap::default();
         let input = "hello world";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert_eq!(result, "hello world");
     }
 
@@ -515,7 +2201,7 @@ mod tests {
             ..Default::default()
         };
         let input = "hello new paragraph world";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains("\n\n"));
     }
 
@@ -529,18 +2215,476 @@ mod tests {
     #[test]
     fn test_apply_voice_commands_multiple_commands() {
         let map = VoiceCommandMap::default();
-        let input = "enter tab period";
-        let result = apply_voice_commands(input, &map);
+        let input = "newline tab period";
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains('\n'));
         assert!(result.contains('\t'));
         assert!(result.contains('.'));
     }
 
+    #[test]
+    fn test_apply_voice_commands_matches_whole_words_only() {
+        let map = VoiceCommandMap::default();
+        let input = "the table entered a tablet";
+        assert_eq!(apply_voice_commands(input, &map.to_commands()), input);
+    }
+
+    #[test]
+    fn test_apply_voice_commands_enter_only_when_mapped() {
+        let default_map = VoiceCommandMap::default();
+        assert_eq!(
+            apply_voice_commands("hit enter now", &default_map.to_commands()),
+            "hit enter now"
+        );
+
+        let map = VoiceCommandMap {
+            newline: "enter".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            apply_voice_commands("hit Enter now", &map.to_commands()),
+            "hit\nnow"
+        );
+    }
+
+    #[test]
+    fn test_apply_voice_commands_ignores_case() {
+        let map = VoiceCommandMap::default();
+        assert_eq!(
+            apply_voice_commands("Hello TAB world New Paragraph done", &map.to_commands()),
+            "Hello\tworld\n\ndone"
+        );
+    }
+
+    #[test]
+    fn test_apply_voice_commands_prefers_longest_phrase() {
+        let map = VoiceCommandMap {
+            newline: "new".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            apply_voice_commands("one new paragraph two", &map.to_commands()),
+            "one\n\ntwo"
+        );
+        assert_eq!(
+            apply_voice_commands("one new two", &map.to_commands()),
+            "one\ntwo"
+        );
+    }
+
+    #[test]
+    fn test_apply_voice_commands_drops_whisper_punctuation() {
+        let map = VoiceCommandMap::default();
+        assert_eq!(
+            apply_voice_commands("Hello, comma, world. Period.", &map.to_commands()),
+            "Hello, world."
+        );
+        assert_eq!(
+            apply_voice_commands(
+                "First point. New paragraph. Second point.",
+                &map.to_commands()
+            ),
+            "First point.\n\nSecond point."
+        );
+        assert_eq!(
+            apply_voice_commands("Dear team, colon, thanks", &map.to_commands()),
+            "Dear team: thanks"
+        );
+    }
+
+    #[test]
+    fn test_apply_voice_commands_spaces_quotes_and_code() {
+        let map = VoiceCommandMap::default();
+        assert_eq!(
+            apply_voice_commands(
+                "he said open quote hi there close quote and left",
+                &map.to_commands()
+            ),
+            "he said \"hi there\" and left"
+        );
+        assert_eq!(
+            apply_voice_commands("run backtick cargo test backtick first", &map.to_commands()),
+            "run `cargo test` first"
+        );
+        assert_eq!(
+            apply_voice_commands(
+                "example code block let x = 1; code block done",
+                &map.to_commands()
+            ),
+            "example\n```\nlet x = 1;\n```\ndone"
+        );
+    }
+
+    #[test]
+    fn test_apply_voice_commands_user_text() {
+        let mut commands = VoiceCommandMap::default().to_commands();
+        commands.push(VoiceCommand {
+            phrases: vec!["arrow".to_string(), "fat arrow".to_string()],
+            action: CommandAction::Text("=>".to_string()),
+        });
+
+        assert_eq!(
+            apply_voice_commands("x fat arrow y, arrow z", &commands),
+            "x => y, => z"
+        );
+    }
+
+    #[test]
+    fn test_voice_command_actions_press_keys_between_text() {
+        let commands = VoiceCommandMap::default().to_commands();
+        assert_eq!(
+            voice_command_actions("Fix the typo. Select all. Press enter. Thanks", &commands),
+            vec![
+                OutputAction::Text("Fix the typo.".to_string()),
+                OutputAction::Keys(parse_key_sequence("cmd+a").unwrap()),
+                OutputAction::Keys(parse_key_sequence("enter").unwrap()),
+                OutputAction::Text("Thanks".to_string()),
+            ]
+        );
+        assert_eq!(
+            voice_command_actions("delete word", &commands),
+            vec![OutputAction::Keys(
+                parse_key_sequence("alt+backspace").unwrap()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_voice_command_actions_scratch_that() {
+        let commands = VoiceCommandMap::default().to_commands();
+        assert_eq!(
+            voice_command_actions("Scratch that.", &commands),
+            vec![OutputAction::RemoveLastDictation]
+        );
+        assert_eq!(
+            voice_command_actions("send it now scratch that send it tomorrow", &commands),
+            vec![OutputAction::Text("send it tomorrow".to_string())]
+        );
+        assert_eq!(
+            voice_command_actions("undo scratch that", &commands),
+            vec![
+                OutputAction::Keys(parse_key_sequence("cmd+z").unwrap()),
+                OutputAction::RemoveLastDictation,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_post_process_actions_without_voice_commands() {
+        let mut prefs = Preferences::default();
+        prefs.voice_commands.enabled = false;
+        assert_eq!(
+            post_process_actions("select   all", &prefs, None),
+            vec![OutputAction::Text("select all".to_string())]
+        );
+        assert!(post_process_actions("   ", &prefs, None).is_empty());
+    }
+
+    #[test]
+    fn test_post_process_text_normalizes_numbers_after_voice_commands() {
+        let prefs = Preferences::default();
+        assert_eq!(
+            post_process_text(
+                "it grew twenty three point five percent period",
+                &prefs,
+                None
+            ),
+            "it grew 23.5%."
+        );
+        assert_eq!(
+            post_process_actions("press enter meet at three pm", &prefs, Some("en")),
+            vec![
+                OutputAction::Keys(parse_key_sequence("enter").unwrap()),
+                OutputAction::Text("meet at 3 PM".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_post_process_text_itn_respects_language_and_toggle() {
+        let mut prefs = Preferences::default();
+        assert_eq!(
+            post_process_text("twenty percent", &prefs, Some("de")),
+            "twenty percent"
+        );
+        prefs.itn.languages = vec!["de".to_string()];
+        assert_eq!(
+            post_process_text("twenty percent", &prefs, Some("en")),
+            "twenty percent"
+        );
+        prefs.itn.languages = vec!["en".to_string()];
+        prefs.itn.enabled = false;
+        assert_eq!(
+            post_process_text("twenty percent", &prefs, Some("en")),
+            "twenty percent"
+        );
+    }
+
+    #[test]
+    fn test_post_process_text_expands_snippets_last() {
+        let mut prefs = Preferences::default();
+        prefs.snippets.entries = vec![
+            Snippet::new("my email", "me@example.com"),
+            Snippet::new("ticket", "#"),
+        ];
+        assert_eq!(
+            post_process_text("um send it to my email period", &prefs, None),
+            "send it to me@example.com."
+        );
+        assert_eq!(
+            post_process_text("todo marker ticket twenty three", &prefs, Some("en")),
+            "todo marker # 23"
+        );
+        prefs.snippets.enabled = false;
+        assert_eq!(post_process_text("my email", &prefs, None), "my email");
+    }
+
+    #[test]
+    fn test_remove_disfluencies_cases() {
+        let filter = DisfluencyFilter::default();
+        let cases = [
+            ("um I think it works", "I think it works"),
+            ("Um, so the fix is in", "So the fix is in"),
+            ("it was, uh, fine", "it was, fine"),
+            ("it works, um.", "it works."),
+            ("the the cache is stale", "the cache is stale"),
+            ("The the cache", "The cache"),
+            ("I think I think so", "I think so"),
+            ("we we we shipped it", "we shipped it"),
+            ("the um the build", "the build"),
+            ("it is, you know, broken", "it is, broken"),
+            ("do you know the way", "do you know the way"),
+            ("I know that that works", "I know that that works"),
+            ("in twenty twenty four", "in twenty twenty four"),
+            ("Stop. Stop.", "Stop. Stop."),
+            ("umbrella and hummus", "umbrella and hummus"),
+            ("um", ""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                remove_disfluencies(input, &filter, "en"),
+                expected,
+                "input: {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_remove_disfluencies_self_corrections() {
+        let filter = DisfluencyFilter {
+            self_corrections: true,
+            ..Default::default()
+        };
+        let cases = [
+            ("meet at three, no wait, four", "meet at four"),
+            ("send it Tuesday, no wait, Wednesday", "send it Wednesday"),
+            ("the blue car, I mean, the red car", "the red car"),
+            ("call John, I mean, Jane", "call Jane"),
+            ("that is what I mean", "that is what I mean"),
+            ("Done. I mean it", "Done. I mean it"),
+            (
+                "what I mean is that this works",
+                "what I mean is that this works",
+            ),
+            ("by the way I mean well", "by the way I mean well"),
+            ("meet at three no wait four", "meet at three no wait four"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                remove_disfluencies(input, &filter, "en"),
+                expected,
+                "input: {input:?}"
+            );
+        }
+        assert_eq!(
+            remove_disfluencies(
+                "meet at three, no wait, four",
+                &DisfluencyFilter::default(),
+                "en"
+            ),
+            "meet at three, no wait, four"
+        );
+    }
+
+    #[test]
+    fn test_remove_disfluencies_uses_language_list() {
+        let filter = DisfluencyFilter::default();
+        assert_eq!(
+            remove_disfluencies("äh ich denke", &filter, "de"),
+            "ich denke"
+        );
+        assert_eq!(
+            remove_disfluencies("euh je pense", &filter, "fr-CA"),
+            "je pense"
+        );
+        assert_eq!(remove_disfluencies("um yes", &filter, "de"), "um yes");
+        assert_eq!(remove_disfluencies("um yes", &filter, "ja"), "um yes");
+    }
+
+    #[test]
+    fn test_remove_disfluencies_disabled() {
+        let filter = DisfluencyFilter {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            remove_disfluencies("um the the test", &filter, "en"),
+            "um the the test"
+        );
+    }
+
+    #[test]
+    fn test_post_process_keeps_unfiltered_text() {
+        let prefs = Preferences::default();
+        let processed = post_process("um  the the test", &prefs, None);
+        assert_eq!(processed.text(), "the test");
+        assert_eq!(processed.unfiltered.as_deref(), Some("um the the test"));
+
+        let processed = post_process("clean   text", &prefs, None);
+        assert_eq!(processed.text(), "clean text");
+        assert_eq!(processed.unfiltered, None);
+    }
+
+    #[test]
+    fn test_apply_code_mode_cases() {
+        let cases = [
+            ("camel case user id", "userId"),
+            ("pascal case user id", "UserId"),
+            ("snake case user id", "user_id"),
+            ("kebab case user id", "user-id"),
+            ("screaming snake max retry count", "MAX_RETRY_COUNT"),
+            ("screaming snake case max retries", "MAX_RETRIES"),
+            ("Camel case, user ID.", "userId."),
+            ("camel case user, id", "user, id"),
+            ("camel case HTTP request 2", "httpRequest2"),
+            ("camel case", ""),
+            (
+                "let camel case user id equals get user open paren close paren",
+                "let userId = get user()",
+            ),
+            (
+                "camel case user id pipe snake case get all",
+                "userId | get_all",
+            ),
+            ("x fat arrow x double equals y", "x => x == y"),
+            (
+                "a not equals b double ampersand c double pipe d",
+                "a != b && c || d",
+            ),
+            ("a triple equals b", "a === b"),
+            ("foo arrow bar", "foo -> bar"),
+            ("Fat arrow.", "=>"),
+            ("a pipe b ampersand c", "a | b & c"),
+            ("items open bracket zero close bracket", "items[zero]"),
+            ("if open paren ready close paren open brace", "if(ready) {"),
+            ("self dot name", "self.name"),
+            ("std double colon io", "std::io"),
+            (
+                "snake case first name\ncamel case last name",
+                "first_name\nlastName",
+            ),
+            ("\tpascal case app state", "\tAppState"),
+            ("plain words stay put.", "plain words stay put."),
+            ("", ""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(apply_code_mode(input), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_code_mode_enabled_by_app_or_detection() {
+        let prefs = CodeModePrefs {
+            apps: vec!["Code".to_string(), " iTerm2 ".to_string()],
+            detect_code_blocks: true,
+        };
+        let cases = [
+            (Some("code"), "hello there", true),
+            (Some("iTerm2"), "hello there", true),
+            (Some("Mail"), "hello there", false),
+            (None, "hello there", false),
+            (Some("Mail"), "```\nlet x equals one", true),
+        ];
+        for (app, text, expected) in cases {
+            assert_eq!(
+                code_mode_enabled(&prefs, app, text),
+                expected,
+                "app: {:?}, text: {:?}",
+                app,
+                text
+            );
+        }
+
+        let no_detection = CodeModePrefs {
+            detect_code_blocks: false,
+            ..prefs
+        };
+        assert!(!code_mode_enabled(
+            &no_detection,
+            Some("Mail"),
+            "```\nlet x equals one"
+        ));
+    }
+
+    #[test]
+    fn test_result_code_mode_keeps_key_presses() {
+        let mut result = scripted_result("");
+        result.actions = vec![
+            OutputAction::Text("camel case user id".to_string()),
+            OutputAction::Keys(parse_key_sequence("enter").unwrap()),
+            OutputAction::Text("snake case next one".to_string()),
+        ];
+        result.apply_code_mode();
+        assert_eq!(result.text, "userIdnext_one");
+        assert_eq!(
+            result.actions[1],
+            OutputAction::Keys(parse_key_sequence("enter").unwrap())
+        );
+
+        let mut plain = scripted_result("pascal case app state");
+        plain.apply_code_mode();
+        assert_eq!(plain.text, "AppState");
+    }
+
+    #[test]
+    fn test_result_redact_masks_stored_text_and_drops_blocked_typing() {
+        let prefs = RedactionPrefs {
+            emails: RedactionAction::Block,
+            ..RedactionPrefs::default()
+        };
+        let mut result = scripted_result("card 4111 1111 1111 1111 to jane@example.com");
+        result.unfiltered_text = Some("um card 4111 1111 1111 1111".to_string());
+        result.redact(&prefs);
+
+        assert_eq!(
+            result.text,
+            "card [redacted card number] to [redacted email]"
+        );
+        assert_eq!(
+            result.unfiltered_text.as_deref(),
+            Some("um card [redacted card number]")
+        );
+        assert_eq!(
+            result.output_actions(),
+            vec![OutputAction::Text(
+                "card 4111 1111 1111 1111 to".to_string()
+            )]
+        );
+
+        let mut blocked = scripted_result("jane@example.com");
+        blocked.redact(&prefs);
+        assert_eq!(blocked.text, "[redacted email]");
+        assert_eq!(
+            blocked.output_actions(),
+            vec![OutputAction::Text(String::new())]
+        );
+    }
+
     #[test]
     fn test_apply_voice_commands_preserves_text() {
         let map = VoiceCommandMap::default();
         let input = "actual speech text";
-        let result = apply_voice_commands(input, &map);
+        let result = apply_voice_commands(input, &map.to_commands());
         assert!(result.contains("actual"));
         assert!(result.contains("speech"));
         assert!(result.contains("text"));
@@ -549,21 +2693,21 @@ mod tests {
 
 #[test]
 fn test_get_model_info_english_small() {
-    let info = get_model_info(&ModelProfile::EnglishSmall);
+    let info = get_model_info(&ModelProfile::EnglishSmall).unwrap();
     assert_eq!(info.filename, "ggml-model-small.en.bin");
     assert_eq!(info.sha256.len(), 64);
 }
 
 #[test]
 fn test_get_model_info_multilingual_small() {
-    let info = get_model_info(&ModelProfile::MultilingualSmall);
+    let info = get_model_info(&ModelProfile::MultilingualSmall).unwrap();
     assert_eq!(info.filename, "ggml-model-small.bin");
     assert_eq!(info.sha256.len(), 64);
 }
 
 #[test]
 fn test_get_model_info_multilingual_medium() {
-    let info = get_model_info(&ModelProfile::MultilingualMedium);
+    let info = get_model_info(&ModelProfile::MultilingualMedium).unwrap();
     assert_eq!(info.filename, "ggml-model-medium.bin");
     assert_eq!(info.sha256.len(), 64);
 }
@@ -575,6 +2719,498 @@ fn test_stt_engine_initial_state() {
     assert!(engine.get_current_profile().is_none());
 }
 
+#[test]
+fn test_stt_engine_transcribe_requires_loaded_model() {
+    let engine = SttEngine::with_backend(ScriptedBackend::new().otherwise("hello"));
+    let result = engine.transcribe(&[0.0; 16], &Preferences::default());
+    assert!(result.unwrap_err().to_string().contains("No model loaded"));
+}
+
+#[test]
+fn test_stt_engine_post_processes_backend_output() {
+    let engine = SttEngine::with_backend(ScriptedBackend::new().otherwise("  hello    world "));
+    engine
+        .load_model(ModelProfile::EnglishSmall, PathBuf::new())
+        .unwrap();
+    assert!(engine.is_loaded());
+
+    let prefs = Preferences {
+        voice_commands: VoiceCommands {
+            enabled: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let result = engine.transcribe(&[0.1; 16], &prefs).unwrap();
+    assert_eq!(result.text, "hello world");
+}
+
+#[test]
+fn test_stt_engine_keeps_text_before_disfluency_removal() {
+    let engine = SttEngine::with_backend(ScriptedBackend::new().otherwise("uh  the the test"));
+    engine
+        .load_model(ModelProfile::EnglishSmall, PathBuf::new())
+        .unwrap();
+
+    let result = engine
+        .transcribe(&[0.1; 16], &Preferences::default())
+        .unwrap();
+    assert_eq!(result.text, "the test");
+    assert_eq!(result.unfiltered_text.as_deref(), Some("uh the the test"));
+}
+
+#[test]
+fn test_stt_engine_drops_hallucinations_before_post_processing() {
+    let engine = SttEngine::with_backend(
+        ScriptedBackend::new()
+            .respond(&[0.0; 1600], "Thank you.")
+            .respond(&[0.2; 1600], "Thanks for watching!"),
+    );
+    engine
+        .load_model(ModelProfile::EnglishSmall, PathBuf::new())
+        .unwrap();
+
+    let prefs = Preferences::default();
+    assert_eq!(engine.transcribe(&[0.0; 1600], &prefs).unwrap().text, "");
+    assert_eq!(engine.transcribe(&[0.2; 1600], &prefs).unwrap().text, "");
+}
+
+#[cfg(test)]
+fn bilingual_engine(backend: ScriptedBackend) -> (SttEngine, Arc<ScriptedBackend>) {
+    let backend = Arc::new(backend);
+    let engine = SttEngine::with_backend(Arc::clone(&backend));
+    engine
+        .load_model(ModelProfile::MultilingualSmall, PathBuf::new())
+        .unwrap();
+    (engine, backend)
+}
+
+#[cfg(test)]
+fn translation_prefs(mode: TranslationMode, typed: TextKind) -> Preferences {
+    let mut prefs = Preferences::default();
+    prefs.translation.mode = mode;
+    prefs.translation.typed = typed;
+    prefs
+}
+
+#[test]
+fn test_stt_engine_translation_only_decodes_once() {
+    let (engine, backend) = bilingual_engine(
+        ScriptedBackend::new()
+            .respond(&[0.2; 1600], "hola a todos")
+            .translate(&[0.2; 1600], "hello everyone"),
+    );
+
+    let result = engine
+        .transcribe(&[0.2; 1600], &Preferences::default())
+        .unwrap();
+    assert_eq!(result.text, "hello everyone");
+    assert_eq!(result.original_text, None);
+    assert_eq!(result.translated_text, None);
+    assert_eq!(backend.calls(), 1);
+}
+
+#[test]
+fn test_stt_engine_both_passes_keeps_original_and_translation() {
+    let (engine, backend) = bilingual_engine(
+        ScriptedBackend::new()
+            .respond(&[0.2; 1600], "hola a todos")
+            .translate(&[0.2; 1600], "hello everyone"),
+    );
+
+    let prefs = translation_prefs(TranslationMode::BothPasses, TextKind::Translation);
+    let result = engine.transcribe(&[0.2; 1600], &prefs).unwrap();
+    assert_eq!(result.text, "hello everyone");
+    assert_eq!(result.original_text.as_deref(), Some("hola a todos"));
+    assert_eq!(result.translated_text.as_deref(), Some("hello everyone"));
+    assert_eq!(backend.calls(), 2);
+
+    let prefs = translation_prefs(TranslationMode::BothPasses, TextKind::Original);
+    let result = engine.transcribe(&[0.2; 1600], &prefs).unwrap();
+    assert_eq!(result.text, "hola a todos");
+    assert_eq!(result.translated_text.as_deref(), Some("hello everyone"));
+}
+
+#[test]
+fn test_stt_engine_background_mode_decodes_typed_text_only() {
+    let (engine, backend) = bilingual_engine(
+        ScriptedBackend::new()
+            .respond(&[0.2; 1600], "hola a todos")
+            .translate(&[0.2; 1600], "hello everyone"),
+    );
+
+    let prefs = translation_prefs(TranslationMode::Background, TextKind::Original);
+    let result = engine.transcribe(&[0.2; 1600], &prefs).unwrap();
+    assert_eq!(result.text, "hola a todos");
+    assert_eq!(result.original_text.as_deref(), Some("hola a todos"));
+    assert_eq!(result.translated_text, None);
+    assert_eq!(backend.calls(), 1);
+}
+
+#[test]
+fn test_stt_engine_skips_second_pass_for_english() {
+    let mut english = scripted_result("good morning");
+    english.language = Some("en".to_string());
+    let (engine, backend) =
+        bilingual_engine(ScriptedBackend::new().respond_with(&[0.2; 1600], english));
+
+    let prefs = translation_prefs(TranslationMode::BothPasses, TextKind::Translation);
+    let result = engine.transcribe(&[0.2; 1600], &prefs).unwrap();
+    assert_eq!(result.original_text.as_deref(), Some("good morning"));
+    assert_eq!(result.translated_text.as_deref(), Some("good morning"));
+    assert_eq!(backend.calls(), 1);
+}
+
+#[test]
+fn test_stt_engine_transcribes_long_audio_in_windows() {
+    let audio: Vec<f32> = (0..16_000 * 70)
+        .map(|i| (i as f32 * 0.05).sin() * 0.3)
+        .collect();
+    let windows = crate::windowing::plan_windows(&audio);
+    assert_eq!(windows.len(), 3);
+
+    let backend = Arc::new(
+        ScriptedBackend::new()
+            .respond(&audio[windows[0].clone()], " The first window ends here")
+            .respond(
+                &audio[windows[1].clone()],
+                " ends here and the second goes on",
+            )
+            .respond(&audio[windows[2].clone()], " goes on until the end."),
+    );
+    let engine = SttEngine::with_backend(Arc::clone(&backend));
+    engine
+        .load_model(ModelProfile::EnglishSmall, PathBuf::new())
+        .unwrap();
+
+    let mut progress = Vec::new();
+    let result = engine
+        .transcribe_with_progress(&audio, &Preferences::default(), &CancelToken::new(), |p| {
+            progress.push((p.completed_windows, p.total_windows))
+        })
+        .unwrap();
+
+    assert_eq!(
+        result.text,
+        "The first window ends here and the second goes on until the end."
+    );
+    assert_eq!(progress, vec![(1, 3), (2, 3), (3, 3)]);
+    assert_eq!(
+        backend.prompts(),
+        vec![
+            None,
+            Some("The first window ends here".to_string()),
+            Some("The first window ends here and the second goes on".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_stt_engine_stops_between_windows_when_cancelled() {
+    let audio = vec![0.3; 16_000 * 40];
+    let backend = Arc::new(ScriptedBackend::new().otherwise("window"));
+    let engine = SttEngine::with_backend(Arc::clone(&backend));
+    engine
+        .load_model(ModelProfile::EnglishSmall, PathBuf::new())
+        .unwrap();
+
+    let cancel = CancelToken::new();
+    let result = engine.transcribe_with_progress(&audio, &Preferences::default(), &cancel, |_| {
+        cancel.cancel()
+    });
+    assert!(result.is_err());
+    assert_eq!(backend.calls(), 1);
+}
+
+#[test]
+fn test_scripted_backend_answers_per_input() {
+    let first = vec![0.1; 32];
+    let second = vec![0.2; 32];
+    let broken = vec![0.3; 32];
+    let backend = Arc::new(
+        ScriptedBackend::new()
+            .respond(&first, "first utterance")
+            .respond(&second, "second utterance")
+            .fail(&broken, "decoder exploded"),
+    );
+    let engine = SttEngine::with_backend(Arc::clone(&backend));
+    engine
+        .load_model(ModelProfile::MultilingualSmall, PathBuf::new())
+        .unwrap();
+
+    let prefs = Preferences::default();
+    assert_eq!(
+        engine.transcribe(&second, &prefs).unwrap().text,
+        "second utterance"
+    );
+    assert_eq!(
+        engine.transcribe(&first, &prefs).unwrap().text,
+        "first utterance"
+    );
+    assert!(engine
+        .transcribe(&broken, &prefs)
+        .unwrap_err()
+        .to_string()
+        .contains("decoder exploded"));
+    assert!(engine.transcribe(&[0.9; 4], &prefs).is_err());
+    assert_eq!(backend.calls(), 4);
+}
+
+#[cfg(test)]
+fn loaded_worker(backend: ScriptedBackend) -> (InferenceWorker, Arc<ScriptedBackend>) {
+    let backend = Arc::new(backend);
+    let engine = SttEngine::with_backend(Arc::clone(&backend));
+    engine
+        .load_model(ModelProfile::EnglishSmall, PathBuf::new())
+        .unwrap();
+    (InferenceWorker::spawn(Arc::new(engine)), backend)
+}
+
+#[cfg(test)]
+fn submit_and_wait(
+    worker: &InferenceWorker,
+    audio_data: &[f32],
+    timeout: Duration,
+) -> (JobHandle, mpsc::Receiver<InferenceOutcome>) {
+    let (tx, rx) = mpsc::channel();
+    let handle = worker.submit(
+        audio_data.to_vec(),
+        Preferences::default(),
+        timeout,
+        move |_, outcome| {
+            let _ = tx.send(outcome);
+        },
+    );
+    (handle, rx)
+}
+
+#[test]
+fn test_inference_worker_completes_jobs_in_order() {
+    let (worker, _) = loaded_worker(
+        ScriptedBackend::new()
+            .respond(&[0.1; 8], "first")
+            .respond(&[0.2; 8], "second"),
+    );
+    let (tx, rx) = mpsc::channel();
+    for audio in [[0.1; 8], [0.2; 8]] {
+        let tx = tx.clone();
+        worker.submit(
+            audio.to_vec(),
+            Preferences::default(),
+            Duration::from_secs(5),
+            move |id, outcome| {
+                let _ = tx.send((id, outcome));
+            },
+        );
+    }
+
+    let texts: Vec<(JobId, String)> = (0..2)
+        .map(|_| match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            (id, InferenceOutcome::Completed(result)) => (id, result.text),
+            (_, other) => panic!("unexpected outcome: {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        texts,
+        vec![(1, "first".to_string()), (2, "second".to_string())]
+    );
+}
+
+#[test]
+fn test_inference_worker_cancels_running_job() {
+    let (worker, _) = loaded_worker(ScriptedBackend::new().block_until_cancelled(&[0.5; 8]));
+    let (handle, rx) = submit_and_wait(&worker, &[0.5; 8], Duration::from_secs(30));
+
+    handle.cancel();
+    let outcome = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(matches!(outcome, InferenceOutcome::Cancelled));
+}
+
+#[test]
+fn test_inference_worker_times_out_long_job() {
+    let (worker, _) = loaded_worker(ScriptedBackend::new().block_until_cancelled(&[0.5; 8]));
+    let (_, rx) = submit_and_wait(&worker, &[0.5; 8], Duration::from_millis(20));
+
+    let outcome = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(matches!(outcome, InferenceOutcome::TimedOut));
+}
+
+#[test]
+fn test_inference_worker_skips_job_cancelled_while_queued() {
+    let (worker, backend) = loaded_worker(
+        ScriptedBackend::new()
+            .block_until_cancelled(&[0.5; 8])
+            .otherwise("queued"),
+    );
+    let (running, first) = submit_and_wait(&worker, &[0.5; 8], Duration::from_secs(30));
+    let (queued, second) = submit_and_wait(&worker, &[0.7; 8], Duration::from_secs(30));
+    while backend.calls() == 0 {
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    queued.cancel();
+    running.cancel();
+
+    assert!(matches!(
+        first.recv_timeout(Duration::from_secs(5)).unwrap(),
+        InferenceOutcome::Cancelled
+    ));
+    assert!(matches!(
+        second.recv_timeout(Duration::from_secs(5)).unwrap(),
+        InferenceOutcome::Cancelled
+    ));
+    assert_eq!(backend.calls(), 1);
+}
+
+#[test]
+fn test_inference_worker_cancel_session_spares_other_jobs() {
+    let (worker, backend) = loaded_worker(
+        ScriptedBackend::new()
+            .block_until_cancelled(&[0.5; 8])
+            .otherwise("kept"),
+    );
+    let submit = |session_id: Option<&str>, audio_data: &[f32]| {
+        let (tx, rx) = mpsc::channel();
+        let on_complete = move |_, outcome| {
+            let _ = tx.send(outcome);
+        };
+        let audio_data = audio_data.to_vec();
+        let prefs = Preferences::default();
+        let timeout = Duration::from_secs(30);
+        let handle = match session_id {
+            Some(id) => worker.submit_for_session(id, audio_data, prefs, timeout, on_complete),
+            None => worker.submit(audio_data, prefs, timeout, on_complete),
+        };
+        (handle, rx)
+    };
+    let (running, in_flight) = submit(Some("earlier"), &[0.5; 8]);
+    let (_, superseded) = submit(Some("open"), &[0.6; 8]);
+    let (_, background) = submit(None, &[0.7; 8]);
+    while backend.calls() == 0 {
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    assert_eq!(worker.cancel_session("open"), 1);
+    assert_eq!(worker.cancel_session("open"), 0);
+    assert!(in_flight.try_recv().is_err());
+
+    running.cancel();
+    assert!(matches!(
+        in_flight.recv_timeout(Duration::from_secs(5)).unwrap(),
+        InferenceOutcome::Cancelled
+    ));
+    assert!(matches!(
+        superseded.recv_timeout(Duration::from_secs(5)).unwrap(),
+        InferenceOutcome::Cancelled
+    ));
+    match background.recv_timeout(Duration::from_secs(5)).unwrap() {
+        InferenceOutcome::Completed(result) => assert_eq!(result.text, "kept"),
+        other => panic!("expected completed background pass, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_inference_worker_queues_transcription_behind_model_load() {
+    let backend = Arc::new(ScriptedBackend::new().respond(&[0.4; 8], "first words"));
+    let engine = SttEngine::with_backend(Arc::clone(&backend));
+    let worker = InferenceWorker::spawn(Arc::new(engine));
+
+    let (loaded_tx, loaded) = mpsc::channel();
+    worker.load_model(ModelProfile::EnglishSmall, PathBuf::new(), move |result| {
+        loaded_tx.send(result).unwrap();
+    });
+    let (_, outcome) = submit_and_wait(&worker, &[0.4; 8], Duration::from_secs(5));
+
+    assert_eq!(loaded.recv_timeout(Duration::from_secs(5)).unwrap(), Ok(()));
+    match outcome.recv_timeout(Duration::from_secs(5)).unwrap() {
+        InferenceOutcome::Completed(result) => assert_eq!(result.text, "first words"),
+        other => panic!("expected completed transcription, got {:?}", other),
+    }
+    // The warm-up decode has no scripted answer; its failure is only logged.
+    assert_eq!(backend.calls(), 2);
+}
+
+#[test]
+fn test_inference_worker_uses_each_jobs_model_for_that_job_only() {
+    let backend = Arc::new(ScriptedBackend::new().otherwise("words"));
+    let engine = Arc::new(SttEngine::with_backend(Arc::clone(&backend)));
+    let worker = InferenceWorker::spawn(Arc::clone(&engine));
+    let (loaded_tx, loaded) = mpsc::channel();
+    worker.load_model(ModelProfile::EnglishSmall, PathBuf::new(), move |result| {
+        loaded_tx.send(result).unwrap();
+    });
+    assert_eq!(loaded.recv_timeout(Duration::from_secs(5)).unwrap(), Ok(()));
+
+    let decoded_with = |profile: ModelProfile| {
+        let prefs = Preferences {
+            model_profile: profile,
+            ..Preferences::default()
+        };
+        let (tx, rx) = mpsc::channel();
+        worker.submit(
+            vec![0.4; 8],
+            prefs,
+            Duration::from_secs(5),
+            move |_, outcome| {
+                tx.send(outcome).unwrap();
+            },
+        );
+        let outcome = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(outcome, InferenceOutcome::Completed(_)));
+        assert_eq!(
+            engine.get_current_profile(),
+            Some(ModelProfile::EnglishSmall)
+        );
+        backend.profiles().pop().unwrap()
+    };
+
+    assert_eq!(
+        decoded_with(ModelProfile::MultilingualSmall),
+        ModelProfile::MultilingualSmall
+    );
+    assert_eq!(
+        decoded_with(ModelProfile::EnglishSmall),
+        ModelProfile::EnglishSmall
+    );
+    // A model that can't be loaded falls back to the loaded one.
+    assert_eq!(
+        decoded_with(ModelProfile::Imported("missing".to_string())),
+        ModelProfile::EnglishSmall
+    );
+    // Only the engine's own load warmed up.
+    assert_eq!(backend.calls(), 4);
+}
+
+#[test]
+fn test_inference_worker_reports_model_load_failure() {
+    let engine = SttEngine::with_backend(ScriptedBackend::new().fail_load("checksum mismatch"));
+    let worker = InferenceWorker::spawn(Arc::new(engine));
+
+    let (tx, rx) = mpsc::channel();
+    worker.load_model(ModelProfile::EnglishSmall, PathBuf::new(), move |result| {
+        tx.send(result).unwrap();
+    });
+
+    let error = rx
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap()
+        .unwrap_err();
+    assert!(error.contains("checksum mismatch"));
+}
+
+#[test]
+fn test_warm_up_requires_loaded_model() {
+    let backend = Arc::new(ScriptedBackend::new().otherwise(""));
+    let engine = SttEngine::with_backend(Arc::clone(&backend));
+    assert!(engine.warm_up().is_err());
+
+    engine
+        .load_model(ModelProfile::EnglishSmall, PathBuf::new())
+        .unwrap();
+    engine.warm_up().unwrap();
+    assert_eq!(backend.calls(), 1);
+}
+
 #[test]
 fn test_stt_engine_load_model_missing_file() {
     let engine = SttEngine::new();
@@ -643,7 +3279,7 @@ fn test_compute_file_sha256_binary_content() {
 
 #[test]
 fn test_model_info_sha256_format() {
-    let info = get_model_info(&ModelProfile::MultilingualSmall);
+    let info = get_model_info(&ModelProfile::MultilingualSmall).unwrap();
 
     for c in info.sha256.chars() {
         assert!(
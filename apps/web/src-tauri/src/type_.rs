@@ -1,9 +1,13 @@
-use arboard::Clipboard;
+use crate::keyplan::{plan_keystrokes, KeyEvent, KeyPosition, KeyboardLayout, PlanKey};
+use crate::output::OutputError;
+use crate::voice_commands::{inserted_text, KeyChord, NamedKey, OutputAction};
+use arboard::{Clipboard, ImageData};
 use enigo::{
-    Direction::{Click, Press, Release},
+    Direction::{self, Click, Press, Release},
     Enigo, Key, Keyboard, Settings,
 };
 use parking_lot::Mutex;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
@@ -67,6 +71,10 @@ pub struct TypeOptions {
     pub clipboard_fallback: bool,
     pub detect_code_context: bool,
     pub detect_password_fields: bool,
+    pub layout: KeyboardLayout,
+    /// Base time pasted text stays on the clipboard before the previous
+    /// contents are put back.
+    pub clipboard_restore_ms: u64,
 }
 
 impl Default for TypeOptions {
@@ -78,16 +86,292 @@ impl Default for TypeOptions {
             clipboard_fallback: true,
             detect_code_context: true,
             detect_password_fields: true,
+            layout: KeyboardLayout::Us,
+            clipboard_restore_ms: 250,
         }
     }
 }
 
+fn enigo_key(key: NamedKey) -> Result<Key, String> {
+    Ok(match key {
+        NamedKey::Enter => Key::Return,
+        NamedKey::Tab => Key::Tab,
+        NamedKey::Space => Key::Space,
+        NamedKey::Backspace => Key::Backspace,
+        NamedKey::Delete => Key::Delete,
+        NamedKey::Escape => Key::Escape,
+        NamedKey::Up => Key::UpArrow,
+        NamedKey::Down => Key::DownArrow,
+        NamedKey::Left => Key::LeftArrow,
+        NamedKey::Right => Key::RightArrow,
+        NamedKey::Home => Key::Home,
+        NamedKey::End => Key::End,
+        NamedKey::PageUp => Key::PageUp,
+        NamedKey::PageDown => Key::PageDown,
+        NamedKey::Function(1) => Key::F1,
+        NamedKey::Function(2) => Key::F2,
+        NamedKey::Function(3) => Key::F3,
+        NamedKey::Function(4) => Key::F4,
+        NamedKey::Function(5) => Key::F5,
+        NamedKey::Function(6) => Key::F6,
+        NamedKey::Function(7) => Key::F7,
+        NamedKey::Function(8) => Key::F8,
+        NamedKey::Function(9) => Key::F9,
+        NamedKey::Function(10) => Key::F10,
+        NamedKey::Function(11) => Key::F11,
+        NamedKey::Function(12) => Key::F12,
+        NamedKey::Function(n) => return Err(format!("F{} is not a key that can be pressed", n)),
+        NamedKey::Char(c) => Key::Unicode(c),
+    })
+}
+
+/// A key plan event resolved to what enigo sends.
+enum KeyStep {
+    Key(Key, Direction),
+    Text(char),
+}
+
+/// macOS virtual key codes for each `KeyPosition`, row by row.
+#[cfg(target_os = "macos")]
+const MAC_KEYCODES: [&[u32]; 4] = [
+    &[
+        0x32, 0x12, 0x13, 0x14, 0x15, 0x17, 0x16, 0x1A, 0x1C, 0x19, 0x1D, 0x1B, 0x18,
+    ],
+    &[
+        0x0C, 0x0D, 0x0E, 0x0F, 0x11, 0x10, 0x20, 0x22, 0x1F, 0x23, 0x21, 0x1E, 0x2A,
+    ],
+    &[
+        0x00, 0x01, 0x02, 0x03, 0x05, 0x04, 0x26, 0x28, 0x25, 0x29, 0x27, 0x2A,
+    ],
+    &[
+        0x0A, 0x06, 0x07, 0x08, 0x09, 0x0B, 0x2D, 0x2E, 0x2B, 0x2F, 0x2C,
+    ],
+];
+
+/// Whether planned Shift presses are sent. Only macOS types positions by
+/// key code; elsewhere a `Key::Unicode` lets enigo choose the keysym, which
+/// can drop or misapply a held Shift, so the shifted character is sent.
+const SENDS_SHIFT: bool = cfg!(target_os = "macos");
+
+fn plan_enigo_key(key: PlanKey, shifted: bool, layout: KeyboardLayout) -> Result<Key, String> {
+    match key {
+        PlanKey::Shift => Ok(Key::Shift),
+        PlanKey::Return => Ok(Key::Return),
+        PlanKey::Tab => Ok(Key::Tab),
+        PlanKey::Space => Ok(Key::Space),
+        PlanKey::Position(position) => position_key(position, shifted, layout)
+            .ok_or_else(|| format!("No key at {:?} on {:?}", position, layout)),
+    }
+}
+
+/// macOS types by key code so the active input source decides the character.
+#[cfg(target_os = "macos")]
+fn position_key(position: KeyPosition, _shifted: bool, _layout: KeyboardLayout) -> Option<Key> {
+    MAC_KEYCODES
+        .get(position.row as usize)?
+        .get(position.column as usize)
+        .map(|&code| Key::Other(code))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn position_key(position: KeyPosition, shifted: bool, layout: KeyboardLayout) -> Option<Key> {
+    layout.char_at(position, shifted).map(Key::Unicode)
+}
+
+/// Resolves a key plan to what enigo sends on this platform.
+fn resolve_plan(events: Vec<KeyEvent>, layout: KeyboardLayout) -> Result<Vec<KeyStep>, String> {
+    let mut steps = Vec::with_capacity(events.len());
+    let mut shifted = false;
+    for event in events {
+        let step = match event {
+            KeyEvent::Press(PlanKey::Shift) | KeyEvent::Release(PlanKey::Shift) => {
+                shifted = matches!(event, KeyEvent::Press(_));
+                if !SENDS_SHIFT {
+                    continue;
+                }
+                KeyStep::Key(Key::Shift, if shifted { Press } else { Release })
+            }
+            KeyEvent::Press(key) => KeyStep::Key(plan_enigo_key(key, shifted, layout)?, Press),
+            KeyEvent::Release(key) => KeyStep::Key(plan_enigo_key(key, shifted, layout)?, Release),
+            KeyEvent::Click(key) => KeyStep::Key(plan_enigo_key(key, shifted, layout)?, Click),
+            KeyEvent::Unicode(c) => KeyStep::Text(c),
+        };
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+/// What was on the clipboard before a paste. Every format arboard can read
+/// is captured, but each arboard write replaces the whole clipboard and only
+/// HTML can be written together with its plain text. So HTML comes back with
+/// its text, while a copy that also held an image or files gets only those
+/// back and loses its text and HTML.
+#[derive(Default)]
+struct ClipboardSnapshot {
+    text: Option<String>,
+    html: Option<String>,
+    image: Option<ImageData<'static>>,
+    files: Option<Vec<PathBuf>>,
+}
+
+impl ClipboardSnapshot {
+    fn capture(clipboard: &mut Clipboard) -> Self {
+        Self {
+            text: clipboard.get().text().ok(),
+            html: clipboard.get().html().ok(),
+            image: clipboard.get().image().ok(),
+            files: clipboard
+                .get()
+                .file_list()
+                .ok()
+                .filter(|files| !files.is_empty()),
+        }
+    }
+
+    /// The single write that puts back as much of the snapshot as arboard
+    /// allows, richest format first.
+    fn restore_write(&self) -> ClipboardWrite<'_> {
+        if let Some(files) = &self.files {
+            ClipboardWrite::Files(files)
+        } else if let Some(image) = &self.image {
+            ClipboardWrite::Image(image)
+        } else if let Some(html) = &self.html {
+            ClipboardWrite::Html {
+                html,
+                alt_text: self.text.as_deref(),
+            }
+        } else if let Some(text) = &self.text {
+            ClipboardWrite::Text(text)
+        } else {
+            ClipboardWrite::Clear
+        }
+    }
+
+    fn restore(&self, clipboard: &mut Clipboard) -> Result<(), arboard::Error> {
+        match self.restore_write() {
+            ClipboardWrite::Files(files) => clipboard.set().file_list(files),
+            ClipboardWrite::Image(image) => clipboard.set().image(image.clone()),
+            ClipboardWrite::Html { html, alt_text } => clipboard.set().html(html, alt_text),
+            ClipboardWrite::Text(text) => clipboard.set().text(text),
+            ClipboardWrite::Clear => clipboard.clear(),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ClipboardWrite<'a> {
+    Files(&'a [PathBuf]),
+    Image(&'a ImageData<'static>),
+    Html {
+        html: &'a str,
+        alt_text: Option<&'a str>,
+    },
+    Text(&'a str),
+    Clear,
+}
+
+/// How long to leave pasted text on the clipboard: `base_ms`, plus time for
+/// slow apps to read a long paste, up to four times `base_ms`.
+fn restore_delay(base_ms: u64, chars: usize) -> Duration {
+    let extra = (chars as u64 / 20).min(base_ms * 3);
+    Duration::from_millis(base_ms + extra)
+}
+
+/// What one stretch of dictation left in the focused app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TypedRun {
+    /// Characters sent one key event each.
+    Keystrokes(usize),
+    /// Characters pasted in a single edit.
+    Paste(usize),
+}
+
+/// What a dictation typed since its last key press, so "scratch that" can
+/// take exactly that back out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypedOutput {
+    runs: Vec<TypedRun>,
+}
+
+impl TypedOutput {
+    pub fn keystrokes(chars: usize) -> Self {
+        let mut typed = Self::default();
+        typed.push(TypedRun::Keystrokes(chars));
+        typed
+    }
+
+    pub fn chars(&self) -> usize {
+        self.runs
+            .iter()
+            .map(|run| match *run {
+                TypedRun::Keystrokes(chars) | TypedRun::Paste(chars) => chars,
+            })
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars() == 0
+    }
+
+    fn push(&mut self, run: TypedRun) {
+        match (self.runs.last_mut(), run) {
+            (_, TypedRun::Keystrokes(0) | TypedRun::Paste(0)) => {}
+            (Some(TypedRun::Keystrokes(chars)), TypedRun::Keystrokes(more)) => *chars += more,
+            _ => self.runs.push(run),
+        }
+    }
+
+    fn extend(&mut self, other: TypedOutput) {
+        for run in other.runs {
+            self.push(run);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.runs.clear();
+    }
+}
+
+/// Stops typing that is under way. The key listener calls `cancel`; presses
+/// while nothing is being typed are ignored so they can't cancel the next
+/// dictation.
+#[derive(Debug, Default)]
+pub struct TypingCancel {
+    armed: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+impl TypingCancel {
+    /// Returns whether typing was under way to be cancelled.
+    pub fn cancel(&self) -> bool {
+        if !self.armed.load(Ordering::SeqCst) {
+            return false;
+        }
+        self.cancelled.store(true, Ordering::SeqCst);
+        true
+    }
+
+    fn arm(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+        self.armed.store(true, Ordering::SeqCst);
+    }
+
+    fn set_armed(&self, armed: bool) {
+        self.armed.store(armed, Ordering::SeqCst);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
 pub struct Typer {
     enigo: Arc<Mutex<Enigo>>,
     options: TypeOptions,
     modifiers_held: AtomicU64,
     last_type_time: AtomicU64,
     newline_pending: AtomicBool,
+    cancel: Arc<TypingCancel>,
 }
 
 impl Typer {
@@ -99,9 +383,15 @@ impl Typer {
             modifiers_held: AtomicU64::new(0),
             last_type_time: AtomicU64::new(0),
             newline_pending: AtomicBool::new(false),
+            cancel: Arc::new(TypingCancel::default()),
         })
     }
 
+    pub fn with_cancel(mut self, cancel: Arc<TypingCancel>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
     pub fn with_defaults() -> Result<Self, enigo::NewConError> {
         Self::new(TypeOptions::default())
     }
@@ -123,103 +413,84 @@ impl Typer {
         self.modifiers_held.store(0, Ordering::SeqCst);
     }
 
-    fn type_via_keystroke(&self, text: &str) -> Result<(), String> {
+    fn throttle(&self) {
+        if self.options.throttle_ms == 0 {
+            return;
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let last = self.last_type_time.load(Ordering::SeqCst);
+        if now.saturating_sub(last) < self.options.throttle_ms {
+            std::thread::sleep(Duration::from_millis(
+                self.options
+                    .throttle_ms
+                    .saturating_sub(now.saturating_sub(last)),
+            ));
+        }
+        self.last_type_time.store(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            Ordering::SeqCst,
+        );
+    }
+
+    /// Types `text`, checking for cancellation between characters.
+    fn type_via_keystroke(&self, text: &str) -> Result<(), OutputError> {
+        let layout = self.options.layout;
+        let steps = resolve_plan(plan_keystrokes(text, layout), layout)?;
+
         self.release_all_modifiers();
 
+        let mut typed_chars = 0;
         let mut enigo = self.enigo.lock();
-
-        for c in text.chars() {
-            if self.options.throttle_ms > 0 {
-                let now = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis() as u64;
-                let last = self.last_type_time.load(Ordering::SeqCst);
-                if now.saturating_sub(last) < self.options.throttle_ms {
-                    std::thread::sleep(Duration::from_millis(
-                        self.options
-                            .throttle_ms
-                            .saturating_sub(now.saturating_sub(last)),
-                    ));
-                }
-                self.last_type_time.store(
-                    std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis() as u64,
-                    Ordering::SeqCst,
-                );
+        for step in steps {
+            if self.cancel.is_cancelled() {
+                drop(enigo);
+                self.release_all_modifiers();
+                return Err(OutputError::Cancelled {
+                    typed_chars,
+                    typed: TypedOutput::keystrokes(typed_chars),
+                });
             }
-
-            match c {
-                'a'..='z' | 'A'..='Z' | '0'..='9' | ' ' => {
-                    if c.is_uppercase() {
-                        let _ = enigo.key(Key::Shift, Press);
+            match step {
+                KeyStep::Key(key, Press) => {
+                    let _ = enigo.key(key, Press);
+                    if key == Key::Shift {
                         self.modifiers_held.fetch_or(0x01, Ordering::SeqCst);
                     }
-                    let _ = enigo.key(Key::Unicode(c.to_ascii_lowercase()), Click);
-                    if c.is_uppercase() {
-                        let _ = enigo.key(Key::Shift, Release);
+                }
+                KeyStep::Key(key, Release) => {
+                    let _ = enigo.key(key, Release);
+                    if key == Key::Shift {
                         self.modifiers_held.fetch_and(!0x01, Ordering::SeqCst);
                     }
                 }
-                '\n' => {
-                    let _ = enigo.key(Key::Return, Click);
-                }
-                '\t' => {
-                    let _ = enigo.key(Key::Tab, Click);
-                }
-                '.' | ',' | '!' | '?' | ':' | ';' | '\'' | '"' | '-' | '(' | ')' => {
-                    self.type_punctuation(&mut enigo, c)?;
+                KeyStep::Key(key, direction) => {
+                    self.throttle();
+                    let _ = enigo.key(key, direction);
+                    typed_chars += 1;
                 }
-                _ => {
-                    let _ = enigo.key(Key::Unicode(c), Click);
+                KeyStep::Text(c) => {
+                    self.throttle();
+                    let _ = enigo.text(c.encode_utf8(&mut [0; 4]));
+                    typed_chars += 1;
                 }
             }
         }
+        drop(enigo);
 
         self.release_all_modifiers();
         Ok(())
     }
 
-    fn type_punctuation(&self, enigo: &mut Enigo, c: char) -> Result<(), String> {
-        match c {
-            '!' | '?' | ':' | '"' | '(' | ')' => {
-                let _ = enigo.key(Key::Shift, Press);
-                self.modifiers_held.fetch_or(0x01, Ordering::SeqCst);
-            }
-            _ => {}
-        }
-
-        let key = match c {
-            '.' => Key::Unicode('.'),
-            ',' => Key::Unicode(','),
-            '!' => Key::Unicode('1'),
-            '?' => Key::Unicode('?'),
-            ':' => Key::Unicode(':'),
-            ';' => Key::Unicode(';'),
-            '\'' => Key::Unicode('\''),
-            '"' => Key::Unicode('"'),
-            '-' => Key::Unicode('-'),
-            '(' => Key::Unicode('('),
-            ')' => Key::Unicode(')'),
-            _ => Key::Unicode(c),
-        };
-
-        let _ = enigo.key(key, Click);
-
-        if "!?:\"()".contains(c) {
-            let _ = enigo.key(Key::Shift, Release);
-            self.modifiers_held.fetch_and(!0x01, Ordering::SeqCst);
-        }
-
-        Ok(())
-    }
-
     fn type_via_clipboard(&self, text: &str) -> Result<(), String> {
         let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
 
-        let previous = clipboard.get_text().ok();
+        let snapshot = ClipboardSnapshot::capture(&mut clipboard);
 
         clipboard.set_text(text).map_err(|e| e.to_string())?;
 
@@ -236,43 +507,158 @@ impl Typer {
         self.modifiers_held
             .fetch_and(!paste_modifier.bitmask(), Ordering::SeqCst);
 
-        std::thread::sleep(Duration::from_millis(50));
-
         drop(enigo);
 
-        if let Some(prev) = previous {
-            let _ = clipboard.set_text(prev);
+        std::thread::sleep(restore_delay(
+            self.options.clipboard_restore_ms,
+            text.chars().count(),
+        ));
+
+        // Whatever was copied while the paste landed is newer than the
+        // snapshot, so it stays.
+        let current = clipboard.get_text().ok();
+        if current.as_deref() != Some(text) {
+            log::info!("Clipboard changed during paste, not restoring it");
+        } else if let Err(e) = snapshot.restore(&mut clipboard) {
+            log::warn!("Failed to restore clipboard: {}", e);
         }
 
         Ok(())
     }
 
-    pub fn type_text(&self, text: &str) -> Result<(), String> {
-        let context = self.detect_context(text);
+    /// Runs dictation output in order and returns what it left typed, so a
+    /// later "scratch that" knows what to remove. Key presses move the cursor
+    /// somewhere unknown, so they reset that record. `last_dictation` is what
+    /// the previous dictation typed.
+    pub fn run_actions(
+        &self,
+        actions: &[OutputAction],
+        last_dictation: &TypedOutput,
+    ) -> Result<TypedOutput, OutputError> {
+        let context = self.detect_context(&inserted_text(actions));
         if context == ContextHeuristic::PasswordField {
-            return Err("Skipping typing in password field".to_string());
+            return Err(OutputError::Failed(
+                "Skipping typing in password field".to_string(),
+            ));
         }
 
-        let mut text = text.to_string();
+        self.cancel.arm();
+        let result = self.run_armed(actions, last_dictation);
+        self.cancel.set_armed(false);
+        result
+    }
 
-        if self.options.newline_append && !text.ends_with('\n') {
-            text.push('\n');
+    fn run_armed(
+        &self,
+        actions: &[OutputAction],
+        last_dictation: &TypedOutput,
+    ) -> Result<TypedOutput, OutputError> {
+        let mut typed = TypedOutput::default();
+        let mut typed_total = 0;
+        for (index, action) in actions.iter().enumerate() {
+            if self.cancel.is_cancelled() {
+                return Err(OutputError::Cancelled {
+                    typed_chars: typed_total,
+                    typed,
+                });
+            }
+            match action {
+                OutputAction::Text(text) => {
+                    let mut text = text.clone();
+                    let is_last = index + 1 == actions.len();
+                    if self.options.newline_append && is_last && !text.ends_with('\n') {
+                        text.push('\n');
+                    }
+                    match self.insert_text(&text) {
+                        Ok(run) => typed.push(run),
+                        Err(OutputError::Cancelled {
+                            typed_chars,
+                            typed: partial,
+                        }) => {
+                            typed.extend(partial);
+                            return Err(OutputError::Cancelled {
+                                typed_chars: typed_total + typed_chars,
+                                typed,
+                            });
+                        }
+                        Err(e) => return Err(e),
+                    }
+                    typed_total += text.chars().count();
+                }
+                OutputAction::Keys(chords) => {
+                    // A chord such as "press escape" must not cancel the
+                    // dictation that sends it.
+                    self.cancel.set_armed(false);
+                    for chord in chords {
+                        self.press_chord(chord)?;
+                    }
+                    self.cancel.set_armed(true);
+                    typed.clear();
+                }
+                OutputAction::RemoveLastDictation => self.retract(last_dictation)?,
+            }
         }
+        Ok(typed)
+    }
 
+    fn insert_text(&self, text: &str) -> Result<TypedRun, OutputError> {
+        let chars = text.chars().count();
         if self.options.method == TypeMethod::Clipboard {
-            return self.type_via_clipboard(&text);
+            self.type_via_clipboard(text)?;
+            return Ok(TypedRun::Paste(chars));
+        }
+
+        match self.type_via_keystroke(text) {
+            Ok(()) => Ok(TypedRun::Keystrokes(chars)),
+            Err(OutputError::Failed(e)) if self.options.clipboard_fallback => {
+                log::warn!("Keystroke typing failed, pasting instead: {}", e);
+                self.type_via_clipboard(text)?;
+                Ok(TypedRun::Paste(chars))
+            }
+            Err(e) => Err(e),
         }
+    }
 
-        match self.type_via_keystroke(&text) {
-            Ok(()) => Ok(()),
-            Err(e) => {
-                if self.options.clipboard_fallback {
-                    self.type_via_clipboard(&text)
-                } else {
-                    Err(e)
+    /// Removes what `typed` left, newest first. Typed characters are
+    /// backspaced one by one; a paste is undone with the app's undo, since
+    /// it is a single edit and the app may have reformatted what it pasted.
+    fn retract(&self, typed: &TypedOutput) -> Result<(), String> {
+        self.release_all_modifiers();
+        for run in typed.runs.iter().rev() {
+            match *run {
+                TypedRun::Keystrokes(chars) => {
+                    let mut enigo = self.enigo.lock();
+                    for _ in 0..chars {
+                        self.throttle();
+                        let _ = enigo.key(Key::Backspace, Click);
+                    }
                 }
+                TypedRun::Paste(_) => self.press_chord(&KeyChord {
+                    modifiers: vec![ModifierKey::for_clipboard_paste()],
+                    key: NamedKey::Char('z'),
+                })?,
             }
         }
+        Ok(())
+    }
+
+    fn press_chord(&self, chord: &KeyChord) -> Result<(), String> {
+        let key = enigo_key(chord.key)?;
+        self.release_all_modifiers();
+
+        let mut enigo = self.enigo.lock();
+        for modifier in &chord.modifiers {
+            let _ = enigo.key(modifier.to_enigo_key(), Press);
+            self.modifiers_held
+                .fetch_or(modifier.bitmask(), Ordering::SeqCst);
+        }
+        let _ = enigo.key(key, Click);
+        for modifier in chord.modifiers.iter().rev() {
+            let _ = enigo.key(modifier.to_enigo_key(), Release);
+            self.modifiers_held
+                .fetch_and(!modifier.bitmask(), Ordering::SeqCst);
+        }
+        Ok(())
     }
 
     pub fn set_options(&mut self, options: TypeOptions) {
@@ -476,6 +862,8 @@ mod tests {
         assert!(options.clipboard_fallback);
         assert!(options.detect_code_context);
         assert!(options.detect_password_fields);
+        assert_eq!(options.layout, KeyboardLayout::Us);
+        assert_eq!(options.clipboard_restore_ms, 250);
     }
 
     #[test]
@@ -487,11 +875,14 @@ mod tests {
             clipboard_fallback: false,
             detect_code_context: false,
             detect_password_fields: false,
+            layout: KeyboardLayout::De,
+            clipboard_restore_ms: 500,
         };
         assert_eq!(options.method, TypeMethod::Clipboard);
         assert_eq!(options.throttle_ms, 10);
         assert!(options.newline_append);
         assert!(!options.clipboard_fallback);
+        assert_eq!(options.layout, KeyboardLayout::De);
     }
 
     #[test]
@@ -548,6 +939,141 @@ mod tests {
         assert_eq!(options_default.throttle_ms, 0);
     }
 
+    #[test]
+    fn test_typing_cancel_only_while_armed() {
+        let cancel = TypingCancel::default();
+        assert!(!cancel.cancel());
+        assert!(!cancel.is_cancelled());
+
+        cancel.arm();
+        assert!(cancel.cancel());
+        assert!(cancel.is_cancelled());
+
+        // The next dictation starts uncancelled.
+        cancel.set_armed(false);
+        cancel.arm();
+        assert!(!cancel.is_cancelled());
+    }
+
+    #[test]
+    fn test_resolved_plan_never_holds_shift_around_unicode() {
+        let layouts = [
+            KeyboardLayout::Us,
+            KeyboardLayout::Uk,
+            KeyboardLayout::De,
+            KeyboardLayout::Fr,
+            KeyboardLayout::Dvorak,
+        ];
+        for layout in layouts {
+            let steps = resolve_plan(plan_keystrokes("Hi! \"OK\"? é@", layout), layout).unwrap();
+            let mut shift_held = false;
+            for step in &steps {
+                match step {
+                    KeyStep::Key(Key::Shift, direction) => {
+                        shift_held = matches!(direction, Press);
+                    }
+                    KeyStep::Key(Key::Unicode(c), _) | KeyStep::Text(c) => {
+                        assert!(!shift_held, "Shift held around {c:?} on {layout:?}");
+                    }
+                    _ => {}
+                }
+            }
+            assert!(!shift_held);
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_shifted_positions_send_the_shifted_character() {
+        let us = KeyboardLayout::Us;
+        let steps = resolve_plan(plan_keystrokes("!a", us), us).unwrap();
+        assert!(matches!(
+            steps.as_slice(),
+            [
+                KeyStep::Key(Key::Unicode('!'), Click),
+                KeyStep::Key(Key::Unicode('a'), Click)
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_typed_output_merges_keystroke_runs() {
+        let mut typed = TypedOutput::keystrokes(5);
+        typed.push(TypedRun::Keystrokes(3));
+        typed.push(TypedRun::Paste(0));
+        typed.push(TypedRun::Paste(10));
+        typed.push(TypedRun::Keystrokes(2));
+        assert_eq!(
+            typed.runs,
+            vec![
+                TypedRun::Keystrokes(8),
+                TypedRun::Paste(10),
+                TypedRun::Keystrokes(2)
+            ]
+        );
+        assert_eq!(typed.chars(), 20);
+
+        typed.clear();
+        assert!(typed.is_empty());
+        assert!(TypedOutput::keystrokes(0).is_empty());
+    }
+
+    #[test]
+    fn test_restore_delay_grows_with_paste_length() {
+        assert_eq!(restore_delay(250, 10), Duration::from_millis(250));
+        assert_eq!(restore_delay(250, 2_000), Duration::from_millis(350));
+        assert_eq!(restore_delay(250, 1_000_000), Duration::from_millis(1000));
+        assert_eq!(restore_delay(0, 5_000), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_clipboard_restore_writes_richest_format() {
+        let html = ClipboardSnapshot {
+            text: Some("plain".to_string()),
+            html: Some("<b>plain</b>".to_string()),
+            ..ClipboardSnapshot::default()
+        };
+        assert!(matches!(
+            html.restore_write(),
+            ClipboardWrite::Html {
+                html: "<b>plain</b>",
+                alt_text: Some("plain"),
+            }
+        ));
+
+        // arboard can't write an image or files together with text, so the
+        // text copied alongside them is not restored.
+        let image = ClipboardSnapshot {
+            text: Some("caption".to_string()),
+            image: Some(ImageData {
+                width: 1,
+                height: 1,
+                bytes: vec![0; 4].into(),
+            }),
+            ..ClipboardSnapshot::default()
+        };
+        assert!(matches!(image.restore_write(), ClipboardWrite::Image(_)));
+        let files = ClipboardSnapshot {
+            text: Some("notes.txt".to_string()),
+            files: Some(vec![PathBuf::from("notes.txt")]),
+            ..ClipboardSnapshot::default()
+        };
+        assert!(matches!(files.restore_write(), ClipboardWrite::Files([_])));
+
+        let text = ClipboardSnapshot {
+            text: Some("plain".to_string()),
+            ..ClipboardSnapshot::default()
+        };
+        assert!(matches!(
+            text.restore_write(),
+            ClipboardWrite::Text("plain")
+        ));
+        assert!(matches!(
+            ClipboardSnapshot::default().restore_write(),
+            ClipboardWrite::Clear
+        ));
+    }
+
     #[test]
     fn test_type_options_all_keystroke() {
         let options = TypeOptions {
@@ -557,6 +1083,8 @@ mod tests {
             clipboard_fallback: true,
             detect_code_context: true,
             detect_password_fields: true,
+            layout: KeyboardLayout::Us,
+            clipboard_restore_ms: 250,
         };
         assert_eq!(options.method, TypeMethod::Keystroke);
         assert_eq!(options.throttle_ms, 50);
@@ -573,6 +1101,8 @@ mod tests {
             clipboard_fallback: false,
             detect_code_context: false,
             detect_password_fields: false,
+            layout: KeyboardLayout::Fr,
+            clipboard_restore_ms: 0,
         };
         assert_eq!(options.method, TypeMethod::Clipboard);
         assert_eq!(options.throttle_ms, 0);
@@ -699,4 +1229,25 @@ mod tests {
         assert_eq!(ModifierKey::Shift.bitmask(), 0x01);
         assert_eq!(ModifierKey::Alt.bitmask(), 0x04);
     }
+
+    #[test]
+    fn test_key_chords_map_to_enigo_keys() {
+        let chords =
+            crate::voice_commands::parse_key_sequence("enter alt+backspace f5 cmd+a").unwrap();
+        let keys: Vec<Key> = chords
+            .iter()
+            .map(|chord| enigo_key(chord.key).unwrap())
+            .collect();
+        assert_eq!(
+            keys,
+            vec![Key::Return, Key::Backspace, Key::F5, Key::Unicode('a')]
+        );
+    }
+
+    #[test]
+    fn test_unknown_function_key_is_an_error() {
+        assert_eq!(enigo_key(NamedKey::Function(12)), Ok(Key::F12));
+        assert!(enigo_key(NamedKey::Function(13)).is_err());
+        assert!(enigo_key(NamedKey::Function(0)).is_err());
+    }
 }
@@ -0,0 +1,602 @@
+use crate::type_::ModifierKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum VoiceCommandError {
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid voice command: {0}")]
+    Invalid(String),
+    #[error("\"{0}\" is used by more than one voice command")]
+    Conflict(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuiltinCommand {
+    Newline,
+    NewParagraph,
+    Tab,
+    Period,
+    Comma,
+    Colon,
+    Semicolon,
+    OpenQuote,
+    CloseQuote,
+    Backtick,
+    CodeBlock,
+    /// Removes the text dictated just before it.
+    ScratchThat,
+}
+
+/// What saying a command does: type literal text, press keys (e.g.
+/// `"cmd+shift+z"`, `"enter enter"`), or run a built-in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum CommandAction {
+    Text(String),
+    Keys(String),
+    Builtin(BuiltinCommand),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoiceCommand {
+    pub phrases: Vec<String>,
+    pub action: CommandAction,
+}
+
+impl VoiceCommand {
+    pub fn new(phrase: &str, action: CommandAction) -> Self {
+        Self {
+            phrases: vec![phrase.to_string()],
+            action,
+        }
+    }
+}
+
+pub fn default_commands() -> Vec<VoiceCommand> {
+    VoiceCommandMap::default().to_commands()
+}
+
+fn editing_commands() -> Vec<VoiceCommand> {
+    vec![
+        VoiceCommand::new(
+            "scratch that",
+            CommandAction::Builtin(BuiltinCommand::ScratchThat),
+        ),
+        VoiceCommand::new("select all", CommandAction::Keys("cmd+a".to_string())),
+        VoiceCommand::new(
+            "delete word",
+            CommandAction::Keys("alt+backspace".to_string()),
+        ),
+        VoiceCommand::new("undo", CommandAction::Keys("cmd+z".to_string())),
+        VoiceCommand::new("press enter", CommandAction::Keys("enter".to_string())),
+    ]
+}
+
+/// What a dictation does once voice commands are applied, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputAction {
+    Text(String),
+    Keys(Vec<KeyChord>),
+    /// Removes the text the previous dictation typed.
+    RemoveLastDictation,
+}
+
+/// The text `actions` insert, ignoring key presses.
+pub fn inserted_text(actions: &[OutputAction]) -> String {
+    actions
+        .iter()
+        .filter_map(|action| match action {
+            OutputAction::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The fixed phrase table configs used before commands became a list. Only
+/// read to migrate those configs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct VoiceCommandMap {
+    pub newline: String,
+    pub new_paragraph: String,
+    pub tab: String,
+    pub period: String,
+    pub comma: String,
+    pub colon: String,
+    pub semicolon: String,
+    pub open_quote: String,
+    pub close_quote: String,
+    pub backtick: String,
+    pub code_block: String,
+}
+
+impl Default for VoiceCommandMap {
+    fn default() -> Self {
+        Self {
+            newline: "newline".to_string(),
+            new_paragraph: "new paragraph".to_string(),
+            tab: "tab".to_string(),
+            period: "period".to_string(),
+            comma: "comma".to_string(),
+            colon: "colon".to_string(),
+            semicolon: "semicolon".to_string(),
+            open_quote: "open quote".to_string(),
+            close_quote: "close quote".to_string(),
+            backtick: "backtick".to_string(),
+            code_block: "code block".to_string(),
+        }
+    }
+}
+
+impl VoiceCommandMap {
+    /// The mapped phrases as commands, followed by the editing commands that
+    /// came after the map and don't clash with it.
+    pub fn to_commands(&self) -> Vec<VoiceCommand> {
+        let mut commands: Vec<VoiceCommand> = [
+            (&self.newline, BuiltinCommand::Newline),
+            (&self.new_paragraph, BuiltinCommand::NewParagraph),
+            (&self.tab, BuiltinCommand::Tab),
+            (&self.period, BuiltinCommand::Period),
+            (&self.comma, BuiltinCommand::Comma),
+            (&self.colon, BuiltinCommand::Colon),
+            (&self.semicolon, BuiltinCommand::Semicolon),
+            (&self.open_quote, BuiltinCommand::OpenQuote),
+            (&self.close_quote, BuiltinCommand::CloseQuote),
+            (&self.backtick, BuiltinCommand::Backtick),
+            (&self.code_block, BuiltinCommand::CodeBlock),
+        ]
+        .into_iter()
+        .filter(|(phrase, _)| !phrase.trim().is_empty())
+        .map(|(phrase, builtin)| VoiceCommand::new(phrase, CommandAction::Builtin(builtin)))
+        .collect();
+
+        let mapped: Vec<Vec<String>> = commands
+            .iter()
+            .flat_map(|command| command.phrases.iter().map(|phrase| phrase_words(phrase)))
+            .collect();
+        commands.extend(editing_commands().into_iter().filter(|command| {
+            !command
+                .phrases
+                .iter()
+                .any(|phrase| mapped.contains(&phrase_words(phrase)))
+        }));
+        commands
+    }
+}
+
+/// Lowercase words of a spoken phrase, with the punctuation whisper puts
+/// around words stripped so "Comma," matches "comma".
+pub fn phrase_words(phrase: &str) -> Vec<String> {
+    phrase
+        .split_whitespace()
+        .map(word_core)
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+pub fn word_core(word: &str) -> String {
+    word.trim_matches(is_edge_punctuation).to_lowercase()
+}
+
+fn is_edge_punctuation(c: char) -> bool {
+    matches!(
+        c,
+        '.' | ',' | '!' | '?' | ';' | ':' | '"' | '\'' | '“' | '”' | '‘' | '’' | '…' | '¿' | '¡'
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandConflict {
+    pub phrase: String,
+    /// Indexes into the command list of every command using `phrase`.
+    pub commands: Vec<usize>,
+}
+
+/// Phrases claimed by more than one command, compared the way they are
+/// matched (case and surrounding punctuation ignored).
+pub fn find_conflicts(commands: &[VoiceCommand]) -> Vec<CommandConflict> {
+    let mut owners: Vec<(String, Vec<usize>)> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for (index, command) in commands.iter().enumerate() {
+        for phrase in &command.phrases {
+            let key = phrase_words(phrase).join(" ");
+            if key.is_empty() {
+                continue;
+            }
+            let position = *positions.entry(key.clone()).or_insert_with(|| {
+                owners.push((key, Vec::new()));
+                owners.len() - 1
+            });
+            let indexes = &mut owners[position].1;
+            if indexes.last() != Some(&index) {
+                indexes.push(index);
+            }
+        }
+    }
+
+    owners
+        .into_iter()
+        .filter(|(_, indexes)| indexes.len() > 1)
+        .map(|(phrase, commands)| CommandConflict { phrase, commands })
+        .collect()
+}
+
+pub fn validate_commands(commands: &[VoiceCommand]) -> Result<(), VoiceCommandError> {
+    for command in commands {
+        if command.phrases.is_empty()
+            || command
+                .phrases
+                .iter()
+                .any(|phrase| phrase_words(phrase).is_empty())
+        {
+            return Err(VoiceCommandError::Invalid(
+                "every voice command needs a non-empty phrase".to_string(),
+            ));
+        }
+        match &command.action {
+            CommandAction::Text(text) if text.is_empty() => {
+                return Err(VoiceCommandError::Invalid(format!(
+                    "\"{}\" has no text to insert",
+                    command.phrases[0]
+                )));
+            }
+            CommandAction::Keys(keys) => {
+                parse_key_sequence(keys).map_err(|e| {
+                    VoiceCommandError::Invalid(format!("\"{}\": {}", command.phrases[0], e))
+                })?;
+            }
+            _ => {}
+        }
+    }
+
+    match find_conflicts(commands).into_iter().next() {
+        Some(conflict) => Err(VoiceCommandError::Conflict(conflict.phrase)),
+        None => Ok(()),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CommandExport {
+    version: u32,
+    commands: Vec<VoiceCommand>,
+}
+
+pub fn export_commands(commands: &[VoiceCommand]) -> Result<String, VoiceCommandError> {
+    Ok(serde_json::to_string_pretty(&CommandExport {
+        version: EXPORT_VERSION,
+        commands: commands.to_vec(),
+    })?)
+}
+
+/// Parses an export (or a bare command array) and checks it on its own.
+pub fn import_commands(json: &str) -> Result<Vec<VoiceCommand>, VoiceCommandError> {
+    let commands = match serde_json::from_str::<CommandExport>(json) {
+        Ok(export) if export.version > EXPORT_VERSION => {
+            return Err(VoiceCommandError::Invalid(format!(
+                "export version {} is newer than this app supports",
+                export.version
+            )))
+        }
+        Ok(export) => export.commands,
+        Err(_) => serde_json::from_str::<Vec<VoiceCommand>>(json)?,
+    };
+    validate_commands(&commands)?;
+    Ok(commands)
+}
+
+/// Adds `imported` to `existing`. Imported commands take over any phrase an
+/// existing command also uses; existing commands left without phrases are
+/// dropped.
+pub fn merge_commands(existing: &[VoiceCommand], imported: Vec<VoiceCommand>) -> Vec<VoiceCommand> {
+    let taken: Vec<Vec<String>> = imported
+        .iter()
+        .flat_map(|command| command.phrases.iter().map(|phrase| phrase_words(phrase)))
+        .collect();
+
+    let mut merged: Vec<VoiceCommand> = existing
+        .iter()
+        .filter_map(|command| {
+            let phrases: Vec<String> = command
+                .phrases
+                .iter()
+                .filter(|phrase| !taken.contains(&phrase_words(phrase)))
+                .cloned()
+                .collect();
+            (!phrases.is_empty()).then(|| VoiceCommand {
+                phrases,
+                action: command.action.clone(),
+            })
+        })
+        .collect();
+    merged.extend(imported);
+    merged
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedKey {
+    Enter,
+    Tab,
+    Space,
+    Backspace,
+    Delete,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Function(u8),
+    Char(char),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChord {
+    pub modifiers: Vec<ModifierKey>,
+    pub key: NamedKey,
+}
+
+impl KeyChord {
+    /// The character this chord types, for chords that only type text.
+    pub fn text(&self) -> Option<char> {
+        if !self.modifiers.is_empty() {
+            return None;
+        }
+        match self.key {
+            NamedKey::Enter => Some('\n'),
+            NamedKey::Tab => Some('\t'),
+            NamedKey::Space => Some(' '),
+            NamedKey::Char(c) => Some(c),
+            _ => None,
+        }
+    }
+}
+
+/// Parses whitespace-separated chords such as `"cmd+shift+z"` or
+/// `"enter enter"`.
+pub fn parse_key_sequence(sequence: &str) -> Result<Vec<KeyChord>, String> {
+    let chords: Vec<KeyChord> = sequence
+        .split_whitespace()
+        .map(parse_key_chord)
+        .collect::<Result<_, _>>()?;
+    if chords.is_empty() {
+        return Err("key sequence is empty".to_string());
+    }
+    Ok(chords)
+}
+
+fn parse_key_chord(chord: &str) -> Result<KeyChord, String> {
+    let lowercase = chord.to_lowercase();
+    let mut parts: Vec<&str> = lowercase.split('+').collect();
+    let key = match parts.pop() {
+        // "ctrl++" presses the plus key.
+        Some("") if lowercase.ends_with("++") => {
+            parts.pop();
+            "+"
+        }
+        Some(key) => key,
+        None => unreachable!("split always yields at least one part"),
+    };
+
+    let mut modifiers = Vec::new();
+    for part in parts {
+        let modifier = match part {
+            "cmd" | "command" | "meta" | "super" | "win" => ModifierKey::Meta,
+            "ctrl" | "control" => ModifierKey::Control,
+            "alt" | "option" | "opt" => ModifierKey::Alt,
+            "shift" => ModifierKey::Shift,
+            other => return Err(format!("unknown modifier \"{}\" in \"{}\"", other, chord)),
+        };
+        if !modifiers.contains(&modifier) {
+            modifiers.push(modifier);
+        }
+    }
+
+    let key = match key {
+        "enter" | "return" => NamedKey::Enter,
+        "tab" => NamedKey::Tab,
+        "space" => NamedKey::Space,
+        "backspace" => NamedKey::Backspace,
+        "delete" | "del" => NamedKey::Delete,
+        "escape" | "esc" => NamedKey::Escape,
+        "up" => NamedKey::Up,
+        "down" => NamedKey::Down,
+        "left" => NamedKey::Left,
+        "right" => NamedKey::Right,
+        "home" => NamedKey::Home,
+        "end" => NamedKey::End,
+        "pageup" => NamedKey::PageUp,
+        "pagedown" => NamedKey::PageDown,
+        _ => {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => NamedKey::Char(c),
+                (Some('f'), Some(_)) => match key[1..].parse::<u8>() {
+                    Ok(n @ 1..=12) => NamedKey::Function(n),
+                    _ => return Err(format!("unknown key \"{}\" in \"{}\"", key, chord)),
+                },
+                _ => return Err(format!("unknown key \"{}\" in \"{}\"", key, chord)),
+            }
+        }
+    };
+
+    Ok(KeyChord { modifiers, key })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_command(phrases: &[&str], text: &str) -> VoiceCommand {
+        VoiceCommand {
+            phrases: phrases.iter().map(|p| p.to_string()).collect(),
+            action: CommandAction::Text(text.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_defaults_are_the_old_builtin_phrases_plus_editing() {
+        let commands = default_commands();
+        assert_eq!(commands.len(), 16);
+        assert_eq!(
+            commands[1],
+            VoiceCommand::new(
+                "new paragraph",
+                CommandAction::Builtin(BuiltinCommand::NewParagraph)
+            )
+        );
+        assert_eq!(
+            commands[11],
+            VoiceCommand::new(
+                "scratch that",
+                CommandAction::Builtin(BuiltinCommand::ScratchThat)
+            )
+        );
+        assert!(validate_commands(&commands).is_ok());
+    }
+
+    #[test]
+    fn test_legacy_maps_skip_editing_phrases_they_already_use() {
+        let map = VoiceCommandMap {
+            newline: "Undo".to_string(),
+            ..Default::default()
+        };
+        let commands = map.to_commands();
+        assert_eq!(commands.len(), 15);
+        assert!(find_conflicts(&commands).is_empty());
+    }
+
+    #[test]
+    fn test_inserted_text_skips_key_presses() {
+        let actions = vec![
+            OutputAction::Text("hello".to_string()),
+            OutputAction::Keys(parse_key_sequence("enter").unwrap()),
+            OutputAction::RemoveLastDictation,
+            OutputAction::Text(" world".to_string()),
+        ];
+        assert_eq!(inserted_text(&actions), "hello world");
+    }
+
+    #[test]
+    fn test_action_serialization_is_tagged() {
+        let command = text_command(&["arrow"], "->");
+        let json = serde_json::to_value(&command).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "phrases": ["arrow"], "action": { "type": "text", "value": "->" } })
+        );
+
+        let builtin: CommandAction =
+            serde_json::from_str(r#"{ "type": "builtin", "value": "open_quote" }"#).unwrap();
+        assert_eq!(builtin, CommandAction::Builtin(BuiltinCommand::OpenQuote));
+    }
+
+    #[test]
+    fn test_conflicts_ignore_case_and_punctuation() {
+        let commands = vec![
+            text_command(&["arrow", "fat arrow"], "->"),
+            text_command(&["Fat Arrow,"], "=>"),
+            text_command(&["pipe"], "|"),
+            text_command(&["pipe"], "|"),
+        ];
+        assert_eq!(
+            find_conflicts(&commands),
+            vec![
+                CommandConflict {
+                    phrase: "fat arrow".to_string(),
+                    commands: vec![0, 1],
+                },
+                CommandConflict {
+                    phrase: "pipe".to_string(),
+                    commands: vec![2, 3],
+                },
+            ]
+        );
+        assert!(matches!(
+            validate_commands(&commands),
+            Err(VoiceCommandError::Conflict(phrase)) if phrase == "fat arrow"
+        ));
+    }
+
+    #[test]
+    fn test_validation_rejects_empty_phrases_text_and_bad_keys() {
+        assert!(validate_commands(&[text_command(&[], "x")]).is_err());
+        assert!(validate_commands(&[text_command(&["..."], "x")]).is_err());
+        assert!(validate_commands(&[text_command(&["blank"], "")]).is_err());
+        let bad_keys = VoiceCommand::new("redo", CommandAction::Keys("hyper+z".to_string()));
+        assert!(validate_commands(&[bad_keys]).is_err());
+    }
+
+    #[test]
+    fn test_export_round_trips_and_import_accepts_bare_lists() {
+        let commands = vec![
+            text_command(&["arrow"], "->"),
+            VoiceCommand::new("redo", CommandAction::Keys("cmd+shift+z".to_string())),
+        ];
+        let exported = export_commands(&commands).unwrap();
+        assert_eq!(import_commands(&exported).unwrap(), commands);
+
+        let bare = serde_json::to_string(&commands).unwrap();
+        assert_eq!(import_commands(&bare).unwrap(), commands);
+
+        assert!(import_commands(r#"{ "version": 99, "commands": [] }"#).is_err());
+        assert!(import_commands("not json").is_err());
+    }
+
+    #[test]
+    fn test_merge_lets_imported_commands_take_over_phrases() {
+        let existing = vec![
+            text_command(&["arrow", "right arrow"], "->"),
+            text_command(&["pipe"], "|"),
+        ];
+        let imported = vec![text_command(&["Arrow"], "→")];
+
+        let merged = merge_commands(&existing, imported.clone());
+        assert_eq!(
+            merged,
+            vec![
+                text_command(&["right arrow"], "->"),
+                text_command(&["pipe"], "|"),
+                imported[0].clone(),
+            ]
+        );
+        assert!(find_conflicts(&merged).is_empty());
+    }
+
+    #[test]
+    fn test_parses_key_sequences() {
+        assert_eq!(
+            parse_key_sequence("Cmd+Shift+Z").unwrap(),
+            vec![KeyChord {
+                modifiers: vec![ModifierKey::Meta, ModifierKey::Shift],
+                key: NamedKey::Char('z'),
+            }]
+        );
+
+        let enters = parse_key_sequence("enter  return").unwrap();
+        assert_eq!(enters.len(), 2);
+        assert!(enters.iter().all(|chord| chord.text() == Some('\n')));
+
+        assert_eq!(
+            parse_key_sequence("ctrl++").unwrap()[0].key,
+            NamedKey::Char('+')
+        );
+        assert_eq!(
+            parse_key_sequence("f12").unwrap()[0].key,
+            NamedKey::Function(12)
+        );
+        assert_eq!(parse_key_sequence("alt+left").unwrap()[0].text(), None);
+
+        for invalid in ["", "f13", "hyper+a", "ctrl+banana"] {
+            assert!(parse_key_sequence(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use crate::audio::{compute_rms, TARGET_SAMPLE_RATE};
+use crate::stt::TranscriptionResult;
+use std::ops::Range;
+
+/// Whisper decodes 30 s at a time; staying a little under leaves room for the
+/// overlap and avoids the model padding a nearly-full window.
+const MAX_WINDOW_SECONDS: f32 = 28.0;
+const MIN_WINDOW_SECONDS: f32 = 20.0;
+const OVERLAP_SECONDS: f32 = 1.0;
+const FRAME_SECONDS: f32 = 0.1;
+/// Roughly the tail whisper can use as a prompt without crowding out the window.
+const PROMPT_CHARS: usize = 200;
+const MAX_OVERLAP_WORDS: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TranscriptionProgress {
+    pub completed_windows: usize,
+    pub total_windows: usize,
+}
+
+fn seconds_to_samples(seconds: f32) -> usize {
+    (seconds * TARGET_SAMPLE_RATE as f32) as usize
+}
+
+fn samples_to_ms(samples: usize) -> i32 {
+    (samples as u64 * 1000 / TARGET_SAMPLE_RATE as u64) as i32
+}
+
+/// Splits audio into windows of at most `MAX_WINDOW_SECONDS`, cutting at the
+/// quietest point after `MIN_WINDOW_SECONDS`. Each window after the first
+/// starts `OVERLAP_SECONDS` before the previous cut so words straddling the
+/// cut are heard in full at least once.
+pub fn plan_windows(audio_data: &[f32]) -> Vec<Range<usize>> {
+    let max_window = seconds_to_samples(MAX_WINDOW_SECONDS);
+    let min_window = seconds_to_samples(MIN_WINDOW_SECONDS);
+    let overlap = seconds_to_samples(OVERLAP_SECONDS);
+
+    let mut windows = Vec::new();
+    let mut start = 0;
+    while audio_data.len() - start > max_window {
+        let cut = quietest_point(audio_data, start + min_window, start + max_window);
+        windows.push(start..cut);
+        start = cut - overlap;
+    }
+    windows.push(start..audio_data.len());
+    windows
+}
+
+/// Returns the middle of the quietest frame in `from..to`, preferring later
+/// frames on ties so windows stay long.
+fn quietest_point(audio_data: &[f32], from: usize, to: usize) -> usize {
+    let frame = seconds_to_samples(FRAME_SECONDS);
+    let step = frame / 2;
+
+    let mut best = (f32::MAX, to);
+    let mut frame_start = from;
+    while frame_start + frame <= to {
+        let rms = compute_rms(&audio_data[frame_start..frame_start + frame]);
+        if rms <= best.0 {
+            best = (rms, frame_start + frame / 2);
+        }
+        frame_start += step;
+    }
+    best.1
+}
+
+/// The end of the text decoded so far, trimmed to whole words, for use as the
+/// next window's prompt.
+pub fn prompt_tail(text: &str) -> &str {
+    let text = text.trim();
+    if text.chars().count() <= PROMPT_CHARS {
+        return text;
+    }
+
+    let start = text
+        .char_indices()
+        .rev()
+        .nth(PROMPT_CHARS - 1)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let tail = &text[start..];
+    match tail.find(char::is_whitespace) {
+        Some(space) => tail[space..].trim_start(),
+        None => tail,
+    }
+}
+
+fn word_key(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Number of leading words of `next` that repeat the end of `previous`. Also
+/// tolerates one clipped word at the start of `next`, which whisper often
+/// produces for a word cut in half by the window start.
+fn overlapping_words(previous: &str, next: &str) -> usize {
+    let previous: Vec<String> = previous.split_whitespace().map(word_key).collect();
+    let next: Vec<String> = next.split_whitespace().map(word_key).collect();
+
+    for (skip, min_len) in [(0, 1), (1, 2)] {
+        let available = next.len().saturating_sub(skip);
+        let longest = MAX_OVERLAP_WORDS.min(previous.len()).min(available);
+        for len in (min_len..=longest).rev() {
+            let tail = &previous[previous.len() - len..];
+            let head = &next[skip..skip + len];
+            if tail == head && head.iter().any(|key| !key.is_empty()) {
+                return skip + len;
+            }
+        }
+    }
+    0
+}
+
+/// Appends one window's result to the running transcription, dropping words
+/// the overlap made whisper transcribe twice and shifting segment times by
+/// the window's start.
+pub fn merge_window(
+    merged: &mut TranscriptionResult,
+    mut window: TranscriptionResult,
+    window_start: usize,
+) {
+    let offset_ms = samples_to_ms(window_start);
+    let mut skip = overlapping_words(&merged.text, &window.text);
+    if skip > 0 {
+        log::debug!(
+            "Dropping {} overlapping word(s) at {} ms: {:?}",
+            skip,
+            offset_ms,
+            window
+                .text
+                .split_whitespace()
+                .take(skip)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    let text: Vec<&str> = window.text.split_whitespace().skip(skip).collect();
+    if !text.is_empty() {
+        if !merged.text.is_empty() {
+            merged.text.push(' ');
+        }
+        merged.text.push_str(&text.join(" "));
+    }
+
+    for mut segment in window.segments.drain(..) {
+        let words = segment.text.split_whitespace().count();
+        if skip >= words {
+            skip -= words;
+            continue;
+        }
+        if skip > 0 {
+            segment.text = segment
+                .text
+                .split_whitespace()
+                .skip(skip)
+                .collect::<Vec<_>>()
+                .join(" ");
+            skip = 0;
+        }
+        segment.start_ms += offset_ms;
+        segment.end_ms += offset_ms;
+        merged.segments.push(segment);
+    }
+
+    if merged.language.is_none() {
+        merged.language = window.language;
+        merged.language_probability = window.language_probability;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stt::TextSegment;
+
+    fn result(text: &str) -> TranscriptionResult {
+        TranscriptionResult {
+            text: text.to_string(),
+            segments: vec![TextSegment {
+                text: text.to_string(),
+                start_ms: 0,
+                end_ms: 2000,
+                no_speech_prob: 0.0,
+                speaker: None,
+            }],
+            language: None,
+            language_probability: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            actions: Vec::new(),
+        }
+    }
+
+    fn empty() -> TranscriptionResult {
+        TranscriptionResult {
+            text: String::new(),
+            segments: Vec::new(),
+            language: None,
+            language_probability: None,
+            original_text: None,
+            translated_text: None,
+            unfiltered_text: None,
+            actions: Vec::new(),
+        }
+    }
+
+    fn tone(seconds: f32) -> Vec<f32> {
+        (0..seconds_to_samples(seconds))
+            .map(|i| (i as f32 * 0.05).sin() * 0.3)
+            .collect()
+    }
+
+    fn silence(seconds: f32) -> Vec<f32> {
+        vec![0.0; seconds_to_samples(seconds)]
+    }
+
+    #[test]
+    fn test_short_audio_is_a_single_window() {
+        let audio = tone(12.0);
+        assert_eq!(plan_windows(&audio), vec![0..audio.len()]);
+        assert_eq!(plan_windows(&[]), vec![0..0]);
+    }
+
+    #[test]
+    fn test_long_audio_is_cut_at_silence() {
+        let mut audio = tone(23.0);
+        audio.extend(silence(0.5));
+        audio.extend(tone(20.0));
+
+        let windows = plan_windows(&audio);
+        assert_eq!(windows.len(), 2);
+
+        let cut = windows[0].end;
+        let silence_range = seconds_to_samples(23.0)..seconds_to_samples(23.5);
+        assert!(silence_range.contains(&cut), "cut at {}", cut);
+        assert_eq!(windows[1].start, cut - seconds_to_samples(OVERLAP_SECONDS));
+        assert_eq!(windows[1].end, audio.len());
+    }
+
+    #[test]
+    fn test_windows_cover_audio_and_stay_within_limits() {
+        let audio = tone(300.0);
+        let windows = plan_windows(&audio);
+
+        assert_eq!(windows.first().unwrap().start, 0);
+        assert_eq!(windows.last().unwrap().end, audio.len());
+        for pair in windows.windows(2) {
+            assert!(pair[1].start < pair[0].end, "windows must overlap");
+        }
+        for window in &windows {
+            assert!(window.len() <= seconds_to_samples(MAX_WINDOW_SECONDS));
+        }
+    }
+
+    #[test]
+    fn test_prompt_tail_keeps_whole_words() {
+        assert_eq!(prompt_tail("  short text "), "short text");
+
+        let long = "word ".repeat(100);
+        let tail = prompt_tail(&long);
+        assert!(tail.len() <= PROMPT_CHARS);
+        assert!(tail.starts_with("word"));
+        assert!(tail.ends_with("word"));
+    }
+
+    #[test]
+    fn test_merge_drops_duplicated_overlap_words() {
+        let mut merged = empty();
+        merge_window(&mut merged, result(" We should ship the release"), 0);
+        merge_window(
+            &mut merged,
+            result(" ship the release on Friday."),
+            seconds_to_samples(20.0),
+        );
+        assert_eq!(merged.text, "We should ship the release on Friday.");
+    }
+
+    #[test]
+    fn test_merge_ignores_case_and_punctuation_in_overlap() {
+        let mut merged = empty();
+        merge_window(&mut merged, result("Call me tomorrow."), 0);
+        merge_window(&mut merged, result("Tomorrow, after lunch."), 16_000);
+        assert_eq!(merged.text, "Call me tomorrow. after lunch.");
+    }
+
+    #[test]
+    fn test_merge_skips_clipped_first_word() {
+        let mut merged = empty();
+        merge_window(&mut merged, result("the quick brown fox"), 0);
+        merge_window(&mut merged, result("own brown fox jumps"), 16_000);
+        assert_eq!(merged.text, "the quick brown fox jumps");
+    }
+
+    #[test]
+    fn test_merge_without_overlap_appends() {
+        let mut merged = empty();
+        merge_window(&mut merged, result("first part"), 0);
+        merge_window(&mut merged, result("second part"), 16_000);
+        assert_eq!(merged.text, "first part second part");
+    }
+
+    #[test]
+    fn test_merge_offsets_and_trims_segments() {
+        let mut merged = empty();
+        merge_window(&mut merged, result("alpha beta"), 0);
+        merge_window(&mut merged, result("beta gamma"), seconds_to_samples(25.0));
+
+        assert_eq!(merged.segments.len(), 2);
+        assert_eq!(merged.segments[1].text, "gamma");
+        assert_eq!(merged.segments[1].start_ms, 25_000);
+        assert_eq!(merged.segments[1].end_ms, 27_000);
+    }
+
+    #[test]
+    fn test_merge_keeps_first_detected_language() {
+        let mut merged = empty();
+        let mut first = result("hola");
+        first.language = Some("es".to_string());
+        first.language_probability = Some(0.9);
+        let mut second = result("amigos");
+        second.language = Some("pt".to_string());
+
+        merge_window(&mut merged, first, 0);
+        merge_window(&mut merged, second, 16_000);
+        assert_eq!(merged.language.as_deref(), Some("es"));
+        assert_eq!(merged.language_probability, Some(0.9));
+    }
+}
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 export type SessionMode = "hold" | "toggle" | "record";
 
@@ -19,6 +20,7 @@ export interface Session {
 	language: string | null;
 	mode: SessionMode;
 	model_profile: string;
+	profile: string | null;
 	started_at: number;
 	translated: boolean;
 	words_count: number;
@@ -30,6 +32,7 @@ export interface SessionCreate {
 	language: string | null;
 	mode: SessionMode;
 	model_profile: string;
+	profile?: string | null;
 	started_at: number;
 	translated: boolean;
 }
@@ -37,73 +40,224 @@ export interface SessionCreate {
 export interface Entry {
 	ended_at: number;
 	id: string;
+	language: string | null;
+	language_probability: number | null;
+	original_text: string | null;
+	retracted: boolean;
 	session_id: string;
 	source: SessionMode;
+	speaker: string | null;
 	started_at: number;
 	text: string;
+	translated_text: string | null;
 	typed: boolean;
+	typed_chars: number | null;
+	unfiltered_text: string | null;
 }
 
 export interface EntryCreate {
 	ended_at: number;
 	id: string;
+	language: string | null;
+	language_probability: number | null;
+	original_text: string | null;
 	session_id: string;
 	source: SessionMode;
+	speaker: string | null;
 	started_at: number;
 	text: string;
+	translated_text: string | null;
 	typed: boolean;
+	typed_chars?: number | null;
+	unfiltered_text: string | null;
+}
+
+export interface SessionSpeaker {
+	name: string;
+	speaker: string;
 }
 
+export type EntryTextField = "text" | "original" | "translation";
+
 export type ActivationMode = "hold" | "toggle";
 export type SilenceRms = "low" | "medium" | "high";
-export type ModelProfile =
+export type BuiltinModelProfile =
 	| "small.en"
 	| "multilingual-small"
 	| "multilingual-medium";
+// Imported models are selected by their registry id, e.g. "small.en-1a2b3c4d".
+export type ModelProfile = BuiltinModelProfile | string;
+
+export interface ImportedModel {
+	filename: string;
+	format: "ggml" | "gguf";
+	id: string;
+	imported_at: number;
+	model_type: string;
+	multilingual: boolean;
+	sha256: string;
+	size_bytes: number;
+}
+
+export type EngineStatus =
+	| { state: "unloaded" }
+	| { profile: ModelProfile; state: "loading" | "ready" }
+	| { message: string; profile: ModelProfile; state: "error" };
 
 export interface Hotkeys {
 	left_chord: boolean;
 	right_chord: boolean;
 }
 
+export type TypingMethod = "keystroke" | "clipboard";
+
+export type KeyboardLayout = "us" | "uk" | "de" | "fr" | "dvorak";
+
 export interface TypingPrefs {
+	cancel_chord: string;
+	clipboard_restore_ms: number;
+	layout: KeyboardLayout;
+	method: TypingMethod;
 	newline_at_end: boolean;
+	scratch_chord: string;
 	throttle_ms: number;
 }
 
-export interface VoiceCommandMap {
-	backtick: string;
-	close_quote: string;
-	code_block: string;
-	colon: string;
-	comma: string;
-	new_paragraph: string;
-	newline: string;
-	open_quote: string;
-	period: string;
-	semicolon: string;
-	tab: string;
+export type OutputTarget =
+	| "type"
+	| "clipboard"
+	| "stdout"
+	| "file"
+	| "socket";
+
+export interface OutputPrefs {
+	path: string;
+	target: OutputTarget;
+}
+
+export type BuiltinCommand =
+	| "newline"
+	| "new_paragraph"
+	| "tab"
+	| "period"
+	| "comma"
+	| "colon"
+	| "semicolon"
+	| "open_quote"
+	| "close_quote"
+	| "backtick"
+	| "code_block"
+	| "scratch_that";
+
+export type CommandAction =
+	| { type: "text"; value: string }
+	| { type: "keys"; value: string }
+	| { type: "builtin"; value: BuiltinCommand };
+
+export interface VoiceCommand {
+	action: CommandAction;
+	phrases: string[];
 }
 
 export interface VoiceCommands {
+	commands: VoiceCommand[];
 	enabled: boolean;
-	map: VoiceCommandMap;
 }
 
 export interface RecordPrefs {
 	chunk_seconds: number;
+	diarization: boolean;
 	max_file_gb: number;
 	max_hours: number;
 }
 
+export type TranslationMode = "translation_only" | "both_passes" | "background";
+export type TextKind = "original" | "translation";
+
+export interface TranslationPrefs {
+	mode: TranslationMode;
+	typed: TextKind;
+}
+
+/** Overrides for sessions started in one of `apps`; null keeps the default. */
+export interface AppProfile {
+	apps: string[];
+	code_mode: boolean | null;
+	model_profile: ModelProfile | null;
+	name: string;
+	newline_at_end: boolean | null;
+	throttle_ms: number | null;
+	translate_to_english: boolean | null;
+	translation: TranslationPrefs | null;
+	typing_method: TypingMethod | null;
+	voice_commands: VoiceCommands | null;
+}
+
+export interface CodeModePrefs {
+	apps: string[];
+	detect_code_blocks: boolean;
+}
+
+export interface DisfluencyFilter {
+	correction_cues: Record<string, string[]>;
+	enabled: boolean;
+	fillers: Record<string, string[]>;
+	self_corrections: boolean;
+}
+
+export type RedactionAction = "off" | "mask" | "block";
+
+export interface RedactionPrefs {
+	aws_keys: RedactionAction;
+	card_numbers: RedactionAction;
+	emails: RedactionAction;
+	enabled: boolean;
+	github_tokens: RedactionAction;
+	jwts: RedactionAction;
+	phone_numbers: RedactionAction;
+}
+
+export interface Snippet {
+	expansion: string;
+	regex: boolean;
+	trigger: string;
+}
+
+export interface Snippets {
+	enabled: boolean;
+	entries: Snippet[];
+}
+
+export interface ItnPrefs {
+	enabled: boolean;
+	languages: string[];
+}
+
+export interface HallucinationFilter {
+	blocklist: string[];
+	enabled: boolean;
+	max_repeats: number;
+	min_rms: number;
+	no_speech_threshold: number;
+}
+
 export interface Preferences {
+	app_profiles: AppProfile[];
+	code_mode: CodeModePrefs;
+	disfluency_filter: DisfluencyFilter;
+	hallucination_filter: HallucinationFilter;
 	hotkeys: Hotkeys;
+	itn: ItnPrefs;
 	mode: ActivationMode;
 	model_profile: ModelProfile;
+	output: OutputPrefs;
 	record: RecordPrefs;
+	redaction: RedactionPrefs;
 	silence_rms: SilenceRms;
 	silence_seconds: number;
+	snippets: Snippets;
 	translate_to_english: boolean;
+	translation: TranslationPrefs;
 	typing: TypingPrefs;
 	voice_commands: VoiceCommands;
 }
@@ -115,6 +269,12 @@ export const api = {
 			invoke<void>("update_preferences", { prefs }),
 	},
 
+	voiceCommands: {
+		export: () => invoke<string>("export_voice_commands"),
+		import: (json: string) =>
+			invoke<VoiceCommand[]>("import_voice_commands", { json }),
+	},
+
 	dirs: {
 		config: () => invoke<string>("get_config_dir"),
 		data: () => invoke<string>("get_data_dir"),
@@ -126,6 +286,14 @@ export const api = {
 		getCurrent: () => invoke<string | null>("get_current_model"),
 		verify: (profile: string) =>
 			invoke<ModelStatus>("verify_model", { profile }),
+		getImported: () => invoke<ImportedModel[]>("get_imported_models"),
+		import: (path: string) => invoke<ImportedModel>("import_model", { path }),
+	},
+
+	engine: {
+		getStatus: () => invoke<EngineStatus>("get_engine_status"),
+		onStatus: (handler: (status: EngineStatus) => void) =>
+			listen<EngineStatus>("engine-status", (event) => handler(event.payload)),
 	},
 
 	sessions: {
@@ -159,8 +327,22 @@ export const api = {
 		update: (id: string, text?: string, typed?: boolean) =>
 			invoke<Entry | null>("update_entry", { id, text, typed }),
 		delete: (id: string) => invoke<boolean>("delete_entry", { id }),
-		search: (query: string) => invoke<Entry[]>("search_entries", { query }),
+		search: (query: string, field?: EntryTextField) =>
+			invoke<Entry[]>("search_entries", { query, field }),
 		addTyped: (text: string) => invoke<Entry>("add_typed_entry", { text }),
 		addUntyped: (text: string) => invoke<Entry>("add_untyped_entry", { text }),
 	},
+
+	speakers: {
+		getBySession: (sessionId: string) =>
+			invoke<SessionSpeaker[]>("get_session_speakers", {
+				session_id: sessionId,
+			}),
+		rename: (sessionId: string, speaker: string, name: string) =>
+			invoke<void>("rename_speaker", {
+				session_id: sessionId,
+				speaker,
+				name,
+			}),
+	},
 };
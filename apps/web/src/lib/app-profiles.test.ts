@@ -0,0 +1,50 @@
+import { describe, expect, it } from "vitest";
+import {
+	describeOverrides,
+	emptyProfile,
+	overrideChoice,
+	parseApps,
+	parseOverrideChoice,
+	parseThrottle,
+} from "./app-profiles";
+
+describe("App Profiles Module", () => {
+	describe("override choices", () => {
+		it("should round-trip unset, on and off", () => {
+			for (const value of [null, true, false]) {
+				expect(parseOverrideChoice(overrideChoice(value))).toBe(value);
+			}
+		});
+	});
+
+	describe("parseThrottle", () => {
+		it("should keep the global throttle for blank input", () => {
+			expect(parseThrottle("  ")).toBeNull();
+			expect(parseThrottle("abc")).toBeNull();
+			expect(parseThrottle("25")).toBe(25);
+		});
+	});
+
+	describe("parseApps", () => {
+		it("should split names and bundle ids", () => {
+			expect(parseApps("Terminal, com.googlecode.iterm2,, ")).toEqual([
+				"Terminal",
+				"com.googlecode.iterm2",
+			]);
+		});
+	});
+
+	describe("describeOverrides", () => {
+		it("should list only the fields a profile sets", () => {
+			expect(describeOverrides(emptyProfile())).toEqual([]);
+			expect(
+				describeOverrides({
+					...emptyProfile(),
+					code_mode: false,
+					translate_to_english: true,
+					typing_method: "clipboard",
+				})
+			).toEqual(["typing method", "code mode", "translation"]);
+		});
+	});
+});
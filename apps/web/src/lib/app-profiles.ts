@@ -0,0 +1,66 @@
+import type { AppProfile } from "./api";
+
+/** How a settings select shows an optional on/off override. */
+export type OverrideChoice = "default" | "on" | "off";
+
+export function overrideChoice(value: boolean | null): OverrideChoice {
+	if (value === null) {
+		return "default";
+	}
+	return value ? "on" : "off";
+}
+
+export function parseOverrideChoice(choice: string): boolean | null {
+	if (choice === "default") {
+		return null;
+	}
+	return choice === "on";
+}
+
+/** An empty throttle field keeps the global throttle. */
+export function parseThrottle(input: string): number | null {
+	const trimmed = input.trim();
+	if (!trimmed) {
+		return null;
+	}
+	const value = Number.parseInt(trimmed, 10);
+	return Number.isNaN(value) ? null : value;
+}
+
+export function parseApps(input: string): string[] {
+	return input
+		.split(",")
+		.map((app) => app.trim())
+		.filter(Boolean);
+}
+
+export function emptyProfile(): AppProfile {
+	return {
+		apps: [],
+		code_mode: null,
+		model_profile: null,
+		name: "",
+		newline_at_end: null,
+		throttle_ms: null,
+		translate_to_english: null,
+		translation: null,
+		typing_method: null,
+		voice_commands: null,
+	};
+}
+
+/** Names of the overrides a profile sets, for a one-line summary. */
+export function describeOverrides(profile: AppProfile): string[] {
+	const overrides: [string, unknown][] = [
+		["typing method", profile.typing_method],
+		["throttle", profile.throttle_ms],
+		["newline", profile.newline_at_end],
+		["code mode", profile.code_mode],
+		["voice commands", profile.voice_commands],
+		["model", profile.model_profile],
+		["translation", profile.translate_to_english ?? profile.translation],
+	];
+	return overrides
+		.filter(([, value]) => value !== null && value !== undefined)
+		.map(([name]) => name);
+}
@@ -322,6 +322,11 @@ describe("Export Module", () => {
 			const blob = createExportBlob("test content", "md");
 			expect(blob.type).toMatch(/^text\/markdown/);
 		});
+
+		it("should create blob with application/json for json", () => {
+			const blob = createExportBlob("{}", "json");
+			expect(blob.type).toBe("application/json");
+		});
 	});
 
 	describe("downloadExport", () => {
@@ -159,14 +159,21 @@ export function exportEntries(
 	return exportToMd(entries, session, options);
 }
 
-export function createExportBlob(content: string, format: "txt" | "md"): Blob {
-	const mimeType = format === "txt" ? "text/plain" : "text/markdown";
-	return new Blob([content], { type: mimeType });
+const EXPORT_MIME_TYPES = {
+	txt: "text/plain",
+	md: "text/markdown",
+	json: "application/json",
+};
+
+export type ExportFormat = keyof typeof EXPORT_MIME_TYPES;
+
+export function createExportBlob(content: string, format: ExportFormat): Blob {
+	return new Blob([content], { type: EXPORT_MIME_TYPES[format] });
 }
 
 export function downloadExport(
 	content: string,
-	format: "txt" | "md",
+	format: ExportFormat,
 	filename?: string
 ): void {
 	const blob = createExportBlob(content, format);
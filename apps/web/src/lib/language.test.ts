@@ -0,0 +1,143 @@
+import { describe, expect, it } from "vitest";
+import { mockEntry, mockSession } from "../test/mocks";
+import {
+	ALL_LANGUAGES,
+	alternateText,
+	collectLanguages,
+	filterByLanguage,
+	formatEntryLanguage,
+	formatLanguageLists,
+	isTranslatedEntry,
+	languageName,
+	parseLanguageLists,
+} from "./language";
+
+const spanishEntry = {
+	...mockEntry,
+	id: "entry-es",
+	language: "es",
+	language_probability: 0.934,
+};
+const unknownEntry = {
+	...mockEntry,
+	id: "entry-unknown",
+	language: null,
+	language_probability: null,
+};
+
+describe("Language Module", () => {
+	describe("collectLanguages", () => {
+		it("should return unique sorted languages", () => {
+			expect(
+				collectLanguages([spanishEntry, mockEntry, spanishEntry, unknownEntry])
+			).toEqual(["en", "es"]);
+		});
+
+		it("should return empty list when no language was detected", () => {
+			expect(collectLanguages([unknownEntry])).toEqual([]);
+		});
+	});
+
+	describe("filterByLanguage", () => {
+		const entries = [mockEntry, spanishEntry, unknownEntry];
+
+		it("should keep every entry for the all filter", () => {
+			expect(filterByLanguage(entries, ALL_LANGUAGES)).toHaveLength(3);
+		});
+
+		it("should keep only entries in the selected language", () => {
+			const result = filterByLanguage(entries, "es");
+			expect(result).toHaveLength(1);
+			expect(result[0].id).toBe("entry-es");
+		});
+	});
+
+	describe("formatEntryLanguage", () => {
+		it("should include the rounded probability", () => {
+			expect(formatEntryLanguage(spanishEntry)).toBe("Spanish (93%)");
+		});
+
+		it("should omit the probability when unknown", () => {
+			expect(
+				formatEntryLanguage({ ...spanishEntry, language_probability: null })
+			).toBe("Spanish");
+		});
+
+		it("should return null without a language", () => {
+			expect(formatEntryLanguage(unknownEntry)).toBeNull();
+		});
+
+		it("should fall back to the code for unlisted languages", () => {
+			expect(languageName("eu")).toBe("eu");
+		});
+	});
+
+	describe("isTranslatedEntry", () => {
+		it("should flag non-English entries in translated sessions", () => {
+			expect(
+				isTranslatedEntry(spanishEntry, { ...mockSession, translated: true })
+			).toBe(true);
+		});
+
+		it("should not flag English entries", () => {
+			expect(
+				isTranslatedEntry(mockEntry, { ...mockSession, translated: true })
+			).toBe(false);
+		});
+
+		it("should not flag entries when translation is off", () => {
+			expect(isTranslatedEntry(spanishEntry, mockSession)).toBe(false);
+		});
+	});
+
+	describe("alternateText", () => {
+		it("should show the original when the translation was typed", () => {
+			expect(
+				alternateText({
+					...spanishEntry,
+					text: "good morning",
+					original_text: "buenos días",
+					translated_text: "good morning",
+				})
+			).toEqual({ label: "Original", text: "buenos días" });
+		});
+
+		it("should show the translation when the original was typed", () => {
+			expect(
+				alternateText({
+					...spanishEntry,
+					text: "buenos días",
+					original_text: "buenos días",
+					translated_text: "good morning",
+				})
+			).toEqual({ label: "Translation", text: "good morning" });
+		});
+
+		it("should show the text before filler words were removed", () => {
+			expect(
+				alternateText({
+					...mockEntry,
+					text: "the fix works",
+					unfiltered_text: "um the the fix works",
+				})
+			).toEqual({ label: "Before cleanup", text: "um the the fix works" });
+		});
+
+		it("should return null when only one text was kept", () => {
+			expect(alternateText(mockEntry)).toBeNull();
+		});
+	});
+
+	describe("language lists", () => {
+		it("should round-trip filler lists", () => {
+			const lists = { en: ["um", "you know"], fr: ["euh"] };
+			expect(parseLanguageLists(formatLanguageLists(lists))).toEqual(lists);
+		});
+
+		it("should skip malformed lines and merge repeated languages", () => {
+			expect(
+				parseLanguageLists("EN: um, , uh\nnot a list\n: er\nen: hmm\nde:")
+			).toEqual({ en: ["um", "uh", "hmm"] });
+		});
+	});
+});
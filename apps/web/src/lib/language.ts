@@ -0,0 +1,104 @@
+import type { Entry, Session } from "./api";
+
+export const ALL_LANGUAGES = "all";
+
+const LANGUAGE_NAMES: Record<string, string> = {
+	ar: "Arabic",
+	de: "German",
+	en: "English",
+	es: "Spanish",
+	fr: "French",
+	hi: "Hindi",
+	it: "Italian",
+	ja: "Japanese",
+	ko: "Korean",
+	nl: "Dutch",
+	pl: "Polish",
+	pt: "Portuguese",
+	ru: "Russian",
+	sv: "Swedish",
+	tr: "Turkish",
+	uk: "Ukrainian",
+	zh: "Chinese",
+};
+
+export function languageName(code: string): string {
+	return LANGUAGE_NAMES[code] ?? code;
+}
+
+export function collectLanguages(entries: Entry[]): string[] {
+	const languages = new Set<string>();
+	for (const entry of entries) {
+		if (entry.language) {
+			languages.add(entry.language);
+		}
+	}
+	return [...languages].sort();
+}
+
+export function filterByLanguage(entries: Entry[], language: string): Entry[] {
+	if (language === ALL_LANGUAGES) {
+		return entries;
+	}
+	return entries.filter((entry) => entry.language === language);
+}
+
+export function formatEntryLanguage(entry: Entry): string | null {
+	if (!entry.language) {
+		return null;
+	}
+	if (entry.language_probability === null) {
+		return languageName(entry.language);
+	}
+	const percent = Math.round(entry.language_probability * 100);
+	return `${languageName(entry.language)} (${percent}%)`;
+}
+
+export function isTranslatedEntry(entry: Entry, session?: Session): boolean {
+	return Boolean(
+		session?.translated && entry.language && entry.language !== "en"
+	);
+}
+
+/** The stored text that was not typed, labelled for display. */
+export function alternateText(
+	entry: Entry
+): { label: string; text: string } | null {
+	if (entry.original_text && entry.original_text !== entry.text) {
+		return { label: "Original", text: entry.original_text };
+	}
+	if (entry.translated_text && entry.translated_text !== entry.text) {
+		return { label: "Translation", text: entry.translated_text };
+	}
+	if (entry.unfiltered_text && entry.unfiltered_text !== entry.text) {
+		return { label: "Before cleanup", text: entry.unfiltered_text };
+	}
+	return null;
+}
+
+/** One "code: phrase, phrase" line per language, for editing in a textarea. */
+export function formatLanguageLists(lists: Record<string, string[]>): string {
+	return Object.entries(lists)
+		.map(([language, phrases]) => `${language}: ${phrases.join(", ")}`)
+		.join("\n");
+}
+
+export function parseLanguageLists(text: string): Record<string, string[]> {
+	const lists: Record<string, string[]> = {};
+	for (const line of text.split("\n")) {
+		const separator = line.indexOf(":");
+		if (separator === -1) {
+			continue;
+		}
+		const language = line.slice(0, separator).trim().toLowerCase();
+		const phrases = line
+			.slice(separator + 1)
+			.split(",")
+			.map((phrase) => phrase.trim())
+			.filter((phrase) => phrase.length > 0);
+		if (language.length > 0 && phrases.length > 0) {
+			lists[language] = [...(lists[language] ?? []), ...phrases];
+		}
+	}
+	return lists;
+}
@@ -0,0 +1,63 @@
+import { describe, expect, it } from "vitest";
+import { mockEntry } from "../test/mocks";
+import {
+	diarizedSessionIds,
+	speakerName,
+	toSpeakerNames,
+	withSpeakerName,
+} from "./speakers";
+
+const firstSpeaker = {
+	...mockEntry,
+	id: "entry-s1",
+	session_id: "record-1",
+	source: "record" as const,
+	speaker: "S1",
+};
+const secondSpeaker = { ...firstSpeaker, id: "entry-s2", speaker: "S2" };
+
+describe("Speakers Module", () => {
+	describe("diarizedSessionIds", () => {
+		it("should list each session with speaker labels once", () => {
+			expect(
+				diarizedSessionIds([mockEntry, firstSpeaker, secondSpeaker])
+			).toEqual(["record-1"]);
+		});
+	});
+
+	describe("speakerName", () => {
+		const names = toSpeakerNames([
+			["record-1", [{ speaker: "S1", name: "Alice" }]],
+		]);
+
+		it("should prefer the session's name for a label", () => {
+			expect(speakerName(firstSpeaker, names)).toBe("Alice");
+		});
+
+		it("should fall back to the label", () => {
+			expect(speakerName(secondSpeaker, names)).toBe("S2");
+			expect(speakerName(firstSpeaker, {})).toBe("S1");
+		});
+
+		it("should return null for entries without a speaker", () => {
+			expect(speakerName(mockEntry, names)).toBeNull();
+		});
+	});
+
+	describe("withSpeakerName", () => {
+		it("should set and trim a name", () => {
+			const names = withSpeakerName({}, "record-1", "S2", "  Bob ");
+			expect(names).toEqual({ "record-1": { S2: "Bob" } });
+		});
+
+		it("should clear a name when empty", () => {
+			const names = withSpeakerName(
+				{ "record-1": { S1: "Alice", S2: "Bob" } },
+				"record-1",
+				"S1",
+				""
+			);
+			expect(names).toEqual({ "record-1": { S2: "Bob" } });
+		});
+	});
+});
@@ -0,0 +1,49 @@
+import type { Entry, SessionSpeaker } from "./api";
+
+/** Speaker display names keyed by session id, then by diarization label. */
+export type SpeakerNames = Record<string, Record<string, string>>;
+
+export function diarizedSessionIds(entries: Entry[]): string[] {
+	const sessionIds = new Set<string>();
+	for (const entry of entries) {
+		if (entry.speaker) {
+			sessionIds.add(entry.session_id);
+		}
+	}
+	return [...sessionIds];
+}
+
+export function toSpeakerNames(
+	bySession: [string, SessionSpeaker[]][]
+): SpeakerNames {
+	const names: SpeakerNames = {};
+	for (const [sessionId, speakers] of bySession) {
+		names[sessionId] = Object.fromEntries(
+			speakers.map((s) => [s.speaker, s.name])
+		);
+	}
+	return names;
+}
+
+export function speakerName(entry: Entry, names: SpeakerNames): string | null {
+	if (!entry.speaker) {
+		return null;
+	}
+	return names[entry.session_id]?.[entry.speaker] ?? entry.speaker;
+}
+
+export function withSpeakerName(
+	names: SpeakerNames,
+	sessionId: string,
+	speaker: string,
+	name: string
+): SpeakerNames {
+	const session = Object.fromEntries(
+		Object.entries(names[sessionId] ?? {}).filter(([label]) => label !== speaker)
+	);
+	const trimmed = name.trim();
+	if (trimmed) {
+		session[speaker] = trimmed;
+	}
+	return { ...names, [sessionId]: session };
+}
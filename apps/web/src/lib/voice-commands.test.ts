@@ -0,0 +1,60 @@
+import { describe, expect, it } from "vitest";
+import { mockPreferences } from "../test/mocks";
+import {
+	defaultAction,
+	findConflicts,
+	parsePhrases,
+	phraseKey,
+} from "./voice-commands";
+
+describe("Voice Commands Module", () => {
+	describe("phraseKey", () => {
+		it("should ignore case, spacing and edge punctuation", () => {
+			expect(phraseKey("  Fat   Arrow, ")).toBe("fat arrow");
+			expect(phraseKey("“Period.”")).toBe("period");
+		});
+	});
+
+	describe("parsePhrases", () => {
+		it("should split comma separated phrases and drop blanks", () => {
+			expect(parsePhrases("arrow, fat arrow,, ")).toEqual([
+				"arrow",
+				"fat arrow",
+			]);
+		});
+	});
+
+	describe("findConflicts", () => {
+		it("should report nothing for the default list", () => {
+			expect(findConflicts(mockPreferences.voice_commands.commands).size).toBe(
+				0
+			);
+		});
+
+		it("should report phrases shared between commands", () => {
+			const conflicts = findConflicts([
+				...mockPreferences.voice_commands.commands,
+				{ phrases: ["Fat arrow"], action: { type: "text", value: "->" } },
+			]);
+			expect([...conflicts]).toEqual(["fat arrow"]);
+		});
+
+		it("should allow a command to repeat its own phrase", () => {
+			const conflicts = findConflicts([
+				{ phrases: ["pipe", "Pipe"], action: { type: "text", value: "|" } },
+			]);
+			expect(conflicts.size).toBe(0);
+		});
+	});
+
+	describe("defaultAction", () => {
+		it("should give each action type a usable starting value", () => {
+			expect(defaultAction("builtin")).toEqual({
+				type: "builtin",
+				value: "newline",
+			});
+			expect(defaultAction("keys")).toEqual({ type: "keys", value: "enter" });
+			expect(defaultAction("text")).toEqual({ type: "text", value: "" });
+		});
+	});
+});
@@ -0,0 +1,66 @@
+import type { BuiltinCommand, CommandAction, VoiceCommand } from "./api";
+
+export const BUILTIN_COMMAND_LABELS: Record<BuiltinCommand, string> = {
+	newline: "Newline",
+	new_paragraph: "New paragraph",
+	tab: "Tab",
+	period: "Period",
+	comma: "Comma",
+	colon: "Colon",
+	semicolon: "Semicolon",
+	open_quote: "Open quote",
+	close_quote: "Close quote",
+	backtick: "Backtick",
+	code_block: "Code block",
+	scratch_that: "Scratch that",
+};
+
+const EDGE_PUNCTUATION = /^[.,!?;:"'“”‘’…¿¡]+|[.,!?;:"'“”‘’…¿¡]+$/g;
+
+/** Normalizes a phrase the way the backend matches it. */
+export function phraseKey(phrase: string): string {
+	return phrase
+		.split(/\s+/)
+		.map((word) => word.replace(EDGE_PUNCTUATION, "").toLowerCase())
+		.filter(Boolean)
+		.join(" ");
+}
+
+export function parsePhrases(input: string): string[] {
+	return input
+		.split(",")
+		.map((phrase) => phrase.trim())
+		.filter(Boolean);
+}
+
+/** Normalized phrases claimed by more than one command. */
+export function findConflicts(commands: VoiceCommand[]): Set<string> {
+	const owners = new Map<string, number>();
+	const conflicts = new Set<string>();
+	commands.forEach((command, index) => {
+		for (const phrase of command.phrases) {
+			const key = phraseKey(phrase);
+			if (!key) {
+				continue;
+			}
+			const owner = owners.get(key);
+			if (owner === undefined) {
+				owners.set(key, index);
+			} else if (owner !== index) {
+				conflicts.add(key);
+			}
+		}
+	});
+	return conflicts;
+}
+
+export function defaultAction(type: CommandAction["type"]): CommandAction {
+	switch (type) {
+		case "builtin":
+			return { type, value: "newline" };
+		case "keys":
+			return { type, value: "enter" };
+		default:
+			return { type: "text", value: "" };
+	}
+}
@@ -1,4 +1,5 @@
 import { describe, expect, it, vi } from "vitest";
+import type { EngineStatus } from "@/lib/api";
 import { createMockApi, mockPreferences, mockSession } from "@/test/mocks";
 
 vi.mock("@/lib/api", () => ({
@@ -31,6 +32,19 @@ const getModelLabel = (profile: string) => {
 	}
 };
 
+const getEngineLabel = (status: EngineStatus) => {
+	switch (status.state) {
+		case "loading":
+			return "Loading model…";
+		case "ready":
+			return "Ready";
+		case "error":
+			return `Error: ${status.message}`;
+		default:
+			return "No model loaded";
+	}
+};
+
 describe("HUD Component", () => {
 	describe("Mode Display", () => {
 		it("should return correct label for hold mode", () => {
@@ -70,6 +84,34 @@ describe("HUD Component", () => {
 		});
 	});
 
+	describe("Engine Display", () => {
+		it("should show loading while the model warms up", () => {
+			expect(getEngineLabel({ state: "loading", profile: "small.en" })).toBe(
+				"Loading model…"
+			);
+		});
+
+		it("should show ready once warmed up", () => {
+			expect(getEngineLabel({ state: "ready", profile: "small.en" })).toBe(
+				"Ready"
+			);
+		});
+
+		it("should include the load error", () => {
+			expect(
+				getEngineLabel({
+					state: "error",
+					profile: "multilingual-medium",
+					message: "Model file not found",
+				})
+			).toBe("Error: Model file not found");
+		});
+
+		it("should fall back when nothing is loaded", () => {
+			expect(getEngineLabel({ state: "unloaded" })).toBe("No model loaded");
+		});
+	});
+
 	describe("Voice Commands Status", () => {
 		it("should show enabled when voice commands are enabled", () => {
 			expect(mockPreferences.voice_commands.enabled).toBe(true);
@@ -243,53 +243,47 @@ describe("Settings Form Validation", () => {
 				Number.parseInt(formData.get("throttle_ms") as string, 10) || 0;
 			expect(value).toBe(0);
 		});
-	});
-
-	describe("Voice Commands Map", () => {
-		it("should have newline mapping", () => {
-			expect(mockPreferences.voice_commands.map.newline).toBe("Enter");
-		});
 
-		it("should have period mapping", () => {
-			expect(mockPreferences.voice_commands.map.period).toBe(".");
-		});
-
-		it("should have comma mapping", () => {
-			expect(mockPreferences.voice_commands.map.comma).toBe(",");
-		});
+		it("should parse code mode apps one per line", () => {
+			const formData = new FormData();
+			formData.set("code_mode_apps", "Code\n  Terminal \n\n");
 
-		it("should have tab mapping", () => {
-			expect(mockPreferences.voice_commands.map.tab).toBe("Tab");
+			const apps = (formData.get("code_mode_apps") as string)
+				.split("\n")
+				.map((app) => app.trim())
+				.filter((app) => app.length > 0);
+			expect(apps).toEqual(mockPreferences.code_mode.apps);
 		});
 
-		it("should have new_paragraph mapping", () => {
-			expect(mockPreferences.voice_commands.map.new_paragraph).toBe(
-				"Enter Enter"
-			);
-		});
-
-		it("should have colon mapping", () => {
-			expect(mockPreferences.voice_commands.map.colon).toBe(":");
-		});
+		it("should parse number normalization languages", () => {
+			const formData = new FormData();
+			formData.set("itn_languages", " en, ,");
 
-		it("should have semicolon mapping", () => {
-			expect(mockPreferences.voice_commands.map.semicolon).toBe(";");
+			const languages = (formData.get("itn_languages") as string)
+				.split(",")
+				.map((language) => language.trim())
+				.filter((language) => language.length > 0);
+			expect(languages).toEqual(mockPreferences.itn.languages);
 		});
+	});
 
-		it("should have open_quote mapping", () => {
-			expect(mockPreferences.voice_commands.map.open_quote).toBe('"');
-		});
+	describe("Voice Commands List", () => {
+		const { commands } = mockPreferences.voice_commands;
 
-		it("should have close_quote mapping", () => {
-			expect(mockPreferences.voice_commands.map.close_quote).toBe('"');
+		it("should keep built-in commands as ordinary entries", () => {
+			expect(commands[0]).toEqual({
+				phrases: ["newline"],
+				action: { type: "builtin", value: "newline" },
+			});
 		});
 
-		it("should have backtick mapping", () => {
-			expect(mockPreferences.voice_commands.map.backtick).toBe("`");
+		it("should allow several phrases per command", () => {
+			const arrow = commands.find((c) => c.action.type === "text");
+			expect(arrow?.phrases).toEqual(["arrow", "fat arrow"]);
 		});
 
-		it("should have code_block mapping", () => {
-			expect(mockPreferences.voice_commands.map.code_block).toBe("```");
+		it("should include key sequence commands", () => {
+			expect(commands.some((c) => c.action.type === "keys")).toBe(true);
 		});
 	});
 
@@ -328,6 +322,73 @@ describe("Settings Form Validation", () => {
 		});
 	});
 
+	describe("Snippets", () => {
+		it("should start from the saved snippets", () => {
+			expect(mockPreferences.snippets.enabled).toBe(true);
+			expect(
+				mockPreferences.snippets.entries.map((snippet) => snippet.trigger)
+			).toEqual(["todo marker", "my email"]);
+		});
+
+		it("should parse the enabled checkbox", () => {
+			const formData = new FormData();
+			expect(formData.get("snippets_enabled") === "on").toBe(false);
+			formData.set("snippets_enabled", "on");
+			expect(formData.get("snippets_enabled") === "on").toBe(true);
+		});
+	});
+
+	describe("Redaction", () => {
+		it("should read one action per detected value", () => {
+			const formData = new FormData();
+			formData.set("redaction_card_numbers", "mask");
+			formData.set("redaction_phone_numbers", "block");
+			expect(formData.get("redaction_card_numbers")).toBe("mask");
+			expect(formData.get("redaction_phone_numbers")).toBe("block");
+			expect(mockPreferences.redaction.emails).toBe("off");
+		});
+	});
+
+	describe("Keyboard layout", () => {
+		it("should default to US and read the chosen layout", () => {
+			const formData = new FormData();
+			formData.set("keyboard_layout", "de");
+			expect(formData.get("keyboard_layout")).toBe("de");
+			expect(mockPreferences.typing.layout).toBe("us");
+		});
+	});
+
+	describe("Output", () => {
+		it("should default to typing and read the chosen sink", () => {
+			const formData = new FormData();
+			formData.set("output_target", "file");
+			formData.set("output_path", " /tmp/dictation.txt ");
+			expect(formData.get("output_target")).toBe("file");
+			expect((formData.get("output_path") as string).trim()).toBe(
+				"/tmp/dictation.txt"
+			);
+			expect(mockPreferences.output.target).toBe("type");
+		});
+	});
+
+	describe("Cancel chord", () => {
+		it("should default to Escape and allow turning it off", () => {
+			const formData = new FormData();
+			formData.set("cancel_chord", "  ");
+			expect((formData.get("cancel_chord") as string).trim()).toBe("");
+			expect(mockPreferences.typing.cancel_chord).toBe("escape");
+		});
+
+		it("should leave the scratch chord off by default", () => {
+			const formData = new FormData();
+			formData.set("scratch_chord", " ctrl+alt+z ");
+			expect((formData.get("scratch_chord") as string).trim()).toBe(
+				"ctrl+alt+z"
+			);
+			expect(mockPreferences.typing.scratch_chord).toBe("");
+		});
+	});
+
 	describe("Hotkey Checkboxes", () => {
 		it("should handle checked left_chord", () => {
 			const formData = new FormData();
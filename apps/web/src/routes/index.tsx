@@ -3,7 +3,7 @@ import { Activity, Database, Mic, MicOff, Settings } from "lucide-react";
 import { useEffect, useState } from "react";
 
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
-import { api } from "@/lib/api";
+import { api, type EngineStatus } from "@/lib/api";
 
 export const Route = createFileRoute("/")({
 	loader: async () => {
@@ -19,6 +19,26 @@ function HomeComponent() {
 	const { prefs, latestSession } = Route.useLoaderData();
 	const [isRecording] = useState(false);
 	const [micLevel, setMicLevel] = useState(0);
+	const [engineStatus, setEngineStatus] = useState<EngineStatus>({
+		state: "unloaded",
+	});
+
+	useEffect(() => {
+		let unlisten: (() => void) | undefined;
+		let cancelled = false;
+		api.engine.onStatus(setEngineStatus).then((stop) => {
+			if (cancelled) {
+				stop();
+			} else {
+				unlisten = stop;
+			}
+		});
+		api.engine.getStatus().then(setEngineStatus);
+		return () => {
+			cancelled = true;
+			unlisten?.();
+		};
+	}, []);
 
 	useEffect(() => {
 		const interval = setInterval(() => {
@@ -53,6 +73,19 @@ function HomeComponent() {
 		}
 	};
 
+	const getEngineLabel = (status: EngineStatus) => {
+		switch (status.state) {
+			case "loading":
+				return "Loading model…";
+			case "ready":
+				return "Ready";
+			case "error":
+				return `Error: ${status.message}`;
+			default:
+				return "No model loaded";
+		}
+	};
+
 	return (
 		<div className="container mx-auto max-w-3xl px-4 py-2">
 			<div className="mb-6 flex items-center justify-between">
@@ -92,6 +125,18 @@ function HomeComponent() {
 									{getModelLabel(prefs.model_profile)}
 								</div>
 							</div>
+							<div>
+								<div className="text-muted-foreground text-xs">Engine</div>
+								<div
+									className={
+										engineStatus.state === "error"
+											? "font-medium text-red-500"
+											: "font-medium"
+									}
+								>
+									{getEngineLabel(engineStatus)}
+								</div>
+							</div>
 							<div>
 								<div className="text-muted-foreground text-xs">Mic Level</div>
 								<div className="flex items-center gap-2">
@@ -5,7 +5,29 @@ import { toast } from "sonner";
 import { Button } from "@/components/ui/button";
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
 import { Input } from "@/components/ui/input";
-import { api, type Entry, type Session } from "@/lib/api";
+import {
+	api,
+	type Entry,
+	type EntryTextField,
+	type Session,
+	type SessionSpeaker,
+} from "@/lib/api";
+import {
+	ALL_LANGUAGES,
+	alternateText,
+	collectLanguages,
+	filterByLanguage,
+	formatEntryLanguage,
+	isTranslatedEntry,
+	languageName,
+} from "@/lib/language";
+import {
+	diarizedSessionIds,
+	type SpeakerNames,
+	speakerName,
+	toSpeakerNames,
+	withSpeakerName,
+} from "@/lib/speakers";
 
 export const Route = createFileRoute("/logs")({
 	loader: async () => {
@@ -13,17 +35,40 @@ export const Route = createFileRoute("/logs")({
 			api.sessions.getAll(),
 			api.entries.getAll(),
 		]);
-		return { sessions, entries };
+		const speakers = await Promise.all(
+			diarizedSessionIds(entries).map(
+				async (id) =>
+					[id, await api.speakers.getBySession(id)] as [
+						string,
+						SessionSpeaker[],
+					]
+			)
+		);
+		return { sessions, entries, speakerNames: toSpeakerNames(speakers) };
 	},
 	component: LogsComponent,
 });
 
 function LogsComponent() {
-	const { sessions, entries }: { entries: Entry[]; sessions: Session[] } =
-		Route.useLoaderData();
+	const {
+		sessions,
+		entries,
+		speakerNames: loadedSpeakerNames,
+	}: {
+		entries: Entry[];
+		sessions: Session[];
+		speakerNames: SpeakerNames;
+	} = Route.useLoaderData();
+	const [speakerNames, setSpeakerNames] =
+		useState<SpeakerNames>(loadedSpeakerNames);
 	const [searchQuery, setSearchQuery] = useState("");
 	const [filteredEntries, setFilteredEntries] = useState<Entry[]>(entries);
 	const [isSearching, setIsSearching] = useState(false);
+	const [languageFilter, setLanguageFilter] = useState(ALL_LANGUAGES);
+	const [searchField, setSearchField] = useState<EntryTextField>("text");
+
+	const languages = collectLanguages(entries);
+	const visibleEntries = filterByLanguage(filteredEntries, languageFilter);
 
 	const handleSearch = async () => {
 		if (!searchQuery.trim()) {
@@ -33,7 +78,7 @@ function LogsComponent() {
 
 		setIsSearching(true);
 		try {
-			const results = await api.entries.search(searchQuery);
+			const results = await api.entries.search(searchQuery, searchField);
 			setFilteredEntries(results);
 		} catch (error) {
 			toast.error("Search failed", {
@@ -44,9 +89,30 @@ function LogsComponent() {
 		}
 	};
 
+	const handleRenameSpeaker = async (
+		sessionId: string,
+		speaker: string,
+		name: string
+	) => {
+		try {
+			await api.speakers.rename(sessionId, speaker, name);
+			setSpeakerNames((names) =>
+				withSpeakerName(names, sessionId, speaker, name)
+			);
+		} catch (error) {
+			toast.error("Failed to rename speaker", {
+				description: error instanceof Error ? error.message : "Unknown error",
+			});
+		}
+	};
+
 	const handleExport = () => {
-		const content = filteredEntries
-			.map((e) => `[${new Date(e.started_at).toISOString()}] ${e.text}`)
+		const content = visibleEntries
+			.map((e) => {
+				const speaker = speakerName(e, speakerNames);
+				const text = speaker ? `${speaker}: ${e.text}` : e.text;
+				return `[${new Date(e.started_at).toISOString()}] ${text}`;
+			})
 			.join("\n");
 
 		const blob = new Blob([content], { type: "text/plain" });
@@ -97,6 +163,29 @@ function LogsComponent() {
 			<div className="mb-6 flex flex-col gap-4 sm:flex-row sm:items-center sm:justify-between">
 				<h1 className="font-medium text-lg">Logs</h1>
 				<div className="flex gap-2">
+					<select
+						aria-label="Filter by language"
+						className="h-8 rounded-none border border-input bg-transparent px-2.5 py-1 text-xs outline-none focus-visible:border-ring focus-visible:ring-1 focus-visible:ring-ring/50"
+						onChange={(e) => setLanguageFilter(e.target.value)}
+						value={languageFilter}
+					>
+						<option value={ALL_LANGUAGES}>All languages</option>
+						{languages.map((language) => (
+							<option key={language} value={language}>
+								{languageName(language)}
+							</option>
+						))}
+					</select>
+					<select
+						aria-label="Search in"
+						className="h-8 rounded-none border border-input bg-transparent px-2.5 py-1 text-xs outline-none focus-visible:border-ring focus-visible:ring-1 focus-visible:ring-ring/50"
+						onChange={(e) => setSearchField(e.target.value as EntryTextField)}
+						value={searchField}
+					>
+						<option value="text">Typed text</option>
+						<option value="original">Original</option>
+						<option value="translation">Translation</option>
+					</select>
 					<Input
 						className="w-64"
 						onChange={(e) => setSearchQuery(e.target.value)}
@@ -141,6 +230,10 @@ function LogsComponent() {
 												{session.id.slice(0, 8)}
 											</span>
 											<span>{getModeLabel(session.mode)}</span>
+											{session.language && (
+												<span>{languageName(session.language)}</span>
+											)}
+											{session.profile && <span>{session.profile}</span>}
 											<span className="text-muted-foreground">
 												{formatDate(session.started_at)}
 											</span>
@@ -158,17 +251,21 @@ function LogsComponent() {
 
 				<Card>
 					<CardHeader>
-						<CardTitle>Entries ({filteredEntries.length})</CardTitle>
+						<CardTitle>Entries ({visibleEntries.length})</CardTitle>
 					</CardHeader>
 					<CardContent>
-						{filteredEntries.length === 0 ? (
+						{visibleEntries.length === 0 ? (
 							<p className="text-muted-foreground text-sm">
-								{searchQuery ? "No matching entries." : "No entries yet."}
+								{searchQuery || languageFilter !== ALL_LANGUAGES
+									? "No matching entries."
+									: "No entries yet."}
 							</p>
 						) : (
 							<div className="grid gap-2">
-								{filteredEntries.map((entry: Entry) => {
+								{visibleEntries.map((entry: Entry) => {
 									const session = sessionMap.get(entry.session_id);
+									const language = formatEntryLanguage(entry);
+									const alternate = alternateText(entry);
 									return (
 										<div className="rounded border p-3 text-xs" key={entry.id}>
 											<div className="mb-1 flex items-center justify-between text-muted-foreground">
@@ -176,13 +273,37 @@ function LogsComponent() {
 													{formatDate(entry.started_at)} (
 													{formatDuration(entry.started_at, entry.ended_at)})
 												</span>
-												<span>{getModeLabel(entry.source)}</span>
+												<span>
+													{getModeLabel(entry.source)}
+													{language && ` · ${language}`}
+												</span>
 											</div>
-											<p className="wrap-break-word">{entry.text}</p>
+											<p className="wrap-break-word">
+												{entry.speaker && (
+													<SpeakerLabel
+														name={speakerName(entry, speakerNames) ?? entry.speaker}
+														onRename={(name) =>
+															handleRenameSpeaker(
+																entry.session_id,
+																entry.speaker ?? "",
+																name
+															)
+														}
+													/>
+												)}
+												{entry.text}
+											</p>
+											{alternate && (
+												<p className="wrap-break-word mt-1 text-muted-foreground">
+													{alternate.label}: {alternate.text}
+												</p>
+											)}
 											{session && (
 												<div className="mt-1 text-muted-foreground">
 													Session: {session.id.slice(0, 8)} | Typed:{" "}
-													{entry.typed ? "Yes" : "No"}
+													{typedLabel(entry)}
+													{isTranslatedEntry(entry, session) &&
+														` | Translated from ${languageName(entry.language ?? "")}`}
 												</div>
 											)}
 										</div>
@@ -196,3 +317,59 @@ function LogsComponent() {
 		</div>
 	);
 }
+
+function typedLabel(entry: Entry): string {
+	if (entry.retracted) {
+		return "Removed again";
+	}
+	if (entry.typed_chars !== null) {
+		return `Cancelled after ${entry.typed_chars} characters`;
+	}
+	return entry.typed ? "Yes" : "No";
+}
+
+function SpeakerLabel({
+	name,
+	onRename,
+}: {
+	name: string;
+	onRename: (name: string) => void;
+}) {
+	const [isEditing, setIsEditing] = useState(false);
+	const [draft, setDraft] = useState(name);
+
+	if (isEditing) {
+		return (
+			<Input
+				aria-label="Speaker name"
+				autoFocus
+				className="mr-2 inline-flex h-6 w-32"
+				onBlur={() => setIsEditing(false)}
+				onChange={(e) => setDraft(e.target.value)}
+				onKeyDown={(e) => {
+					if (e.key === "Enter") {
+						onRename(draft);
+						setIsEditing(false);
+					} else if (e.key === "Escape") {
+						setIsEditing(false);
+					}
+				}}
+				value={draft}
+			/>
+		);
+	}
+
+	return (
+		<button
+			className="mr-2 font-medium hover:underline"
+			onClick={() => {
+				setDraft(name);
+				setIsEditing(true);
+			}}
+			title="Rename speaker"
+			type="button"
+		>
+			{name}:
+		</button>
+	);
+}
@@ -17,35 +17,209 @@ import { Label } from "@/components/ui/label";
 import {
 	type ActivationMode,
 	api,
+	type AppProfile,
+	type BuiltinCommand,
+	type BuiltinModelProfile,
+	type CommandAction,
+	type ImportedModel,
+	type KeyboardLayout,
 	type ModelProfile,
 	type ModelStatus,
+	type OutputTarget,
 	type Preferences,
+	type RedactionAction,
+	type RedactionPrefs,
 	type SilenceRms,
+	type Snippet,
+	type TextKind,
+	type TranslationMode,
+	type TypingMethod,
+	type VoiceCommand,
+	type VoiceCommands,
 } from "@/lib/api";
+import {
+	describeOverrides,
+	emptyProfile,
+	type OverrideChoice,
+	overrideChoice,
+	parseApps,
+	parseOverrideChoice,
+	parseThrottle,
+} from "@/lib/app-profiles";
+import { downloadExport } from "@/lib/export";
+import { formatLanguageLists, parseLanguageLists } from "@/lib/language";
+import {
+	BUILTIN_COMMAND_LABELS,
+	defaultAction,
+	findConflicts,
+	parsePhrases,
+	phraseKey,
+} from "@/lib/voice-commands";
 
 export const Route = createFileRoute("/settings")({
 	loader: async () => {
-		const [prefs, modelStatuses] = await Promise.all([
+		const [prefs, modelStatuses, importedModels] = await Promise.all([
 			api.preferences.get(),
 			api.models.getStatuses(),
+			api.models.getImported(),
 		]);
-		return { prefs, modelStatuses };
+		return { prefs, modelStatuses, importedModels };
 	},
 	component: SettingsComponent,
 });
 
-const modelProfileLabels: Record<ModelProfile, string> = {
+const modelProfileLabels: Record<BuiltinModelProfile, string> = {
 	"small.en": "English (Small)",
 	"multilingual-small": "Multilingual (Small)",
 	"multilingual-medium": "Multilingual (Medium)",
 };
 
+function importedModelLabel(model: ImportedModel) {
+	const language = model.multilingual ? "Multilingual" : "English";
+	return `${language} (${model.model_type}, imported ${model.sha256.slice(0, 8)})`;
+}
+
 const silenceRmsLabels: Record<SilenceRms, string> = {
 	low: "Low",
 	medium: "Medium",
 	high: "High",
 };
 
+const translationModeLabels: Record<TranslationMode, string> = {
+	translation_only: "Translation only",
+	both_passes: "Keep original (two passes)",
+	background: "Keep original (in background)",
+};
+
+const textKindLabels: Record<TextKind, string> = {
+	translation: "Translation",
+	original: "Original",
+};
+
+const commandActionLabels: Record<CommandAction["type"], string> = {
+	builtin: "Built-in",
+	text: "Insert text",
+	keys: "Press keys",
+};
+
+/** A command being edited; phrases stay raw text until saved. */
+interface CommandRow {
+	action: CommandAction;
+	id: number;
+	phrases: string;
+}
+
+let nextCommandRowId = 0;
+
+function toCommandRows(commands: VoiceCommand[]): CommandRow[] {
+	return commands.map((command) => ({
+		id: nextCommandRowId++,
+		phrases: command.phrases.join(", "),
+		action: command.action,
+	}));
+}
+
+function fromCommandRows(rows: CommandRow[]): VoiceCommand[] {
+	return rows.map((row) => ({
+		phrases: parsePhrases(row.phrases),
+		action: row.action,
+	}));
+}
+
+const typingMethodLabels: Record<TypingMethod, string> = {
+	keystroke: "Keystrokes",
+	clipboard: "Paste from clipboard",
+};
+
+const outputTargetLabels: Record<OutputTarget, string> = {
+	type: "Type into the active app",
+	clipboard: "Copy to clipboard",
+	stdout: "Standard output",
+	file: "Append to file",
+	socket: "Unix socket or FIFO",
+};
+
+const keyboardLayoutLabels: Record<KeyboardLayout, string> = {
+	us: "US",
+	uk: "UK",
+	de: "German (QWERTZ)",
+	fr: "French (AZERTY)",
+	dvorak: "Dvorak",
+};
+
+const redactionActionLabels: Record<RedactionAction, string> = {
+	off: "Keep",
+	mask: "Mask in logs",
+	block: "Block",
+};
+
+type RedactedValue = Exclude<keyof RedactionPrefs, "enabled">;
+
+const redactedValueLabels: Record<RedactedValue, string> = {
+	aws_keys: "AWS access keys",
+	github_tokens: "GitHub tokens",
+	jwts: "JSON web tokens",
+	emails: "Email addresses",
+	card_numbers: "Card numbers",
+	phone_numbers: "Phone numbers",
+};
+
+const overrideLabels: Record<OverrideChoice, string> = {
+	default: "Default",
+	on: "On",
+	off: "Off",
+};
+
+/** A profile being edited; apps and throttle stay raw text until saved. */
+interface ProfileRow {
+	apps: string;
+	id: number;
+	profile: AppProfile;
+	throttle: string;
+}
+
+let nextProfileRowId = 0;
+
+function toProfileRows(profiles: AppProfile[]): ProfileRow[] {
+	return profiles.map((profile) => ({
+		id: nextProfileRowId++,
+		apps: profile.apps.join(", "),
+		throttle: profile.throttle_ms?.toString() ?? "",
+		profile,
+	}));
+}
+
+function fromProfileRows(rows: ProfileRow[]): AppProfile[] {
+	return rows.map((row) => ({
+		...row.profile,
+		name: row.profile.name.trim(),
+		apps: parseApps(row.apps),
+		throttle_ms: parseThrottle(row.throttle),
+	}));
+}
+
+/** A snippet being edited, keyed for React. */
+interface SnippetRow extends Snippet {
+	id: number;
+}
+
+let nextSnippetRowId = 0;
+
+function toSnippetRows(snippets: Snippet[]): SnippetRow[] {
+	return snippets.map((snippet) => ({ ...snippet, id: nextSnippetRowId++ }));
+}
+
+function fromSnippetRows(rows: SnippetRow[]): Snippet[] {
+	return rows.map(({ trigger, expansion, regex }) => ({
+		trigger: trigger.trim(),
+		expansion,
+		regex,
+	}));
+}
+
+const selectClassName =
+	"h-8 w-full rounded-none border border-input bg-transparent px-2.5 py-1 text-xs outline-none focus-visible:border-ring focus-visible:ring-1 focus-visible:ring-ring/50";
+
 function getModelStatusBadge(status: ModelStatus) {
 	if (status.is_verified) {
 		return (
@@ -69,8 +243,115 @@ function getModelStatusBadge(status: ModelStatus) {
 }
 
 function SettingsComponent() {
-	const { prefs, modelStatuses: initialModelStatuses } = Route.useLoaderData();
+	const {
+		prefs,
+		modelStatuses: initialModelStatuses,
+		importedModels: initialImportedModels,
+	} = Route.useLoaderData();
 	const [modelStatuses, setModelStatuses] = useState(initialModelStatuses);
+	const [importedModels, setImportedModels] = useState(initialImportedModels);
+	const [importPath, setImportPath] = useState("");
+	const [commandRows, setCommandRows] = useState(() =>
+		toCommandRows(prefs.voice_commands.commands)
+	);
+	const conflicts = findConflicts(fromCommandRows(commandRows));
+	const [snippetRows, setSnippetRows] = useState(() =>
+		toSnippetRows(prefs.snippets.entries)
+	);
+
+	const [profileRows, setProfileRows] = useState(() =>
+		toProfileRows(prefs.app_profiles)
+	);
+
+	const updateProfileRow = (id: number, change: Partial<ProfileRow>) => {
+		setProfileRows((rows) =>
+			rows.map((row) => (row.id === id ? { ...row, ...change } : row))
+		);
+	};
+
+	const updateProfile = (id: number, change: Partial<AppProfile>) => {
+		setProfileRows((rows) =>
+			rows.map((row) =>
+				row.id === id
+					? { ...row, profile: { ...row.profile, ...change } }
+					: row
+			)
+		);
+	};
+
+	/** Voice commands for a profile from the option picked in its select. */
+	const profileCommands = (
+		choice: string,
+		current: VoiceCommands | null
+	): VoiceCommands | null => {
+		switch (choice) {
+			case "off":
+				return { enabled: false, commands: [] };
+			case "current":
+				return { enabled: true, commands: fromCommandRows(commandRows) };
+			case "custom":
+				return current;
+			default:
+				return null;
+		}
+	};
+
+	const updateSnippetRow = (id: number, change: Partial<SnippetRow>) => {
+		setSnippetRows((rows) =>
+			rows.map((row) => (row.id === id ? { ...row, ...change } : row))
+		);
+	};
+
+	const updateCommandRow = (id: number, change: Partial<CommandRow>) => {
+		setCommandRows((rows) =>
+			rows.map((row) => (row.id === id ? { ...row, ...change } : row))
+		);
+	};
+
+	const handleExportCommands = async () => {
+		try {
+			downloadExport(
+				await api.voiceCommands.export(),
+				"json",
+				"voice-commands.json"
+			);
+		} catch (error) {
+			toast.error("Export failed", {
+				description: error instanceof Error ? error.message : String(error),
+			});
+		}
+	};
+
+	const handleImportCommands = async (file: File | undefined) => {
+		if (!file) {
+			return;
+		}
+		try {
+			const commands = await api.voiceCommands.import(await file.text());
+			setCommandRows(toCommandRows(commands));
+			toast.success("Voice commands imported");
+		} catch (error) {
+			toast.error("Import failed", {
+				description: error instanceof Error ? error.message : String(error),
+			});
+		}
+	};
+
+	const handleImport = async () => {
+		try {
+			const model = await api.models.import(importPath.trim());
+			setImportedModels((prev) => [
+				...prev.filter((m) => m.id !== model.id),
+				model,
+			]);
+			setImportPath("");
+			toast.success(`Imported ${importedModelLabel(model)}`);
+		} catch (error) {
+			toast.error("Import failed", {
+				description: error instanceof Error ? error.message : String(error),
+			});
+		}
+	};
 
 	const handleSave = async (formData: FormData) => {
 		const newPrefs: Preferences = {
@@ -84,26 +365,29 @@ function SettingsComponent() {
 			silence_rms: formData.get("silence_rms") as SilenceRms,
 			model_profile: formData.get("model_profile") as ModelProfile,
 			translate_to_english: formData.get("translate_to_english") === "on",
+			translation: {
+				mode: formData.get("translation_mode") as TranslationMode,
+				typed: formData.get("translation_typed") as TextKind,
+			},
 			typing: {
+				layout: formData.get("keyboard_layout") as KeyboardLayout,
+				method: formData.get("typing_method") as TypingMethod,
 				newline_at_end: formData.get("newline_at_end") === "on",
 				throttle_ms:
 					Number.parseInt(formData.get("throttle_ms") as string, 10) || 0,
+				clipboard_restore_ms: Number(
+					formData.get("clipboard_restore_ms") || 250
+				),
+				cancel_chord: (formData.get("cancel_chord") as string).trim(),
+				scratch_chord: (formData.get("scratch_chord") as string).trim(),
+			},
+			output: {
+				target: formData.get("output_target") as OutputTarget,
+				path: (formData.get("output_path") as string).trim(),
 			},
 			voice_commands: {
 				enabled: formData.get("voice_commands_enabled") === "on",
-				map: {
-					newline: formData.get("map_newline") as string,
-					new_paragraph: formData.get("map_new_paragraph") as string,
-					tab: formData.get("map_tab") as string,
-					period: formData.get("map_period") as string,
-					comma: formData.get("map_comma") as string,
-					colon: formData.get("map_colon") as string,
-					semicolon: formData.get("map_semicolon") as string,
-					open_quote: formData.get("map_open_quote") as string,
-					close_quote: formData.get("map_close_quote") as string,
-					backtick: formData.get("map_backtick") as string,
-					code_block: formData.get("map_code_block") as string,
-				},
+				commands: fromCommandRows(commandRows),
 			},
 			record: {
 				chunk_seconds:
@@ -112,6 +396,60 @@ function SettingsComponent() {
 					Number.parseInt(formData.get("max_hours") as string, 10) || 8,
 				max_file_gb:
 					Number.parseInt(formData.get("max_file_gb") as string, 10) || 4,
+				diarization: formData.get("diarization") === "on",
+			},
+			hallucination_filter: {
+				enabled: formData.get("hallucination_filter_enabled") === "on",
+				no_speech_threshold:
+					Number.parseFloat(formData.get("no_speech_threshold") as string) ||
+					0.6,
+				min_rms: Number.parseFloat(formData.get("min_rms") as string) || 0,
+				max_repeats:
+					Number.parseInt(formData.get("max_repeats") as string, 10) || 3,
+				blocklist: (formData.get("blocklist") as string)
+					.split("\n")
+					.map((phrase) => phrase.trim())
+					.filter((phrase) => phrase.length > 0),
+			},
+			code_mode: {
+				apps: (formData.get("code_mode_apps") as string)
+					.split("\n")
+					.map((app) => app.trim())
+					.filter((app) => app.length > 0),
+				detect_code_blocks: formData.get("detect_code_blocks") === "on",
+			},
+			disfluency_filter: {
+				enabled: formData.get("disfluency_enabled") === "on",
+				self_corrections: formData.get("self_corrections") === "on",
+				fillers: parseLanguageLists(formData.get("fillers") as string),
+				correction_cues: parseLanguageLists(
+					formData.get("correction_cues") as string
+				),
+			},
+			itn: {
+				enabled: formData.get("itn_enabled") === "on",
+				languages: (formData.get("itn_languages") as string)
+					.split(",")
+					.map((language) => language.trim())
+					.filter((language) => language.length > 0),
+			},
+			app_profiles: fromProfileRows(profileRows),
+			redaction: {
+				enabled: formData.get("redaction_enabled") === "on",
+				aws_keys: formData.get("redaction_aws_keys") as RedactionAction,
+				github_tokens: formData.get(
+					"redaction_github_tokens"
+				) as RedactionAction,
+				jwts: formData.get("redaction_jwts") as RedactionAction,
+				emails: formData.get("redaction_emails") as RedactionAction,
+				card_numbers: formData.get("redaction_card_numbers") as RedactionAction,
+				phone_numbers: formData.get(
+					"redaction_phone_numbers"
+				) as RedactionAction,
+			},
+			snippets: {
+				enabled: formData.get("snippets_enabled") === "on",
+				entries: fromSnippetRows(snippetRows),
 			},
 		};
 
@@ -213,6 +551,11 @@ function SettingsComponent() {
 												</option>
 											)
 										)}
+										{importedModels.map((model) => (
+											<option key={model.id} value={model.id}>
+												{importedModelLabel(model)}
+											</option>
+										))}
 									</select>
 								</div>
 								<div className="grid gap-2">
@@ -242,6 +585,40 @@ function SettingsComponent() {
 								/>
 								<span className="text-xs">Translate to English</span>
 							</label>
+							<div className="grid grid-cols-2 gap-4">
+								<div className="grid gap-2">
+									<Label htmlFor="translation_mode">Original text</Label>
+									<select
+										className="h-8 w-full rounded-none border border-input bg-transparent px-2.5 py-1 text-xs outline-none focus-visible:border-ring focus-visible:ring-1 focus-visible:ring-ring/50"
+										defaultValue={prefs.translation.mode}
+										id="translation_mode"
+										name="translation_mode"
+									>
+										{Object.entries(translationModeLabels).map(
+											([value, label]) => (
+												<option key={value} value={value}>
+													{label}
+												</option>
+											)
+										)}
+									</select>
+								</div>
+								<div className="grid gap-2">
+									<Label htmlFor="translation_typed">Type</Label>
+									<select
+										className="h-8 w-full rounded-none border border-input bg-transparent px-2.5 py-1 text-xs outline-none focus-visible:border-ring focus-visible:ring-1 focus-visible:ring-ring/50"
+										defaultValue={prefs.translation.typed}
+										id="translation_typed"
+										name="translation_typed"
+									>
+										{Object.entries(textKindLabels).map(([value, label]) => (
+											<option key={value} value={value}>
+												{label}
+											</option>
+										))}
+									</select>
+								</div>
+							</div>
 						</CardContent>
 					</Card>
 
@@ -268,8 +645,9 @@ function SettingsComponent() {
 									<div className="grid gap-1">
 										<div className="flex items-center gap-2">
 											<span className="font-medium text-sm">
-												{modelProfileLabels[status.profile as ModelProfile] ??
-													status.profile}
+												{modelProfileLabels[
+													status.profile as BuiltinModelProfile
+												] ?? status.profile}
 											</span>
 											{getModelStatusBadge(status)}
 										</div>
@@ -311,6 +689,38 @@ function SettingsComponent() {
 									</Button>
 								</div>
 							))}
+							{importedModels.map((model) => (
+								<div className="grid gap-1 rounded-md border p-3" key={model.id}>
+									<span className="font-medium text-sm">
+										{importedModelLabel(model)}
+									</span>
+									<span className="text-muted-foreground text-xs">
+										{model.filename}
+									</span>
+								</div>
+							))}
+							<div className="grid grid-cols-[1fr_auto] items-end gap-3">
+								<div className="grid gap-2">
+									<Label htmlFor="import_model_path">
+										Import GGML/GGUF model
+									</Label>
+									<Input
+										id="import_model_path"
+										onChange={(e) => setImportPath(e.target.value)}
+										placeholder="/path/to/ggml-model.bin"
+										value={importPath}
+									/>
+								</div>
+								<Button
+									disabled={!importPath.trim()}
+									onClick={handleImport}
+									size="sm"
+									type="button"
+									variant="outline"
+								>
+									Import
+								</Button>
+							</div>
 						</CardContent>
 					</Card>
 
@@ -320,6 +730,40 @@ function SettingsComponent() {
 						</CardHeader>
 						<CardContent className="grid gap-4">
 							<div className="grid grid-cols-2 gap-4">
+								<div className="grid gap-2">
+									<Label htmlFor="typing_method">Method</Label>
+									<select
+										className={selectClassName}
+										defaultValue={prefs.typing.method}
+										id="typing_method"
+										name="typing_method"
+									>
+										{Object.entries(typingMethodLabels).map(
+											([value, label]) => (
+												<option key={value} value={value}>
+													{label}
+												</option>
+											)
+										)}
+									</select>
+								</div>
+								<div className="grid gap-2">
+									<Label htmlFor="keyboard_layout">Keyboard layout</Label>
+									<select
+										className={selectClassName}
+										defaultValue={prefs.typing.layout}
+										id="keyboard_layout"
+										name="keyboard_layout"
+									>
+										{Object.entries(keyboardLayoutLabels).map(
+											([value, label]) => (
+												<option key={value} value={value}>
+													{label}
+												</option>
+											)
+										)}
+									</select>
+								</div>
 								<div className="grid gap-2">
 									<Label htmlFor="throttle_ms">Throttle (ms)</Label>
 									<Input
@@ -331,6 +775,37 @@ function SettingsComponent() {
 										type="number"
 									/>
 								</div>
+								<div className="grid gap-2">
+									<Label htmlFor="clipboard_restore_ms">
+										Clipboard restore delay (ms)
+									</Label>
+									<Input
+										defaultValue={prefs.typing.clipboard_restore_ms}
+										id="clipboard_restore_ms"
+										max="5000"
+										min="0"
+										name="clipboard_restore_ms"
+										type="number"
+									/>
+								</div>
+								<div className="grid gap-2">
+									<Label htmlFor="cancel_chord">Cancel typing with</Label>
+									<Input
+										defaultValue={prefs.typing.cancel_chord}
+										id="cancel_chord"
+										name="cancel_chord"
+										placeholder="escape"
+									/>
+								</div>
+								<div className="grid gap-2">
+									<Label htmlFor="scratch_chord">Remove last dictation with</Label>
+									<Input
+										defaultValue={prefs.typing.scratch_chord}
+										id="scratch_chord"
+										name="scratch_chord"
+										placeholder="ctrl+alt+z"
+									/>
+								</div>
 							</div>
 							<label
 								className="flex items-center gap-2"
@@ -343,6 +818,34 @@ function SettingsComponent() {
 								/>
 								<span className="text-xs">Append newline at end</span>
 							</label>
+							<div className="grid grid-cols-2 gap-4">
+								<div className="grid gap-2">
+									<Label htmlFor="output_target">Send dictation to</Label>
+									<select
+										className={selectClassName}
+										defaultValue={prefs.output.target}
+										id="output_target"
+										name="output_target"
+									>
+										{Object.entries(outputTargetLabels).map(
+											([value, label]) => (
+												<option key={value} value={value}>
+													{label}
+												</option>
+											)
+										)}
+									</select>
+								</div>
+								<div className="grid gap-2">
+									<Label htmlFor="output_path">File or socket path</Label>
+									<Input
+										defaultValue={prefs.output.path}
+										id="output_path"
+										name="output_path"
+										placeholder="/tmp/dictation.txt"
+									/>
+								</div>
+							</div>
 						</CardContent>
 					</Card>
 
@@ -364,117 +867,639 @@ function SettingsComponent() {
 							</CardAction>
 						</CardHeader>
 						<CardContent>
-							<div className="grid grid-cols-2 gap-x-4 gap-y-2">
-								<div className="grid gap-1">
-									<Label className="text-xs" htmlFor="map_newline">
-										New line
-									</Label>
-									<Input
-										defaultValue={prefs.voice_commands.map.newline}
-										id="map_newline"
-										name="map_newline"
-									/>
-								</div>
-								<div className="grid gap-1">
-									<Label className="text-xs" htmlFor="map_new_paragraph">
-										New paragraph
-									</Label>
-									<Input
-										defaultValue={prefs.voice_commands.map.new_paragraph}
-										id="map_new_paragraph"
-										name="map_new_paragraph"
-									/>
-								</div>
-								<div className="grid gap-1">
-									<Label className="text-xs" htmlFor="map_tab">
-										Tab
-									</Label>
-									<Input
-										defaultValue={prefs.voice_commands.map.tab}
-										id="map_tab"
-										name="map_tab"
-									/>
-								</div>
-								<div className="grid gap-1">
-									<Label className="text-xs" htmlFor="map_period">
-										Period
-									</Label>
-									<Input
-										defaultValue={prefs.voice_commands.map.period}
-										id="map_period"
-										name="map_period"
-									/>
-								</div>
-								<div className="grid gap-1">
-									<Label className="text-xs" htmlFor="map_comma">
-										Comma
-									</Label>
-									<Input
-										defaultValue={prefs.voice_commands.map.comma}
-										id="map_comma"
-										name="map_comma"
-									/>
-								</div>
-								<div className="grid gap-1">
-									<Label className="text-xs" htmlFor="map_colon">
-										Colon
-									</Label>
-									<Input
-										defaultValue={prefs.voice_commands.map.colon}
-										id="map_colon"
-										name="map_colon"
-									/>
-								</div>
-								<div className="grid gap-1">
-									<Label className="text-xs" htmlFor="map_semicolon">
-										Semicolon
+							<div className="grid gap-2">
+								{commandRows.map((row) => {
+									const conflicting = parsePhrases(row.phrases).some((phrase) =>
+										conflicts.has(phraseKey(phrase))
+									);
+									return (
+										<div
+											className="grid grid-cols-[2fr_1fr_2fr_auto] items-center gap-2"
+											key={row.id}
+										>
+											<Input
+												aria-invalid={conflicting || undefined}
+												aria-label="Spoken phrases"
+												onChange={(e) =>
+													updateCommandRow(row.id, { phrases: e.target.value })
+												}
+												placeholder="phrase, another phrase"
+												value={row.phrases}
+											/>
+											<select
+												aria-label="Action type"
+												className={selectClassName}
+												onChange={(e) =>
+													updateCommandRow(row.id, {
+														action: defaultAction(
+															e.target.value as CommandAction["type"]
+														),
+													})
+												}
+												value={row.action.type}
+											>
+												{Object.entries(commandActionLabels).map(
+													([type, label]) => (
+														<option key={type} value={type}>
+															{label}
+														</option>
+													)
+												)}
+											</select>
+											{row.action.type === "builtin" ? (
+												<select
+													aria-label="Built-in command"
+													className={selectClassName}
+													onChange={(e) =>
+														updateCommandRow(row.id, {
+															action: {
+																type: "builtin",
+																value: e.target.value as BuiltinCommand,
+															},
+														})
+													}
+													value={row.action.value}
+												>
+													{Object.entries(BUILTIN_COMMAND_LABELS).map(
+														([value, label]) => (
+															<option key={value} value={value}>
+																{label}
+															</option>
+														)
+													)}
+												</select>
+											) : (
+												<Input
+													aria-label={
+														row.action.type === "keys" ? "Key sequence" : "Text"
+													}
+													onChange={(e) => {
+														const type = row.action.type;
+														updateCommandRow(row.id, {
+															action:
+																type === "keys"
+																	? { type, value: e.target.value }
+																	: { type: "text", value: e.target.value },
+														});
+													}}
+													placeholder={
+														row.action.type === "keys" ? "cmd+shift+z" : "=>"
+													}
+													value={row.action.value}
+												/>
+											)}
+											<Button
+												onClick={() =>
+													setCommandRows((rows) =>
+														rows.filter((r) => r.id !== row.id)
+													)
+												}
+												size="sm"
+												type="button"
+												variant="ghost"
+											>
+												Remove
+											</Button>
+										</div>
+									);
+								})}
+								{conflicts.size > 0 && (
+									<p className="text-destructive text-xs">
+										Used by more than one command: {[...conflicts].join(", ")}
+									</p>
+								)}
+								<div className="flex flex-wrap items-center gap-2">
+									<Button
+										onClick={() =>
+											setCommandRows((rows) => [
+												...rows,
+												{
+													id: nextCommandRowId++,
+													phrases: "",
+													action: defaultAction("text"),
+												},
+											])
+										}
+										size="sm"
+										type="button"
+										variant="outline"
+									>
+										Add command
+									</Button>
+									<Button
+										onClick={handleExportCommands}
+										size="sm"
+										type="button"
+										variant="outline"
+									>
+										Export
+									</Button>
+									<Label
+										className="cursor-pointer text-xs underline"
+										htmlFor="import_voice_commands"
+									>
+										Import…
 									</Label>
-									<Input
-										defaultValue={prefs.voice_commands.map.semicolon}
-										id="map_semicolon"
-										name="map_semicolon"
+									<input
+										accept="application/json,.json"
+										className="hidden"
+										id="import_voice_commands"
+										onChange={(e) => {
+											handleImportCommands(e.target.files?.[0]);
+											e.target.value = "";
+										}}
+										type="file"
 									/>
 								</div>
-								<div className="grid gap-1">
-									<Label className="text-xs" htmlFor="map_open_quote">
-										Open quote
-									</Label>
-									<Input
-										defaultValue={prefs.voice_commands.map.open_quote}
-										id="map_open_quote"
-										name="map_open_quote"
+							</div>
+						</CardContent>
+					</Card>
+
+					<Card>
+						<CardHeader>
+							<CardTitle>Code Mode</CardTitle>
+						</CardHeader>
+						<CardContent className="grid gap-4">
+							<p className="text-muted-foreground text-xs">
+								Turns "camel case user id" into userId and spoken symbols like
+								"fat arrow" or "open paren" into code.
+							</p>
+							<div className="grid gap-2">
+								<Label htmlFor="code_mode_apps">
+									Always on in these apps (one per line)
+								</Label>
+								<textarea
+									className="min-h-16 w-full rounded-none border border-input bg-transparent px-2.5 py-1 text-xs outline-none focus-visible:border-ring focus-visible:ring-1 focus-visible:ring-ring/50 dark:bg-input/30"
+									defaultValue={prefs.code_mode.apps.join("\n")}
+									id="code_mode_apps"
+									name="code_mode_apps"
+									placeholder="Code"
+								/>
+							</div>
+							<label
+								className="flex items-center gap-2"
+								htmlFor="detect_code_blocks"
+							>
+								<Checkbox
+									defaultChecked={prefs.code_mode.detect_code_blocks}
+									id="detect_code_blocks"
+									name="detect_code_blocks"
+								/>
+								<span className="text-xs">
+									Also when the dictation looks like code
+								</span>
+							</label>
+						</CardContent>
+					</Card>
+
+					<Card>
+						<CardHeader>
+							<CardTitle>Filler Words</CardTitle>
+						</CardHeader>
+						<CardContent className="grid gap-4">
+							<p className="text-muted-foreground text-xs">
+								Removes fillers like "um" and stutters like "the the" before
+								typing. The text as spoken stays in the logs.
+							</p>
+							<label
+								className="flex items-center gap-2"
+								htmlFor="disfluency_enabled"
+							>
+								<Checkbox
+									defaultChecked={prefs.disfluency_filter.enabled}
+									id="disfluency_enabled"
+									name="disfluency_enabled"
+								/>
+								<span className="text-xs">
+									Remove filler words and stutters
+								</span>
+							</label>
+							<div className="grid gap-2">
+								<Label htmlFor="fillers">
+									Fillers (one language per line, e.g. "en: um, uh")
+								</Label>
+								<textarea
+									className="min-h-16 w-full rounded-none border border-input bg-transparent px-2.5 py-1 text-xs outline-none focus-visible:border-ring focus-visible:ring-1 focus-visible:ring-ring/50 dark:bg-input/30"
+									defaultValue={formatLanguageLists(
+										prefs.disfluency_filter.fillers
+									)}
+									id="fillers"
+									name="fillers"
+								/>
+							</div>
+							<label
+								className="flex items-center gap-2"
+								htmlFor="self_corrections"
+							>
+								<Checkbox
+									defaultChecked={prefs.disfluency_filter.self_corrections}
+									id="self_corrections"
+									name="self_corrections"
+								/>
+								<span className="text-xs">
+									Drop words taken back with "no wait" or "I mean"
+								</span>
+							</label>
+							<div className="grid gap-2">
+								<Label htmlFor="correction_cues">Correction phrases</Label>
+								<textarea
+									className="min-h-16 w-full rounded-none border border-input bg-transparent px-2.5 py-1 text-xs outline-none focus-visible:border-ring focus-visible:ring-1 focus-visible:ring-ring/50 dark:bg-input/30"
+									defaultValue={formatLanguageLists(
+										prefs.disfluency_filter.correction_cues
+									)}
+									id="correction_cues"
+									name="correction_cues"
+								/>
+							</div>
+						</CardContent>
+					</Card>
+
+					<Card>
+						<CardHeader>
+							<CardTitle>Numbers and Dates</CardTitle>
+						</CardHeader>
+						<CardContent className="grid gap-4">
+							<p className="text-muted-foreground text-xs">
+								Writes "twenty three point five percent" as 23.5% and "the fifth
+								of march at three pm" as March 5 at 3 PM.
+							</p>
+							<label
+								className="flex items-center gap-2"
+								htmlFor="itn_enabled"
+							>
+								<Checkbox
+									defaultChecked={prefs.itn.enabled}
+									id="itn_enabled"
+									name="itn_enabled"
+								/>
+								<span className="text-xs">Write spoken numbers as digits</span>
+							</label>
+							<div className="grid gap-2">
+								<Label htmlFor="itn_languages">Languages</Label>
+								<Input
+									defaultValue={prefs.itn.languages.join(", ")}
+									id="itn_languages"
+									name="itn_languages"
+									placeholder="en"
+								/>
+								<p className="text-muted-foreground text-xs">
+									Comma-separated language codes. Only English is supported so
+									far.
+								</p>
+							</div>
+						</CardContent>
+					</Card>
+
+					<Card>
+						<CardHeader>
+							<CardTitle>Privacy</CardTitle>
+							<CardAction>
+								<label
+									className="flex items-center gap-2"
+									htmlFor="redaction_enabled"
+								>
+									<Checkbox
+										defaultChecked={prefs.redaction.enabled}
+										id="redaction_enabled"
+										name="redaction_enabled"
 									/>
-								</div>
-								<div className="grid gap-1">
-									<Label className="text-xs" htmlFor="map_close_quote">
-										Close quote
-									</Label>
-									<Input
-										defaultValue={prefs.voice_commands.map.close_quote}
-										id="map_close_quote"
-										name="map_close_quote"
+									<span className="text-xs">Enabled</span>
+								</label>
+							</CardAction>
+						</CardHeader>
+						<CardContent className="grid gap-4">
+							<p className="text-muted-foreground text-xs">
+								Masked values are typed as dictated but stored as a placeholder.
+								Blocked values are neither typed nor stored.
+							</p>
+							<div className="grid grid-cols-2 gap-4">
+								{Object.entries(redactedValueLabels).map(([value, label]) => (
+									<div className="grid gap-2" key={value}>
+										<Label htmlFor={`redaction_${value}`}>{label}</Label>
+										<select
+											className={selectClassName}
+											defaultValue={prefs.redaction[value as RedactedValue]}
+											id={`redaction_${value}`}
+											name={`redaction_${value}`}
+										>
+											{Object.entries(redactionActionLabels).map(
+												([action, actionLabel]) => (
+													<option key={action} value={action}>
+														{actionLabel}
+													</option>
+												)
+											)}
+										</select>
+									</div>
+								))}
+							</div>
+						</CardContent>
+					</Card>
+
+					<Card>
+						<CardHeader>
+							<CardTitle>App Profiles</CardTitle>
+						</CardHeader>
+						<CardContent className="grid gap-4">
+							<p className="text-muted-foreground text-xs">
+								Sessions started while a listed app is frontmost use its
+								profile. Settings left on Default follow the rest of this page.
+							</p>
+							{profileRows.map((row) => {
+								const { profile } = row;
+								const choiceSelect = (
+									label: string,
+									value: boolean | null,
+									key: "code_mode" | "newline_at_end" | "translate_to_english"
+								) => (
+									<div className="grid gap-1">
+										<span className="text-xs">{label}</span>
+										<select
+											aria-label={label}
+											className={selectClassName}
+											onChange={(e) => {
+												const change: Partial<AppProfile> = {};
+												change[key] = parseOverrideChoice(e.target.value);
+												updateProfile(row.id, change);
+											}}
+											value={overrideChoice(value)}
+										>
+											{Object.entries(overrideLabels).map(([choice, text]) => (
+												<option key={choice} value={choice}>
+													{text}
+												</option>
+											))}
+										</select>
+									</div>
+								);
+								return (
+									<div className="grid gap-2 border p-2" key={row.id}>
+										<div className="grid grid-cols-[1fr_2fr_auto] items-center gap-2">
+											<Input
+												aria-label="Profile name"
+												onChange={(e) =>
+													updateProfile(row.id, { name: e.target.value })
+												}
+												placeholder="Terminal"
+												value={profile.name}
+											/>
+											<Input
+												aria-label="Apps"
+												onChange={(e) =>
+													updateProfileRow(row.id, { apps: e.target.value })
+												}
+												placeholder="Terminal, com.googlecode.iterm2"
+												value={row.apps}
+											/>
+											<Button
+												onClick={() =>
+													setProfileRows((rows) =>
+														rows.filter((r) => r.id !== row.id)
+													)
+												}
+												size="sm"
+												type="button"
+												variant="ghost"
+											>
+												Remove
+											</Button>
+										</div>
+										<div className="grid grid-cols-4 gap-2">
+											<div className="grid gap-1">
+												<span className="text-xs">Typing</span>
+												<select
+													aria-label="Typing method"
+													className={selectClassName}
+													onChange={(e) =>
+														updateProfile(row.id, {
+															typing_method:
+																e.target.value === "default"
+																	? null
+																	: (e.target.value as TypingMethod),
+														})
+													}
+													value={profile.typing_method ?? "default"}
+												>
+													<option value="default">Default</option>
+													{Object.entries(typingMethodLabels).map(
+														([value, label]) => (
+															<option key={value} value={value}>
+																{label}
+															</option>
+														)
+													)}
+												</select>
+											</div>
+											<div className="grid gap-1">
+												<span className="text-xs">Throttle (ms)</span>
+												<Input
+													aria-label="Throttle (ms)"
+													max="1000"
+													min="0"
+													onChange={(e) =>
+														updateProfileRow(row.id, {
+															throttle: e.target.value,
+														})
+													}
+													placeholder="Default"
+													type="number"
+													value={row.throttle}
+												/>
+											</div>
+											{choiceSelect(
+												"Newline at end",
+												profile.newline_at_end,
+												"newline_at_end"
+											)}
+											{choiceSelect(
+												"Code mode",
+												profile.code_mode,
+												"code_mode"
+											)}
+											<div className="grid gap-1">
+												<span className="text-xs">Model</span>
+												<select
+													aria-label="Model"
+													className={selectClassName}
+													onChange={(e) =>
+														updateProfile(row.id, {
+															model_profile:
+																e.target.value === "default"
+																	? null
+																	: (e.target.value as ModelProfile),
+														})
+													}
+													value={profile.model_profile ?? "default"}
+												>
+													<option value="default">Default</option>
+													{Object.entries(modelProfileLabels).map(
+														([value, label]) => (
+															<option key={value} value={value}>
+																{label}
+															</option>
+														)
+													)}
+													{importedModels.map((model) => (
+														<option key={model.id} value={model.id}>
+															{importedModelLabel(model)}
+														</option>
+													))}
+												</select>
+											</div>
+											{choiceSelect(
+												"Translate to English",
+												profile.translate_to_english,
+												"translate_to_english"
+											)}
+											<div className="grid gap-1">
+												<span className="text-xs">Voice commands</span>
+												<select
+													aria-label="Voice commands"
+													className={selectClassName}
+													onChange={(e) =>
+														updateProfile(row.id, {
+															voice_commands: profileCommands(
+																e.target.value,
+																profile.voice_commands
+															),
+														})
+													}
+													value={
+														profile.voice_commands === null
+															? "default"
+															: profile.voice_commands.enabled
+																? "custom"
+																: "off"
+													}
+												>
+													<option value="default">Default</option>
+													<option value="current">Copy of current list</option>
+													{profile.voice_commands?.enabled && (
+														<option value="custom">Own list</option>
+													)}
+													<option value="off">Off</option>
+												</select>
+											</div>
+										</div>
+										{describeOverrides(profile).length === 0 && (
+											<p className="text-muted-foreground text-xs">
+												This profile uses the default settings.
+											</p>
+										)}
+									</div>
+								);
+							})}
+							<div>
+								<Button
+									onClick={() =>
+										setProfileRows((rows) => [
+											...rows,
+											{
+												id: nextProfileRowId++,
+												apps: "",
+												throttle: "",
+												profile: emptyProfile(),
+											},
+										])
+									}
+									size="sm"
+									type="button"
+									variant="outline"
+								>
+									Add profile
+								</Button>
+							</div>
+						</CardContent>
+					</Card>
+
+					<Card>
+						<CardHeader>
+							<CardTitle>Snippets</CardTitle>
+							<CardAction>
+								<label
+									className="flex items-center gap-2"
+									htmlFor="snippets_enabled"
+								>
+									<Checkbox
+										defaultChecked={prefs.snippets.enabled}
+										id="snippets_enabled"
+										name="snippets_enabled"
 									/>
-								</div>
-								<div className="grid gap-1">
-									<Label className="text-xs" htmlFor="map_backtick">
-										Backtick
-									</Label>
+									<span className="text-xs">Enabled</span>
+								</label>
+							</CardAction>
+						</CardHeader>
+						<CardContent className="grid gap-2">
+							<p className="text-muted-foreground text-xs">
+								Spoken triggers expand into longer text. Expansions can use{" "}
+								{"{date}"}, {"{time}"}, {"{clipboard}"} and {"{app}"}; regex
+								triggers can refer to their groups as $1.
+							</p>
+							{snippetRows.map((row) => (
+								<div
+									className="grid grid-cols-[1fr_2fr_auto_auto] items-start gap-2"
+									key={row.id}
+								>
 									<Input
-										defaultValue={prefs.voice_commands.map.backtick}
-										id="map_backtick"
-										name="map_backtick"
+										aria-label="Trigger"
+										onChange={(e) =>
+											updateSnippetRow(row.id, { trigger: e.target.value })
+										}
+										placeholder="my email"
+										value={row.trigger}
 									/>
-								</div>
-								<div className="grid gap-1">
-									<Label className="text-xs" htmlFor="map_code_block">
-										Code block
-									</Label>
-									<Input
-										defaultValue={prefs.voice_commands.map.code_block}
-										id="map_code_block"
-										name="map_code_block"
+									<textarea
+										aria-label="Expansion"
+										className="min-h-8 w-full rounded-none border border-input bg-transparent px-2.5 py-1 text-xs outline-none focus-visible:border-ring focus-visible:ring-1 focus-visible:ring-ring/50 dark:bg-input/30"
+										onChange={(e) =>
+											updateSnippetRow(row.id, { expansion: e.target.value })
+										}
+										placeholder="name@example.com"
+										rows={1}
+										value={row.expansion}
 									/>
+									<label className="flex h-8 items-center gap-2">
+										<Checkbox
+											checked={row.regex}
+											onCheckedChange={(checked) =>
+												updateSnippetRow(row.id, { regex: checked === true })
+											}
+										/>
+										<span className="text-xs">Regex</span>
+									</label>
+									<Button
+										onClick={() =>
+											setSnippetRows((rows) =>
+												rows.filter((r) => r.id !== row.id)
+											)
+										}
+										size="sm"
+										type="button"
+										variant="ghost"
+									>
+										Remove
+									</Button>
 								</div>
+							))}
+							<div>
+								<Button
+									onClick={() =>
+										setSnippetRows((rows) => [
+											...rows,
+											{
+												id: nextSnippetRowId++,
+												trigger: "",
+												expansion: "",
+												regex: false,
+											},
+										])
+									}
+									size="sm"
+									type="button"
+									variant="outline"
+								>
+									Add snippet
+								</Button>
 							</div>
 						</CardContent>
 					</Card>
@@ -519,6 +1544,84 @@ function SettingsComponent() {
 									/>
 								</div>
 							</div>
+							<label className="flex items-center gap-2" htmlFor="diarization">
+								<Checkbox
+									defaultChecked={prefs.record.diarization}
+									id="diarization"
+									name="diarization"
+								/>
+								<span className="text-xs">Label speakers in recordings</span>
+							</label>
+						</CardContent>
+					</Card>
+
+					<Card>
+						<CardHeader>
+							<CardTitle>Hallucination Filter</CardTitle>
+							<CardAction>
+								<label
+									className="flex items-center gap-2"
+									htmlFor="hallucination_filter_enabled"
+								>
+									<Checkbox
+										defaultChecked={prefs.hallucination_filter.enabled}
+										id="hallucination_filter_enabled"
+										name="hallucination_filter_enabled"
+									/>
+									<span className="text-xs">Enabled</span>
+								</label>
+							</CardAction>
+						</CardHeader>
+						<CardContent className="grid gap-4">
+							<div className="grid grid-cols-3 gap-4">
+								<div className="grid gap-2">
+									<Label htmlFor="no_speech_threshold">
+										No-speech threshold
+									</Label>
+									<Input
+										defaultValue={prefs.hallucination_filter.no_speech_threshold}
+										id="no_speech_threshold"
+										max="1"
+										min="0"
+										name="no_speech_threshold"
+										step="0.05"
+										type="number"
+									/>
+								</div>
+								<div className="grid gap-2">
+									<Label htmlFor="min_rms">Min energy (RMS)</Label>
+									<Input
+										defaultValue={prefs.hallucination_filter.min_rms}
+										id="min_rms"
+										max="0.1"
+										min="0"
+										name="min_rms"
+										step="0.001"
+										type="number"
+									/>
+								</div>
+								<div className="grid gap-2">
+									<Label htmlFor="max_repeats">Max repeats</Label>
+									<Input
+										defaultValue={prefs.hallucination_filter.max_repeats}
+										id="max_repeats"
+										min="1"
+										name="max_repeats"
+										type="number"
+									/>
+								</div>
+							</div>
+							<div className="grid gap-2">
+								<Label htmlFor="blocklist">
+									Blocklist (one phrase per line)
+								</Label>
+								<textarea
+									className="min-h-24 w-full rounded-none border border-input bg-transparent px-2.5 py-1 text-xs outline-none focus-visible:border-ring focus-visible:ring-1 focus-visible:ring-ring/50 dark:bg-input/30"
+									defaultValue={prefs.hallucination_filter.blocklist.join("\n")}
+									id="blocklist"
+									name="blocklist"
+								/>
+							</div>
 						</CardContent>
 					</Card>
 
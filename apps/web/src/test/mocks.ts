@@ -19,29 +19,98 @@ export const mockPreferences: Preferences = {
 		right_chord: true,
 	},
 	typing: {
+		cancel_chord: "escape",
+		clipboard_restore_ms: 250,
+		layout: "us",
+		method: "keystroke",
 		newline_at_end: true,
+		scratch_chord: "",
 		throttle_ms: 0,
 	},
+	output: {
+		target: "type",
+		path: "",
+	},
 	voice_commands: {
 		enabled: true,
-		map: {
-			newline: "Enter",
-			new_paragraph: "Enter Enter",
-			tab: "Tab",
-			period: ".",
-			comma: ",",
-			colon: ":",
-			semicolon: ";",
-			open_quote: '"',
-			close_quote: '"',
-			backtick: "`",
-			code_block: "```",
-		},
+		commands: [
+			{ phrases: ["newline"], action: { type: "builtin", value: "newline" } },
+			{
+				phrases: ["new paragraph"],
+				action: { type: "builtin", value: "new_paragraph" },
+			},
+			{ phrases: ["tab"], action: { type: "builtin", value: "tab" } },
+			{ phrases: ["period"], action: { type: "builtin", value: "period" } },
+			{ phrases: ["comma"], action: { type: "builtin", value: "comma" } },
+			{ phrases: ["arrow", "fat arrow"], action: { type: "text", value: "=>" } },
+			{ phrases: ["submit"], action: { type: "keys", value: "enter" } },
+			{
+				phrases: ["scratch that"],
+				action: { type: "builtin", value: "scratch_that" },
+			},
+			{ phrases: ["undo"], action: { type: "keys", value: "cmd+z" } },
+		],
 	},
 	record: {
 		chunk_seconds: 60,
 		max_hours: 8,
 		max_file_gb: 4,
+		diarization: true,
+	},
+	hallucination_filter: {
+		enabled: true,
+		no_speech_threshold: 0.6,
+		min_rms: 0.003,
+		max_repeats: 3,
+		blocklist: ["thanks for watching", "[BLANK_AUDIO]"],
+	},
+	translation: {
+		mode: "translation_only",
+		typed: "translation",
+	},
+	code_mode: {
+		apps: ["Code", "Terminal"],
+		detect_code_blocks: true,
+	},
+	itn: {
+		enabled: true,
+		languages: ["en"],
+	},
+	disfluency_filter: {
+		enabled: true,
+		self_corrections: false,
+		fillers: { en: ["um", "uh", "you know"], fr: ["euh"] },
+		correction_cues: { en: ["no wait", "I mean"] },
+	},
+	app_profiles: [
+		{
+			apps: ["Terminal", "com.googlecode.iterm2"],
+			code_mode: true,
+			model_profile: null,
+			name: "Shell",
+			newline_at_end: false,
+			throttle_ms: null,
+			translate_to_english: false,
+			translation: null,
+			typing_method: "clipboard",
+			voice_commands: null,
+		},
+	],
+	redaction: {
+		enabled: true,
+		aws_keys: "mask",
+		github_tokens: "mask",
+		jwts: "mask",
+		emails: "off",
+		card_numbers: "mask",
+		phone_numbers: "block",
+	},
+	snippets: {
+		enabled: true,
+		entries: [
+			{ trigger: "todo marker", expansion: "// TODO:", regex: false },
+			{ trigger: "my email", expansion: "me@example.com", regex: false },
+		],
 	},
 };
 
@@ -56,6 +125,7 @@ export const mockSession: Session = {
 	model_profile: "small.en",
 	translated: false,
 	app_name: "Terminal",
+	profile: "Shell",
 };
 
 export const mockEntry: Entry = {
@@ -66,6 +136,14 @@ export const mockEntry: Entry = {
 	ended_at: Date.now() - 55_000,
 	text: "Hello world",
 	typed: true,
+	language: "en",
+	language_probability: 0.98,
+	speaker: null,
+	original_text: null,
+	retracted: false,
+	translated_text: null,
+	typed_chars: null,
+	unfiltered_text: null,
 };
 
 export const createMockApi = () => ({
@@ -73,6 +151,10 @@ export const createMockApi = () => ({
 		get: vi.fn().mockResolvedValue(mockPreferences),
 		update: vi.fn().mockResolvedValue(undefined),
 	},
+	voiceCommands: {
+		export: vi.fn().mockResolvedValue('{"version":1,"commands":[]}'),
+		import: vi.fn().mockResolvedValue(mockPreferences.voice_commands.commands),
+	},
 	sessions: {
 		getAll: vi.fn().mockResolvedValue([mockSession]),
 		create: vi.fn().mockResolvedValue(mockSession),
@@ -89,6 +171,17 @@ export const createMockApi = () => ({
 		delete: vi.fn().mockResolvedValue(true),
 		search: vi.fn().mockResolvedValue([mockEntry]),
 	},
+	engine: {
+		getStatus: vi.fn().mockResolvedValue({
+			state: "ready",
+			profile: "small.en",
+		}),
+		onStatus: vi.fn().mockResolvedValue(() => undefined),
+	},
+	speakers: {
+		getBySession: vi.fn().mockResolvedValue([]),
+		rename: vi.fn().mockResolvedValue(undefined),
+	},
 	dirs: {
 		config: vi.fn().mockResolvedValue("/tmp/config"),
 		data: vi.fn().mockResolvedValue("/tmp/data"),